@@ -0,0 +1,58 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use crate::xbin::chunks_to_bytes;
+use imagequant::RGBA;
+use std::io::Write;
+use std::path::Path;
+
+/// ADF screens are always 80 columns wide.
+pub const COLUMNS: usize = 80;
+
+// The EGA palette registers the 16 text attributes select by default.
+const REGISTERS: [usize; 16] = [0, 1, 2, 3, 4, 5, 20, 7, 56, 57, 58, 59, 60, 61, 62, 63];
+
+/// Extensions written as Artworx ADF rather than XBIN.
+pub fn is_adf_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("adf"))
+}
+
+/// Writes an Artworx ADF: a version byte, the 64 EGA palette registers with just
+/// the 16 the attributes use filled in from the 6-bit `palette`, the 8x16
+/// font, then the cells. Rows narrower than 80 columns are padded with blanks
+/// and wider ones cut short.
+pub fn save_adf(
+    path: impl AsRef<Path>,
+    columns: usize,
+    font: &Font,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+) {
+    assert!(
+        font.height == 16,
+        "ADF fonts are 16 rows high, not {}",
+        font.height
+    );
+    let mut registers = [0u8; 64 * 3];
+    for (register, color) in REGISTERS.iter().zip(palette.iter()) {
+        registers[register * 3..register * 3 + 3].copy_from_slice(&[color.r, color.g, color.b]);
+    }
+    let blank = Chunk {
+        fg: 0,
+        bg: 0,
+        codepoint: 0x20,
+    };
+    let cells: Vec<Chunk> = chunks
+        .chunks(columns.max(1))
+        .flat_map(|row| {
+            let mut row = row.to_vec();
+            row.resize(COLUMNS, blank);
+            row
+        })
+        .collect();
+    let mut file = std::fs::File::create(path).unwrap();
+    file.write_all(&[1]).unwrap();
+    file.write_all(&registers).unwrap();
+    file.write_all(&font.glyphs[..256 * 16]).unwrap();
+    file.write_all(&chunks_to_bytes(&cells)).unwrap();
+}
@@ -0,0 +1,532 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use crate::palette;
+use imagequant::RGBA;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// How a UTF-8 ANSI file gives its colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Colors {
+    /// The 16 standard colors, which the palette is taken to be.
+    Ansi16,
+    /// The xterm-256 colors nearest the palette's.
+    Xterm256,
+    /// The palette's own RGB values.
+    Truecolor,
+}
+
+/// Writes the cells as UTF-8 text with SGR colors, only emitting a color
+/// sequence when it changes and resetting at the end of every line, so the
+/// file can be shown by any terminal whatever its width.
+pub fn save_utf8_ansi(
+    path: impl AsRef<Path>,
+    columns: usize,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+    to_char: impl Fn(u8) -> char,
+    colors: Colors,
+) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    write_utf8_ansi(&mut file, columns, palette, chunks, to_char, colors);
+}
+
+/// Writes UTF-8 ANSI as `save_utf8_ansi` does, to any writer.
+pub fn write_utf8_ansi(
+    file: &mut impl Write,
+    columns: usize,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+    to_char: impl Fn(u8) -> char,
+    colors: Colors,
+) {
+    let canonical = canonical(palette, colors);
+    for row in chunks.chunks(columns.max(1)) {
+        let mut current = None;
+        for chunk in row {
+            let wanted = Attributes {
+                fg: canonical[chunk.fg as usize],
+                bg: canonical[chunk.bg as usize],
+            };
+            if current != Some(wanted) {
+                file.write_all(utf8_sgr(palette, colors, current, wanted).as_bytes())
+                    .unwrap();
+                current = Some(wanted);
+            }
+            write!(file, "{}", to_char(chunk.codepoint)).unwrap();
+        }
+        file.write_all(b"\x1b[0m\n").unwrap();
+    }
+}
+
+// Entries shown as the same color share one index, so that a change between
+// them doesn't cost a sequence.
+fn canonical(palette: &[RGBA], colors: Colors) -> Vec<u8> {
+    let shown = |index: usize| match colors {
+        Colors::Ansi16 => (index as u8, 0, 0),
+        Colors::Xterm256 => (palette::nearest_xterm_256(&palette[index]), 0, 0),
+        Colors::Truecolor => (palette[index].r, palette[index].g, palette[index].b),
+    };
+    (0..palette.len())
+        .map(|index| {
+            (0..=index)
+                .find(|other| shown(*other) == shown(index))
+                .unwrap() as u8
+        })
+        .collect()
+}
+
+// The sequence setting the colors of UTF-8 ANSI, from `current` if they're
+// known.
+fn utf8_sgr(
+    palette: &[RGBA],
+    colors: Colors,
+    current: Option<Attributes>,
+    wanted: Attributes,
+) -> String {
+    match colors {
+        Colors::Ansi16 => aixterm_sgr(current, wanted),
+        Colors::Xterm256 => {
+            let fg = palette::nearest_xterm_256(&palette[wanted.fg as usize]);
+            let bg = palette::nearest_xterm_256(&palette[wanted.bg as usize]);
+            format!("\x1b[38;5;{fg};48;5;{bg}m")
+        }
+        Colors::Truecolor => {
+            let (fg, bg) = (palette[wanted.fg as usize], palette[wanted.bg as usize]);
+            format!(
+                "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                fg.r, fg.g, fg.b, bg.r, bg.g, bg.b
+            )
+        }
+    }
+}
+
+/// Writes UTF-8 ANSI of only the cells that look different from those of
+/// `previous`, drawn with `previous_palette`, each stretch of them after a
+/// cursor move, for redrawing one frame of an animation over the last.
+#[allow(clippy::too_many_arguments)]
+pub fn write_utf8_changes(
+    file: &mut impl Write,
+    columns: usize,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+    previous_palette: &[RGBA],
+    previous: &[Chunk],
+    to_char: impl Fn(u8) -> char,
+    colors: Colors,
+) {
+    let canonical = canonical(palette, colors);
+    let shown = |palette: &[RGBA], chunk: &Chunk| {
+        let (fg, bg) = (palette[chunk.fg as usize], palette[chunk.bg as usize]);
+        (to_char(chunk.codepoint), fg.r, fg.g, fg.b, bg.r, bg.g, bg.b)
+    };
+    let changed =
+        |index: usize| shown(palette, &chunks[index]) != shown(previous_palette, &previous[index]);
+    let mut current = None;
+    for (row, start, end) in changed_spans(columns, chunks.len(), changed) {
+        write!(file, "\x1b[{};{}H", row + 1, start + 1).unwrap();
+        for chunk in &chunks[row * columns + start..row * columns + end] {
+            let wanted = Attributes {
+                fg: canonical[chunk.fg as usize],
+                bg: canonical[chunk.bg as usize],
+            };
+            if current != Some(wanted) {
+                file.write_all(utf8_sgr(palette, colors, current, wanted).as_bytes())
+                    .unwrap();
+                current = Some(wanted);
+            }
+            write!(file, "{}", to_char(chunk.codepoint)).unwrap();
+        }
+    }
+    if current.is_some() {
+        file.write_all(b"\x1b[0m").unwrap();
+    }
+}
+
+// Unchanged cells between changed ones that are redrawn rather than moved
+// over, as a cursor move costs about as much.
+const REDRAWN_GAP: usize = 4;
+
+// The stretches of each row holding cells `changed` says differ, as a row
+// and the columns they start and end at.
+fn changed_spans(
+    columns: usize,
+    count: usize,
+    changed: impl Fn(usize) -> bool,
+) -> Vec<(usize, usize, usize)> {
+    let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+    for index in (0..count).filter(|index| changed(*index)) {
+        let (row, column) = (index / columns, index % columns);
+        match spans.last_mut() {
+            Some((last_row, _, end)) if *last_row == row && column - *end <= REDRAWN_GAP => {
+                *end = column + 1
+            }
+            _ => spans.push((row, column, column + 1)),
+        }
+    }
+    spans
+}
+
+/// Extensions written as CP437 ANSI rather than XBIN.
+pub fn is_ansi_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ans"))
+}
+
+// SGR color numbers for VGA attribute colors, which swap red and blue.
+const SGR_COLORS: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+/// The width ANSI viewers wrap at without a SAUCE record saying otherwise.
+const SCREEN_COLUMNS: usize = 80;
+
+/// Codepoints that ANSI viewers act on instead of drawing: bell, backspace,
+/// tab, line feed, carriage return, end of file and escape.
+pub const CONTROLS: [u8; 7] = [0x07, 0x08, 0x09, 0x0a, 0x0d, 0x1a, 0x1b];
+
+/// For every codepoint, one that draws safely: itself, or for one of
+/// `controls` the glyph differing from it in the fewest pixels.
+pub fn printable(font: &Font, controls: &[u8]) -> Vec<u8> {
+    let difference = |a: u8, b: u8| -> u32 {
+        font.glyph(a)
+            .iter()
+            .zip(font.glyph(b))
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum()
+    };
+    (0..=255)
+        .map(|codepoint| {
+            if !controls.contains(&codepoint) {
+                return codepoint;
+            }
+            (0..=255)
+                .filter(|other| !controls.contains(other))
+                .min_by_key(|other| difference(codepoint, *other))
+                .expect("codepoints")
+        })
+        .collect()
+}
+
+// Attributes as SGR leaves them: a reset gives light gray on black.
+#[derive(Clone, Copy, PartialEq)]
+struct Attributes {
+    fg: u8,
+    bg: u8,
+}
+
+const RESET: Attributes = Attributes { fg: 7, bg: 0 };
+
+// The shortest SGR sequence from `current` to `wanted`, using bold for
+// bright foregrounds and blink for bright (iCE) backgrounds; turning either
+// off takes a reset.
+fn sgr(current: Attributes, wanted: Attributes) -> String {
+    if current == wanted {
+        return String::new();
+    }
+    let mut current = current;
+    let mut parameters = Vec::new();
+    if (current.fg >= 8 && wanted.fg < 8) || (current.bg >= 8 && wanted.bg < 8) {
+        parameters.push(0);
+        current = RESET;
+    }
+    if wanted.fg >= 8 && current.fg < 8 {
+        parameters.push(1);
+    }
+    if wanted.bg >= 8 && current.bg < 8 {
+        parameters.push(5);
+    }
+    if wanted.fg & 7 != current.fg & 7 {
+        parameters.push(30 + SGR_COLORS[wanted.fg as usize & 7]);
+    }
+    if wanted.bg & 7 != current.bg & 7 {
+        parameters.push(40 + SGR_COLORS[wanted.bg as usize & 7]);
+    }
+    let parameters: Vec<String> = parameters.iter().map(u8::to_string).collect();
+    format!("\x1b[{}m", parameters.join(";"))
+}
+
+// Sets the colors with the aixterm codes for bright ones, which terminals show
+// without bold fonts or blinking. Each code stands alone, so only changes are
+// sent, but a terminal's own defaults mean each line sets both.
+fn aixterm_sgr(current: Option<Attributes>, wanted: Attributes) -> String {
+    let code = |color: u8, base: u8| {
+        let bright = if color >= 8 { 60 } else { 0 };
+        (base + bright + SGR_COLORS[color as usize & 7]).to_string()
+    };
+    let mut parameters = Vec::new();
+    if current.is_none_or(|current| current.fg != wanted.fg) {
+        parameters.push(code(wanted.fg, 30));
+    }
+    if current.is_none_or(|current| current.bg != wanted.bg) {
+        parameters.push(code(wanted.bg, 40));
+    }
+    format!("\x1b[{}m", parameters.join(";"))
+}
+
+/// Writes the cells as CP437 ANSI for viewers with the standard 16 colors.
+/// Colors a glyph doesn't show are left as they are, blank cells on black
+/// are skipped with cursor moves or trimmed from line ends, and rows that
+/// fill the width rely on the viewer wrapping rather than a line break.
+pub fn save_ansi(path: impl AsRef<Path>, columns: usize, font: &Font, chunks: &[Chunk]) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    write_ansi(&mut file, columns, font, chunks);
+}
+
+/// Writes CP437 ANSI as `save_ansi` does, to any writer.
+pub fn write_ansi(file: &mut impl Write, columns: usize, font: &Font, chunks: &[Chunk]) {
+    let printable = printable(font, &CONTROLS);
+    let empty = |chunk: &Chunk| font.glyph(chunk.codepoint).iter().all(|row| *row == 0x00);
+    let unpainted = |chunk: &Chunk| chunk.bg == 0 && empty(chunk);
+    let mut current = RESET;
+    file.write_all(b"\x1b[0m").unwrap();
+    let rows: Vec<&[Chunk]> = chunks.chunks(columns.max(1)).collect();
+    for (index, row) in rows.iter().enumerate() {
+        let end = row
+            .iter()
+            .rposition(|chunk| !unpainted(chunk))
+            .map_or(0, |i| i + 1);
+        let mut column = 0;
+        while column < end {
+            let skip = row[column..end]
+                .iter()
+                .take_while(|chunk| unpainted(chunk))
+                .count();
+            if skip >= 4 {
+                write!(file, "\x1b[{skip}C").unwrap();
+                column += skip;
+                continue;
+            }
+            write_cell(file, font, &printable, &mut current, &row[column]);
+            column += 1;
+        }
+        // Rows narrower than the screen end themselves, so that art without
+        // a SAUCE width, like a FILE_ID.DIZ, doesn't run on.
+        if (end < row.len() || columns < SCREEN_COLUMNS) && index + 1 < rows.len() {
+            file.write_all(b"\r\n").unwrap();
+        }
+    }
+    file.write_all(b"\x1b[0m").unwrap();
+}
+
+// Writes one cell from the colors left by the last, leaving alone a
+// foreground the glyph doesn't show or a background it covers.
+fn write_cell(
+    file: &mut impl Write,
+    font: &Font,
+    printable: &[u8],
+    current: &mut Attributes,
+    chunk: &Chunk,
+) {
+    let glyph = font.glyph(chunk.codepoint);
+    let wanted = Attributes {
+        fg: if glyph.iter().all(|row| *row == 0x00) {
+            current.fg
+        } else {
+            chunk.fg
+        },
+        bg: if glyph.iter().all(|row| *row == 0xff) {
+            current.bg
+        } else {
+            chunk.bg
+        },
+    };
+    file.write_all(sgr(*current, wanted).as_bytes()).unwrap();
+    *current = wanted;
+    file.write_all(&[printable[chunk.codepoint as usize]])
+        .unwrap();
+}
+
+// Writes only the cells that differ from those of `previous`, each stretch of
+// them after a cursor move.
+fn write_changes(
+    file: &mut impl Write,
+    columns: usize,
+    font: &Font,
+    chunks: &[Chunk],
+    previous: &[Chunk],
+) {
+    let printable = printable(font, &CONTROLS);
+    let changed = |index: usize| chunks[index] != previous[index];
+    let spans = changed_spans(columns, chunks.len(), changed);
+    if spans.is_empty() {
+        return;
+    }
+    let mut current = RESET;
+    file.write_all(b"\x1b[0m").unwrap();
+    for (row, start, end) in spans {
+        write!(file, "\x1b[{};{}H", row + 1, start + 1).unwrap();
+        for chunk in &chunks[row * columns + start..row * columns + end] {
+            write_cell(file, font, &printable, &mut current, chunk);
+        }
+    }
+    file.write_all(b"\x1b[0m").unwrap();
+}
+
+/// Extends `save_ansi` to an ANSImation: the screen is cleared and the first
+/// frame drawn from the top left, then each frame after it only redraws the
+/// cells that changed, which keeps the file small enough to play over a
+/// modem.
+pub fn save_ansimation(path: impl AsRef<Path>, columns: usize, font: &Font, frames: &[&[Chunk]]) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    file.write_all(b"\x1b[2J\x1b[1;1H").unwrap();
+    write_ansi(&mut file, columns, font, frames[0]);
+    for pair in frames.windows(2) {
+        write_changes(&mut file, columns, font, pair[1], pair[0]);
+    }
+}
+
+/// Cells nothing has drawn, as a cleared screen shows them.
+const BLANK: Chunk = Chunk {
+    codepoint: b' ',
+    fg: 7,
+    bg: 0,
+};
+
+// The state SGR sequences change: the colors before bold and blink brighten
+// them, and whether they're swapped.
+struct Pen {
+    fg: u8,
+    bg: u8,
+    bold: bool,
+    blink: bool,
+    inverse: bool,
+}
+
+impl Pen {
+    fn new() -> Self {
+        Pen {
+            fg: 7,
+            bg: 0,
+            bold: false,
+            blink: false,
+            inverse: false,
+        }
+    }
+
+    fn select(&mut self, parameter: usize) {
+        match parameter {
+            0 => *self = Pen::new(),
+            1 => self.bold = true,
+            5 | 6 => self.blink = true,
+            7 => self.inverse = true,
+            22 => self.bold = false,
+            25 => self.blink = false,
+            27 => self.inverse = false,
+            30..=37 => self.fg = SGR_COLORS[parameter - 30],
+            39 => self.fg = 7,
+            40..=47 => self.bg = SGR_COLORS[parameter - 40],
+            49 => self.bg = 0,
+            _ => {}
+        }
+    }
+
+    fn cell(&self, codepoint: u8) -> Chunk {
+        let fg = self.fg + if self.bold { 8 } else { 0 };
+        let bg = self.bg + if self.blink { 8 } else { 0 };
+        let (fg, bg) = if self.inverse { (bg, fg) } else { (fg, bg) };
+        Chunk { codepoint, fg, bg }
+    }
+}
+
+/// Reads CP437 ANSI into cells `columns` wide, as far as the end of the file
+/// or the end-of-file marker before any SAUCE. Cursor moves, erases and SGR
+/// colors are followed, bright foregrounds coming from bold and bright
+/// backgrounds from blink, and other sequences are skipped. The art ends with
+/// the last row anything was drawn on.
+pub fn parse(bytes: &[u8], columns: usize) -> Vec<Chunk> {
+    let mut cells: Vec<Chunk> = Vec::new();
+    let mut pen = Pen::new();
+    let (mut x, mut y) = (0usize, 0usize);
+    let mut saved = (0, 0);
+    let mut rows = 0;
+    let mut i = 0;
+    // A cursor left past the last column wraps to the next row only once
+    // something is drawn or it moves on, so a line break straight after a
+    // full row doesn't skip a line.
+    let wrap = |x: &mut usize, y: &mut usize| {
+        if *x >= columns {
+            *x = 0;
+            *y += 1;
+        }
+    };
+    while i < bytes.len() {
+        let byte = bytes[i];
+        i += 1;
+        match byte {
+            0x1a => break,
+            b'\r' => x = 0,
+            b'\n' => {
+                x = 0;
+                y += 1;
+            }
+            b'\t' => {
+                wrap(&mut x, &mut y);
+                x = (x / 8 + 1) * 8;
+            }
+            0x1b if bytes.get(i) == Some(&b'[') => {
+                let start = i + 1;
+                let Some(length) = bytes[start..]
+                    .iter()
+                    .position(|b| (0x40..=0x7e).contains(b))
+                else {
+                    break;
+                };
+                let command = bytes[start + length];
+                let text = String::from_utf8_lossy(&bytes[start..start + length]);
+                i = start + length + 1;
+                if text.starts_with(['?', '=', '>']) {
+                    continue;
+                }
+                let parameters: Vec<Option<usize>> =
+                    text.split(';').map(|part| part.parse().ok()).collect();
+                let count = parameters.first().copied().flatten().unwrap_or(1).max(1);
+                match command {
+                    b'A' => y = y.saturating_sub(count),
+                    b'B' => y += count,
+                    b'C' => {
+                        wrap(&mut x, &mut y);
+                        x = (x + count).min(columns - 1);
+                    }
+                    b'D' => x = x.min(columns - 1).saturating_sub(count),
+                    b'H' | b'f' => {
+                        let parameter = |index: usize| {
+                            parameters.get(index).copied().flatten().unwrap_or(1).max(1) - 1
+                        };
+                        y = parameter(0);
+                        x = parameter(1).min(columns - 1);
+                    }
+                    b'J' if parameters[0] == Some(2) => {
+                        cells.clear();
+                        rows = 0;
+                        (x, y) = (0, 0);
+                    }
+                    b'K' => {
+                        let row = y * columns;
+                        for index in row + x.min(columns)..(row + columns).min(cells.len()) {
+                            cells[index] = BLANK;
+                        }
+                    }
+                    b'm' => {
+                        for parameter in &parameters {
+                            pen.select(parameter.unwrap_or(0));
+                        }
+                    }
+                    b's' => saved = (x, y),
+                    b'u' => (x, y) = saved,
+                    _ => {}
+                }
+            }
+            _ => {
+                wrap(&mut x, &mut y);
+                let index = y * columns + x;
+                if cells.len() <= index {
+                    cells.resize((y + 1) * columns, BLANK);
+                }
+                cells[index] = pen.cell(byte);
+                rows = rows.max(y + 1);
+                x += 1;
+            }
+        }
+    }
+    cells.truncate(rows * columns);
+    cells
+}
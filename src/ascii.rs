@@ -0,0 +1,71 @@
+use crate::chunks::{rgba_to_oklab, source_pixels, Chunk};
+use crate::palette::Palettes;
+use image::DynamicImage;
+use oklab::Oklab;
+
+pub const DEFAULT_RAMP: &str = " .:-=+*#%@";
+
+#[derive(Clone, Debug)]
+pub struct Ramp(pub Vec<u8>);
+
+impl Ramp {
+    /// Parses a luminance ramp, darkest character first, keeping it 7-bit safe.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if value.is_empty() {
+            return Err("the ramp is empty".to_string());
+        }
+        value
+            .chars()
+            .map(|c| match c {
+                ' '..='~' => Ok(c as u8),
+                _ => Err(format!("'{c}' is not printable ASCII")),
+            })
+            .collect::<Result<_, _>>()
+            .map(Ramp)
+    }
+}
+
+fn distance(a: &Oklab, b: &Oklab) -> f32 {
+    (a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)
+}
+
+/// Encodes each source pixel as one cell whose character is picked from the
+/// ramp by the pixel's lightness, stretched over the image's range. The
+/// background is the darkest palette entry; the foreground is the lightest,
+/// or with `color` the entry nearest the pixel.
+pub fn encode(palettes: &Palettes, image: &DynamicImage, ramp: &Ramp, color: bool) -> Vec<Chunk> {
+    let ok_palette: Vec<Oklab> = palettes.rgba[..palettes.count]
+        .iter()
+        .map(rgba_to_oklab)
+        .collect();
+    let by_lightness = |a: &usize, b: &usize| ok_palette[*a].l.total_cmp(&ok_palette[*b].l);
+    let darkest = (0..ok_palette.len()).min_by(by_lightness).expect("palette") as u8;
+    let lightest = (0..ok_palette.len()).max_by(by_lightness).expect("palette") as u8;
+    let ramp = &ramp.0;
+    let pixels = source_pixels(image);
+    let min = pixels.iter().map(|pixel| pixel.l).fold(f32::MAX, f32::min);
+    let max = pixels.iter().map(|pixel| pixel.l).fold(f32::MIN, f32::max);
+    let range = (max - min).max(f32::EPSILON);
+    pixels
+        .iter()
+        .map(|pixel| {
+            let t = ((pixel.l - min) / range).clamp(0.0, 1.0);
+            let step = ((t * ramp.len() as f32) as usize).min(ramp.len() - 1);
+            let fg = if color {
+                (0..ok_palette.len())
+                    .min_by(|a, b| {
+                        distance(&ok_palette[*a], pixel)
+                            .total_cmp(&distance(&ok_palette[*b], pixel))
+                    })
+                    .expect("palette") as u8
+            } else {
+                lightest
+            };
+            Chunk {
+                fg,
+                bg: darkest,
+                codepoint: ramp[step],
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,69 @@
+use crate::ansi::printable;
+use crate::chunks::Chunk;
+use crate::font::Font;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The Atari's text screen is 40 columns wide.
+pub const COLUMNS: usize = 40;
+
+const ESCAPE: u8 = 0x1b;
+const CLEAR: u8 = 0x7d;
+const END_OF_LINE: u8 = 0x9b;
+const REVERSE_SPACE: u8 = 0xa0;
+
+// Codes the screen editor moves the cursor, edits or sounds the bell on
+// instead of drawing, unless they follow an escape.
+const CONTROLS: [u8; 16] = [
+    0x1b,
+    0x1c,
+    0x1d,
+    0x1e,
+    0x1f,
+    0x7d,
+    0x7e,
+    0x7f,
+    0x9c,
+    0x9d,
+    0x9e,
+    0x9f,
+    0xfd,
+    0xfe,
+    0xff,
+    END_OF_LINE,
+];
+
+/// Extensions written as ATASCII rather than XBIN.
+pub fn is_atascii_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ata"))
+}
+
+/// Writes cells whose codepoints are ATASCII as a stream that clears the
+/// screen and prints them, escaping control codes so they draw. Graphics 0
+/// shows palette entry 0 as the background and entry 1 as text, so cells on
+/// entry 1 are written reversed. Rows narrower than the screen end with an
+/// end of line; the end of line glyph itself can't be drawn and is replaced.
+pub fn save_atascii(path: impl AsRef<Path>, columns: usize, font: &Font, chunks: &[Chunk]) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    let printable = printable(font, &[END_OF_LINE]);
+    file.write_all(&[CLEAR]).unwrap();
+    for row in chunks.chunks(columns.max(1)) {
+        for chunk in row {
+            let codepoint = match (chunk.fg == chunk.bg, chunk.bg == 0) {
+                (true, true) => b' ',
+                (true, false) => REVERSE_SPACE,
+                (false, true) => chunk.codepoint,
+                (false, false) => chunk.codepoint ^ 0x80,
+            };
+            let codepoint = printable[codepoint as usize];
+            if CONTROLS.contains(&codepoint) {
+                file.write_all(&[ESCAPE]).unwrap();
+            }
+            file.write_all(&[codepoint]).unwrap();
+        }
+        if row.len() < COLUMNS {
+            file.write_all(&[END_OF_LINE]).unwrap();
+        }
+    }
+}
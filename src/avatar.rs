@@ -0,0 +1,76 @@
+use crate::ansi::{printable, CONTROLS};
+use crate::chunks::Chunk;
+use crate::font::Font;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const FORM_FEED: u8 = 0x0c;
+const COMMAND: u8 = 0x16;
+const REPEAT: u8 = 0x19;
+
+/// Extensions written as AVATAR rather than XBIN.
+pub fn is_avatar_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("avt"))
+}
+
+/// Writes the cells as CP437 text with AVATAR/0 codes: `^V^A` attributes,
+/// with `^V^B` blinking for bright backgrounds, and `^Y` for runs of a
+/// character. As with ANSI, colors a glyph doesn't show are left as they are
+/// and blank cells on black are trimmed from line ends.
+pub fn save_avatar(path: impl AsRef<Path>, columns: usize, font: &Font, chunks: &[Chunk]) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    let printable = printable(
+        font,
+        &[&CONTROLS[..], &[FORM_FEED, COMMAND, REPEAT]].concat(),
+    );
+    let empty = |chunk: &Chunk| font.glyph(chunk.codepoint).iter().all(|row| *row == 0x00);
+    let solid = |chunk: &Chunk| font.glyph(chunk.codepoint).iter().all(|row| *row == 0xff);
+    let unpainted = |chunk: &Chunk| chunk.bg == 0 && empty(chunk);
+    let mut current: Option<(u8, u8)> = None;
+    let rows: Vec<&[Chunk]> = chunks.chunks(columns.max(1)).collect();
+    for (index, row) in rows.iter().enumerate() {
+        let end = row
+            .iter()
+            .rposition(|chunk| !unpainted(chunk))
+            .map_or(0, |i| i + 1);
+        let mut column = 0;
+        while column < end {
+            let chunk = &row[column];
+            let (fg, bg) = current.unwrap_or((chunk.fg, chunk.bg));
+            let wanted = (
+                if empty(chunk) { fg } else { chunk.fg },
+                if solid(chunk) { bg } else { chunk.bg },
+            );
+            if current != Some(wanted) {
+                let attribute = (wanted.1 & 7) << 4 | wanted.0;
+                file.write_all(&[COMMAND, 0x01, attribute]).unwrap();
+                if wanted.1 >= 8 {
+                    file.write_all(&[COMMAND, 0x02]).unwrap();
+                }
+                current = Some(wanted);
+            }
+            let codepoint = printable[chunk.codepoint as usize];
+            let length = row[column..end]
+                .iter()
+                .take(255)
+                .take_while(|other| {
+                    printable[other.codepoint as usize] == codepoint
+                        && (empty(other) || other.fg == wanted.0)
+                        && (solid(other) || other.bg == wanted.1)
+                })
+                .count();
+            // A run takes three bytes.
+            if length > 3 {
+                file.write_all(&[REPEAT, codepoint, length as u8]).unwrap();
+            } else {
+                file.write_all(&[codepoint]).unwrap();
+            }
+            column += if length > 3 { length } else { 1 };
+        }
+        if end < row.len() && index + 1 < rows.len() {
+            file.write_all(b"\r\n").unwrap();
+        }
+    }
+    file.write_all(&[COMMAND, 0x01, 0x07]).unwrap();
+}
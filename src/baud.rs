@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+/// Bits each byte takes on the line: a start bit, eight data bits and a stop
+/// bit.
+const BITS_PER_BYTE: u64 = 10;
+
+/// How often text arriving over the line is shown, as chunks a second.
+const CHUNKS_PER_SECOND: u32 = 30;
+
+/// How long `bytes` take to arrive over a modem at `baud`.
+pub fn transfer_time(bytes: usize, baud: u32) -> Duration {
+    Duration::from_secs_f64((bytes as u64 * BITS_PER_BYTE) as f64 / f64::from(baud))
+}
+
+/// Retimes `events` as though sent over a modem at `baud`, each split into
+/// the chunks arriving in a thirtieth of a second and shown as they arrive.
+/// An event starts no sooner than its time, nor before the last has
+/// finished arriving.
+pub fn paced(events: &[(Duration, String)], baud: u32) -> Vec<(Duration, String)> {
+    let chunk_bytes = (baud as usize / BITS_PER_BYTE as usize / CHUNKS_PER_SECOND as usize).max(1);
+    let mut paced = Vec::new();
+    let mut arrived = Duration::ZERO;
+    for (time, text) in events {
+        arrived = arrived.max(*time);
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            let mut end = chunk_bytes.min(rest.len());
+            while !rest.is_char_boundary(end) {
+                end += 1;
+            }
+            let (chunk, tail) = rest.split_at(end);
+            arrived += transfer_time(chunk.len(), baud);
+            paced.push((arrived, chunk.to_string()));
+            rest = tail;
+        }
+    }
+    paced
+}
@@ -0,0 +1,58 @@
+use crate::chunks::Chunk;
+use crate::xbin::chunks_to_bytes;
+use std::io::Write;
+use std::path::Path;
+
+/// Widest BIN a SAUCE record can describe, as it stores half the width in a
+/// byte.
+pub const MAX_COLUMNS: usize = 510;
+
+/// Extensions written as BIN rather than XBIN.
+pub fn is_bin_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("bin"))
+}
+
+/// Writes the cells as bare character/attribute pairs, with no header for the
+/// width, palette or font. An odd width gets a blank column on the right,
+/// since SAUCE can only give BIN widths in steps of two. Returns the width
+/// written.
+pub fn save_bin(path: impl AsRef<Path>, columns: usize, chunks: &[Chunk]) -> usize {
+    let blank = Chunk {
+        fg: 0,
+        bg: 0,
+        codepoint: 0x20,
+    };
+    let width = columns.next_multiple_of(2);
+    let cells: Vec<Chunk> = chunks
+        .chunks(columns.max(1))
+        .flat_map(|row| {
+            let mut row = row.to_vec();
+            row.resize(width, blank);
+            row
+        })
+        .collect();
+    let mut file = std::fs::File::create(path).unwrap();
+    file.write_all(&chunks_to_bytes(&cells)).unwrap();
+    width
+}
+
+/// The cells of a BIN's character/attribute pairs, the last row filled out
+/// with blank cells if it's short.
+pub fn parse(bytes: &[u8], columns: usize) -> Vec<Chunk> {
+    let mut chunks: Vec<Chunk> = bytes
+        .chunks_exact(2)
+        .map(|cell| Chunk {
+            codepoint: cell[0],
+            fg: cell[1] & 0x0f,
+            bg: cell[1] >> 4,
+        })
+        .collect();
+    let blank = Chunk {
+        fg: 0,
+        bg: 0,
+        codepoint: 0x20,
+    };
+    chunks.resize(chunks.len().next_multiple_of(columns), blank);
+    chunks
+}
@@ -0,0 +1,64 @@
+use crate::chunks::{best_block, distance_table, rgba_to_oklab, Chunk};
+use crate::font::Font;
+use crate::palette::Palettes;
+
+// Offsets within the 2x4 block for each dot, from bit 7 (dot 8) down to bit 0
+// (dot 1), so the mask encode_pair builds is the Unicode braille offset.
+const DOTS: [(usize, usize); 8] = [
+    (1, 3),
+    (0, 3),
+    (1, 2),
+    (1, 1),
+    (1, 0),
+    (0, 2),
+    (0, 1),
+    (0, 0),
+];
+
+/// The braille character for a cell's dot mask.
+pub fn to_char(mask: u8) -> char {
+    char::from_u32(0x2800 + mask as u32).expect("braille")
+}
+
+/// An 8x16 font with each codepoint drawn as its braille dot pattern, so the
+/// usage report and blink-safe remapping see the real dot coverage.
+pub fn font() -> Font {
+    let mut glyphs = vec![0; 256 * 16];
+    for mask in 0..256 {
+        for (bit, (x, y)) in DOTS.iter().rev().enumerate() {
+            if mask & (1 << bit) != 0 {
+                let row = mask * 16 + y * 4 + 1;
+                let bits = 0x60 >> (x * 4);
+                glyphs[row] |= bits;
+                glyphs[row + 1] |= bits;
+            }
+        }
+    }
+    Font { height: 16, glyphs }
+}
+
+/// Encodes each 2x4 block of pixels as the braille pattern and fg/bg pair
+/// with the least Oklab error, keeping the darker color in the background.
+pub fn encode(palettes: &Palettes, indexes: &[u8], width: usize, height: usize) -> Vec<Chunk> {
+    let distances = distance_table(palettes);
+    let lightness: Vec<f32> = palettes
+        .rgba
+        .iter()
+        .map(|color| rgba_to_oklab(color).l)
+        .collect();
+    let pixel = |x: usize, y: usize| indexes[y.min(height - 1) * width + x.min(width - 1)];
+    let (columns, rows) = (width.div_ceil(2), height.div_ceil(4));
+    let mut chunks = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let block = DOTS.map(|(x, y)| pixel(column * 2 + x, row * 4 + y));
+            let (bg, fg, mask) = best_block(&block, &distances, &lightness);
+            chunks.push(Chunk {
+                fg,
+                bg,
+                codepoint: mask,
+            });
+        }
+    }
+    chunks
+}
@@ -0,0 +1,54 @@
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Extensions written as asciinema recordings rather than XBIN.
+pub fn is_cast_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("cast"))
+}
+
+/// `text` as a JSON string.
+pub fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            character if (character as u32) < 0x20 => {
+                json.push_str(&format!("\\u{:04x}", character as u32));
+            }
+            character => json.push(character),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Writes an asciinema v2 recording of a `columns` by `rows` terminal that
+/// prints each of `events` at its time from the start.
+pub fn save_cast(
+    path: impl AsRef<Path>,
+    columns: usize,
+    rows: usize,
+    events: &[(Duration, String)],
+) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    writeln!(
+        file,
+        "{{\"version\": 2, \"width\": {columns}, \"height\": {rows}, \"env\": {{\"TERM\": \"xterm-256color\"}}}}"
+    )
+    .unwrap();
+    for (time, text) in events {
+        writeln!(
+            file,
+            "[{:.6}, \"o\", {}]",
+            time.as_secs_f64(),
+            json_string(text)
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use crate::cp437;
+
+/// A set of codepoints glyph matching is allowed to choose from.
+#[derive(Clone, Debug)]
+pub struct Charset(pub Vec<u8>);
+
+impl Charset {
+    /// Parses a named set (`blocks`, `shades`, `ascii` or `box`), the path of
+    /// a file listing codepoints as decimal or `0x` hex numbers, or a literal
+    /// string of characters to map through CP437.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let codepoints: Vec<u8> = match value {
+            "blocks" => vec![0x20, 0xb0, 0xb1, 0xb2, 0xdb, 0xdc, 0xdd, 0xde, 0xdf],
+            "shades" => vec![0x20, 0xb0, 0xb1, 0xb2, 0xdb],
+            "ascii" => (0x20..=0x7e).collect(),
+            "box" => std::iter::once(0x20).chain(0xb3..=0xda).collect(),
+            _ if std::path::Path::new(value).is_file() => {
+                let text = std::fs::read_to_string(value).map_err(|err| err.to_string())?;
+                text.split(|c: char| c.is_whitespace() || c == ',')
+                    .filter(|word| !word.is_empty())
+                    .map(|word| {
+                        let parsed = match word.strip_prefix("0x") {
+                            Some(hex) => u8::from_str_radix(hex, 16),
+                            None => word.parse(),
+                        };
+                        parsed.map_err(|_| format!("invalid codepoint '{word}' in {value}"))
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            _ => value
+                .chars()
+                .map(|c| cp437::from_char(c).ok_or_else(|| format!("'{c}' is not in CP437")))
+                .collect::<Result<_, _>>()?,
+        };
+        if codepoints.is_empty() {
+            return Err("the charset is empty".to_string());
+        }
+        Ok(Charset(codepoints))
+    }
+
+    pub fn contains(&self, codepoint: u8) -> bool {
+        self.0.contains(&codepoint)
+    }
+}
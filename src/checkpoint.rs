@@ -0,0 +1,136 @@
+use crate::chunks::Chunk;
+use crate::palette::Palettes;
+use imagequant::RGBA;
+use std::path::{Path, PathBuf};
+
+/// Progress through converting the frames of an animation, kept in a
+/// directory beside the output so an interrupted run can pick up where it
+/// stopped with --resume.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    directory: PathBuf,
+}
+
+// The command line that made the checkpoint, less --resume, which resuming
+// must repeat for the frames to match.
+fn arguments() -> String {
+    std::env::args_os()
+        .skip(1)
+        .filter(|argument| argument != "--resume")
+        .map(|argument| argument.to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn rgba_bytes(colors: &[RGBA]) -> Vec<u8> {
+    colors
+        .iter()
+        .flat_map(|color| [color.r, color.g, color.b, color.a])
+        .collect()
+}
+
+fn rgba_colors(bytes: &[u8]) -> Vec<RGBA> {
+    bytes
+        .chunks_exact(4)
+        .map(|color| RGBA {
+            r: color[0],
+            g: color[1],
+            b: color[2],
+            a: color[3],
+        })
+        .collect()
+}
+
+impl Checkpoint {
+    /// The checkpoint of `output`, as `anim.ans.checkpoint` for `anim.ans`.
+    pub fn for_output(output: &Path) -> Self {
+        let mut directory = output.as_os_str().to_os_string();
+        directory.push(".checkpoint");
+        Checkpoint {
+            directory: PathBuf::from(directory),
+        }
+    }
+
+    /// Whether progress of this command line is kept. Errors if the
+    /// checkpoint was made by a different one.
+    pub fn resumable(&self) -> Result<bool, String> {
+        match std::fs::read_to_string(self.directory.join("arguments")) {
+            Err(_) => Ok(false),
+            Ok(kept) if kept == arguments() => Ok(true),
+            Ok(_) => Err(format!(
+                "{} was made with other arguments, which --resume must repeat",
+                self.directory.display()
+            )),
+        }
+    }
+
+    /// Creates the checkpoint if it isn't kept yet.
+    pub fn begin(&self) {
+        std::fs::create_dir_all(&self.directory).unwrap();
+        self.write("arguments", arguments().as_bytes());
+    }
+
+    /// Removes the checkpoint and everything kept in it.
+    pub fn clear(&self) {
+        if self.directory.exists() {
+            std::fs::remove_dir_all(&self.directory).unwrap();
+        }
+    }
+
+    // Writes to a temporary file first, so an interruption never leaves a
+    // part-written one to resume from.
+    fn write(&self, name: &str, bytes: &[u8]) {
+        let temporary = self.directory.join(format!("{name}.part"));
+        std::fs::write(&temporary, bytes).unwrap();
+        std::fs::rename(temporary, self.directory.join(name)).unwrap();
+    }
+
+    /// The palette the frames share, if kept.
+    pub fn palette(&self) -> Option<Vec<RGBA>> {
+        let bytes = std::fs::read(self.directory.join("palette")).ok()?;
+        Some(rgba_colors(&bytes))
+    }
+
+    pub fn save_palette(&self, palette: &[RGBA]) {
+        self.write("palette", &rgba_bytes(palette));
+    }
+
+    /// The columns, palettes and cells of converted frame `index`, if kept.
+    pub fn frame(&self, index: usize) -> Option<(usize, Palettes, Vec<Chunk>)> {
+        let bytes = std::fs::read(self.directory.join(format!("{index}.frame"))).ok()?;
+        let columns = u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+        let count = bytes[4] as usize;
+        // The VGA palette is kept rather than rebuilt from the RGBA one,
+        // which doesn't always scale back to the same entries.
+        let vga = rgba_colors(&bytes[5..5 + 16 * 4]);
+        let rgba = vga
+            .iter()
+            .map(|color| RGBA {
+                r: color.r * 4,
+                g: color.g * 4,
+                b: color.b * 4,
+                a: 255,
+            })
+            .collect();
+        let chunks = bytes[5 + 16 * 4..]
+            .chunks_exact(3)
+            .map(|cell| Chunk {
+                codepoint: cell[0],
+                fg: cell[1],
+                bg: cell[2],
+            })
+            .collect();
+        Some((columns, Palettes { vga, rgba, count }, chunks))
+    }
+
+    pub fn save_frame(&self, index: usize, columns: usize, palettes: &Palettes, chunks: &[Chunk]) {
+        let mut bytes = Vec::with_capacity(5 + 16 * 4 + chunks.len() * 3);
+        bytes.extend_from_slice(&(columns as u32).to_le_bytes());
+        bytes.push(palettes.count as u8);
+        bytes.extend(rgba_bytes(&palettes.vga[..16]));
+        for chunk in chunks {
+            bytes.extend_from_slice(&[chunk.codepoint, chunk.fg, chunk.bg]);
+        }
+        self.write(&format!("{index}.frame"), &bytes);
+    }
+}
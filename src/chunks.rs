@@ -0,0 +1,654 @@
+use crate::charset::Charset;
+use crate::font::Font;
+use crate::palette::Palettes;
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView};
+use imagequant::RGBA;
+use oklab::{srgb_to_oklab, Oklab};
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum ChunkOpt {
+    /// Use the two most frequent colors in each chunk
+    Frequency,
+    /// Use the pair of colors present in each chunk with the least Oklab error
+    #[default]
+    Error,
+    /// Try every palette pair against the source pixels (slow)
+    Exhaustive,
+}
+
+fn find_closest(rgba: &RGBA, palette: &[RGBA]) -> u8 {
+    let mut closest: Option<u8> = None;
+    let mut closest_distance: f32 = 0.0;
+    let ok_palette: Vec<_> = palette
+        .iter()
+        .map(|color| {
+            srgb_to_oklab(oklab::RGB {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+            })
+        })
+        .collect();
+    let ok_rgba = srgb_to_oklab(oklab::RGB {
+        r: rgba.r,
+        g: rgba.g,
+        b: rgba.b,
+    });
+    for (index, color) in ok_palette.iter().enumerate() {
+        let distance = (color.l - ok_rgba.l).powi(2)
+            + (color.a - ok_rgba.a).powi(2)
+            + (color.b - ok_rgba.b).powi(2);
+        if closest.is_none() || distance < closest_distance {
+            closest_distance = distance;
+            closest = Some(index as u8);
+        }
+    }
+    closest.expect("match")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Chunk {
+    pub fg: u8,
+    pub bg: u8,
+    pub codepoint: u8,
+}
+
+impl Chunk {
+    pub fn solid(index: u8) -> Self {
+        Chunk {
+            fg: index,
+            bg: index,
+            codepoint: 0x00,
+        }
+    }
+
+    // A chunk showing one color is always stored as a blank cell with a
+    // matching attribute, and the darker of two colors always sits in the
+    // background, so identical content encodes identically.
+    fn canonicalize(&mut self, lightness: &[f32]) {
+        match self.codepoint {
+            0x00 => self.fg = self.bg,
+            0xff => *self = Chunk::solid(self.fg),
+            _ if self.fg == self.bg => *self = Chunk::solid(self.bg),
+            _ => {
+                if lightness[self.fg as usize] < lightness[self.bg as usize] {
+                    std::mem::swap(&mut self.fg, &mut self.bg);
+                    self.codepoint = !self.codepoint;
+                }
+            }
+        }
+    }
+
+    /// Palette index shown by the pixel at `column` (0 is the leftmost).
+    pub fn index_at(&self, column: usize) -> u8 {
+        if self.codepoint & (0x80 >> column) != 0 {
+            self.fg
+        } else {
+            self.bg
+        }
+    }
+}
+
+fn break_by_frequency(palettes: &Palettes, mut indexes: Vec<u8>) -> Vec<Chunk> {
+    indexes
+        .chunks_exact_mut(8)
+        .map(|chunk| {
+            if chunk.iter().all(|index| *index == chunk[0]) {
+                return Chunk::solid(chunk[0]);
+            }
+            let mut scores: Vec<(u8, usize)> = (0..16)
+                .map(|index| {
+                    let mut score = 0;
+                    for color_index in chunk.iter() {
+                        if *color_index == index {
+                            score += 1;
+                        }
+                    }
+                    (index, score)
+                })
+                .collect();
+            scores.sort_by(|(_, a), (_, b)| b.cmp(a));
+            scores.resize(2, (0, 0));
+            let common_indexes: Vec<u8> = scores.into_iter().map(|(index, _)| index).collect();
+            let common_palette: Vec<RGBA> = common_indexes
+                .iter()
+                .map(|index| palettes.rgba[*index as usize])
+                .collect();
+            for color_index in chunk.iter_mut() {
+                if !common_indexes.contains(color_index) {
+                    let rgba = &palettes.rgba[*color_index as usize];
+                    *color_index = find_closest(rgba, &common_palette);
+                }
+            }
+            let bg = common_indexes[0];
+            let fg = common_indexes[1];
+            let bitmask = chunk.iter().map(|index| if *index == bg { 0 } else { 1 });
+            let codepoint = bitmask.fold(0, |acc, bit| (acc << 1) + bit);
+            Chunk { fg, bg, codepoint }
+        })
+        .collect()
+}
+
+pub fn rgba_to_oklab(color: &RGBA) -> Oklab {
+    srgb_to_oklab(oklab::RGB {
+        r: color.r,
+        g: color.g,
+        b: color.b,
+    })
+}
+
+pub fn distance_table(palettes: &Palettes) -> [[f32; 16]; 16] {
+    let ok_palette: Vec<Oklab> = palettes.rgba.iter().map(rgba_to_oklab).collect();
+    let mut table = [[0.0; 16]; 16];
+    for (a, color_a) in ok_palette.iter().enumerate() {
+        for (b, color_b) in ok_palette.iter().enumerate() {
+            table[a][b] = (color_a.l - color_b.l).powi(2)
+                + (color_a.a - color_b.a).powi(2)
+                + (color_a.b - color_b.b).powi(2);
+        }
+    }
+    table
+}
+
+pub fn encode_pair(chunk: &[u8], bg: u8, fg: u8, distances: &[[f32; 16]; 16]) -> (u8, f32) {
+    let mut codepoint = 0;
+    let mut error = 0.0;
+    for index in chunk {
+        let to_bg = distances[*index as usize][bg as usize];
+        let to_fg = distances[*index as usize][fg as usize];
+        codepoint <<= 1;
+        if to_fg < to_bg {
+            codepoint += 1;
+            error += to_fg;
+        } else {
+            error += to_bg;
+        }
+    }
+    (codepoint, error)
+}
+
+/// The background, foreground and mask (first pixel in the top bit) that
+/// encode `block` with the least error. The darker color sits in the
+/// background, and a block showing one color is a clear mask with fg = bg.
+pub fn best_block(block: &[u8], distances: &[[f32; 16]; 16], lightness: &[f32]) -> (u8, u8, u8) {
+    let full = (1u16 << block.len()) - 1;
+    let mut present = block.to_vec();
+    present.sort_unstable();
+    present.dedup();
+    let mut best = (present[0], present[0], 0, f32::MAX);
+    for (i, a) in present.iter().enumerate() {
+        for b in present.iter().skip(i + 1) {
+            let (mask, error) = encode_pair(block, *a, *b, distances);
+            if error < best.3 {
+                best = (*a, *b, mask, error);
+            }
+        }
+    }
+    let (mut bg, mut fg, mut mask, _) = best;
+    if lightness[fg as usize] < lightness[bg as usize] {
+        std::mem::swap(&mut fg, &mut bg);
+        mask = !mask & full as u8;
+    }
+    if mask as u16 == full {
+        (bg, mask) = (fg, 0);
+    }
+    if mask == 0 {
+        fg = bg;
+    }
+    (bg, fg, mask)
+}
+
+fn break_by_error(palettes: &Palettes, indexes: Vec<u8>) -> Vec<Chunk> {
+    let distances = distance_table(palettes);
+    indexes
+        .chunks_exact(8)
+        .map(|chunk| {
+            let mut present: Vec<u8> = chunk.to_vec();
+            present.sort_unstable();
+            present.dedup();
+            if present.len() == 1 {
+                return Chunk::solid(present[0]);
+            }
+            let mut best: Option<(u8, u8, u8, f32)> = None;
+            for (i, a) in present.iter().enumerate() {
+                for b in present.iter().skip(i + 1) {
+                    let (codepoint, error) = encode_pair(chunk, *a, *b, &distances);
+                    if best.is_none_or(|(_, _, _, best_error)| error < best_error) {
+                        best = Some((*a, *b, codepoint, error));
+                    }
+                }
+            }
+            let (a, b, codepoint, _) = best.expect("pair");
+            orient(a, b, codepoint)
+        })
+        .collect()
+}
+
+// Set bits select `b`, clear bits select `a`.
+fn orient(a: u8, b: u8, codepoint: u8) -> Chunk {
+    Chunk {
+        fg: b,
+        bg: a,
+        codepoint,
+    }
+}
+
+pub fn source_pixels(image: &DynamicImage) -> Vec<Oklab> {
+    image
+        .pixels()
+        .map(|(_, _, pixel)| {
+            srgb_to_oklab(oklab::RGB {
+                r: pixel[0],
+                g: pixel[1],
+                b: pixel[2],
+            })
+        })
+        .collect()
+}
+
+fn pixel_distances(ok_palette: &[Oklab], chunk: &[Oklab]) -> [[f32; 8]; 16] {
+    let mut distances = [[0.0; 8]; 16];
+    for (color, row) in ok_palette.iter().zip(distances.iter_mut()) {
+        for (pixel, distance) in chunk.iter().zip(row.iter_mut()) {
+            *distance = (color.l - pixel.l).powi(2)
+                + (color.a - pixel.a).powi(2)
+                + (color.b - pixel.b).powi(2);
+        }
+    }
+    distances
+}
+
+fn best_pair(distances: &[[f32; 8]; 16], candidates: &[u8]) -> (u8, u8, u8) {
+    let mut best: Option<(u8, u8, u8, f32)> = None;
+    for (i, a) in candidates.iter().enumerate() {
+        for b in candidates.iter().skip(i) {
+            let mut codepoint = 0;
+            let mut error = 0.0;
+            let to_a = &distances[*a as usize];
+            let to_b = &distances[*b as usize];
+            for (to_a, to_b) in to_a.iter().zip(to_b.iter()) {
+                codepoint <<= 1;
+                if to_b < to_a {
+                    codepoint += 1;
+                    error += to_b;
+                } else {
+                    error += to_a;
+                }
+            }
+            if best.is_none_or(|(_, _, _, best_error)| error < best_error) {
+                best = Some((*a, *b, codepoint, error));
+            }
+        }
+    }
+    let (a, b, codepoint, _) = best.expect("pair");
+    (a, b, codepoint)
+}
+
+fn break_exhaustively(palettes: &Palettes, image: &DynamicImage) -> Vec<Chunk> {
+    let ok_palette: Vec<Oklab> = palettes.rgba.iter().map(rgba_to_oklab).collect();
+    let candidates: Vec<u8> = (0..palettes.count as u8).collect();
+    source_pixels(image)
+        .chunks_exact(8)
+        .map(|chunk| {
+            let distances = pixel_distances(&ok_palette, chunk);
+            if chunk.iter().all(|pixel| *pixel == chunk[0]) {
+                return Chunk::solid(nearest(&distances, palettes.count, 0));
+            }
+            let (a, b, codepoint) = best_pair(&distances, &candidates);
+            orient(a, b, codepoint)
+        })
+        .collect()
+}
+
+// The nearest of the first `count` entries, leaving out the padding of a
+// smaller palette.
+fn nearest(distances: &[[f32; 8]; 16], count: usize, pixel: usize) -> u8 {
+    (0..count)
+        .min_by(|a: &usize, b: &usize| distances[*a][pixel].total_cmp(&distances[*b][pixel]))
+        .expect("palette") as u8
+}
+
+// Encodes chunks in raster order against a working copy of the source,
+// pushing each pixel's residual onto the row below and the chunk to the
+// right so the two-color flattening doesn't drift the local average.
+fn break_with_diffusion(
+    palettes: &Palettes,
+    image: &DynamicImage,
+    opt: ChunkOpt,
+    strength: f32,
+) -> Vec<Chunk> {
+    let ok_palette: Vec<Oklab> = palettes.rgba.iter().map(rgba_to_oklab).collect();
+    let width = image.width() as usize;
+    let mut working = source_pixels(image);
+    let all: Vec<u8> = (0..palettes.count as u8).collect();
+    let mut chunks = Vec::with_capacity(working.len() / 8);
+    for start in (0..working.len() - working.len() % 8).step_by(8) {
+        let distances = pixel_distances(&ok_palette, &working[start..start + 8]);
+        let nearest: Vec<u8> = (0..8)
+            .map(|pixel| nearest(&distances, palettes.count, pixel))
+            .collect();
+        let (a, b, codepoint) = match opt {
+            ChunkOpt::Exhaustive => best_pair(&distances, &all),
+            ChunkOpt::Error => {
+                let mut present = nearest.clone();
+                present.sort_unstable();
+                present.dedup();
+                best_pair(&distances, &present)
+            }
+            ChunkOpt::Frequency => {
+                let mut scores: Vec<(u8, usize)> = (0..palettes.count as u8)
+                    .map(|index| (index, nearest.iter().filter(|n| **n == index).count()))
+                    .collect();
+                scores.sort_by(|(_, a), (_, b)| b.cmp(a));
+                scores.resize(2, (0, 0));
+                best_pair(&distances, &[scores[0].0, scores[1].0])
+            }
+        };
+        let mut carried = Oklab {
+            l: 0.0,
+            a: 0.0,
+            b: 0.0,
+        };
+        for pixel in 0..8 {
+            let chosen = if codepoint & (0x80 >> pixel) != 0 {
+                ok_palette[b as usize]
+            } else {
+                ok_palette[a as usize]
+            };
+            let position = start + pixel;
+            let residual = Oklab {
+                l: (working[position].l - chosen.l) * strength,
+                a: (working[position].a - chosen.a) * strength,
+                b: (working[position].b - chosen.b) * strength,
+            };
+            // The taps below left and below right stop at the image's edges
+            // rather than wrapping to the far side of the next row.
+            let x = position % width;
+            for (offset, weight, reaches) in [
+                (width - 1, 3.0 / 16.0, x > 0),
+                (width, 5.0 / 16.0, true),
+                (width + 1, 1.0 / 16.0, x + 1 < width),
+            ] {
+                if !reaches {
+                    continue;
+                }
+                if let Some(below) = working.get_mut(position + offset) {
+                    below.l += residual.l * weight;
+                    below.a += residual.a * weight;
+                    below.b += residual.b * weight;
+                }
+            }
+            carried.l += residual.l * 7.0 / 16.0;
+            carried.a += residual.a * 7.0 / 16.0;
+            carried.b += residual.b * 7.0 / 16.0;
+        }
+        if (start + 8) % width != 0 {
+            for right in working.iter_mut().skip(start + 8).take(8) {
+                right.l += carried.l / 8.0;
+                right.a += carried.a / 8.0;
+                right.b += carried.b / 8.0;
+            }
+        }
+        chunks.push(orient(a, b, codepoint));
+    }
+    chunks
+}
+
+/// Squared Oklab error of each chunk's encoding against the source pixels.
+pub fn chunk_errors(pixels: &[Oklab], palettes: &Palettes, chunks: &[Chunk]) -> Vec<f32> {
+    let ok_palette: Vec<Oklab> = palettes.rgba.iter().map(rgba_to_oklab).collect();
+    chunks
+        .iter()
+        .zip(pixels.chunks_exact(8))
+        .map(|(chunk, pixels)| {
+            pixels
+                .iter()
+                .enumerate()
+                .map(|(i, pixel)| {
+                    let color = &ok_palette[chunk.index_at(i) as usize];
+                    (color.l - pixel.l).powi(2)
+                        + (color.a - pixel.a).powi(2)
+                        + (color.b - pixel.b).powi(2)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// Re-derives each bitmask by projecting the source pixel onto the line between
+// the chunk's background and foreground and comparing against an ordered
+// threshold, so in-between colors become fg/bg mixes instead of snapping.
+fn dither_chunks(palettes: &Palettes, image: &DynamicImage, chunks: &mut [Chunk]) {
+    let ok_palette: Vec<Oklab> = palettes.rgba.iter().map(rgba_to_oklab).collect();
+    let width = image.width() as usize;
+    let pixels = source_pixels(image);
+    for (index, (chunk, pixels)) in chunks.iter_mut().zip(pixels.chunks_exact(8)).enumerate() {
+        if chunk.fg == chunk.bg {
+            continue;
+        }
+        let bg = ok_palette[chunk.bg as usize];
+        let fg = ok_palette[chunk.fg as usize];
+        let span = Oklab {
+            l: fg.l - bg.l,
+            a: fg.a - bg.a,
+            b: fg.b - bg.b,
+        };
+        let length = span.l * span.l + span.a * span.a + span.b * span.b;
+        if length == 0.0 {
+            continue;
+        }
+        let mut codepoint = 0;
+        for (i, pixel) in pixels.iter().enumerate() {
+            let position = index * 8 + i;
+            let (x, y) = (position % width, position / width);
+            let t =
+                ((pixel.l - bg.l) * span.l + (pixel.a - bg.a) * span.a + (pixel.b - bg.b) * span.b)
+                    / length;
+            let threshold = (BAYER_4X4[y % 4][x % 4] as f32 + 0.5) / 16.0;
+            codepoint <<= 1;
+            if t > threshold {
+                codepoint += 1;
+            }
+        }
+        *chunk = orient(chunk.bg, chunk.fg, codepoint);
+    }
+}
+
+// Re-encodes a chunk with the previous chunk's attribute whenever that costs
+// at most `tolerance` extra mean Oklab distance per pixel, so runs of equal
+// attributes get longer and run-length encoded outputs shrink. Reused chunks
+// are canonicalized like the rest.
+fn bias_runs(
+    image: &DynamicImage,
+    palettes: &Palettes,
+    chunks: &mut [Chunk],
+    tolerance: f32,
+    lightness: &[f32],
+) {
+    let ok_palette: Vec<Oklab> = palettes.rgba.iter().map(rgba_to_oklab).collect();
+    let pixels = source_pixels(image);
+    let errors = chunk_errors(&pixels, palettes, chunks);
+    let columns = (image.width() / 8).max(1) as usize;
+    for index in 1..chunks.len() {
+        if index % columns == 0 {
+            continue;
+        }
+        let (bg, fg) = (chunks[index - 1].bg, chunks[index - 1].fg);
+        if (bg, fg) == (chunks[index].bg, chunks[index].fg) {
+            continue;
+        }
+        let distances = pixel_distances(&ok_palette, &pixels[index * 8..index * 8 + 8]);
+        let (_, _, codepoint) = best_pair(&distances, &[bg, fg]);
+        let mut reused = Chunk { fg, bg, codepoint };
+        let error: f32 = (0..8)
+            .map(|i| distances[reused.index_at(i) as usize][i])
+            .sum();
+        if (error / 8.0).sqrt() - (errors[index] / 8.0).sqrt() <= tolerance {
+            reused.canonicalize(lightness);
+            chunks[index] = reused;
+        }
+    }
+}
+
+/// Keeps every background in the low eight entries, which viewers without
+/// iCE colors can show without blinking. The entries used most as backgrounds
+/// are moved there first; remaining high backgrounds are swapped with a low
+/// foreground where the font has an inverse glyph, or replaced by the nearest
+/// low entry.
+pub fn restrict_backgrounds(
+    palettes: &mut Palettes,
+    chunks: &mut [Chunk],
+    font: &Font,
+    charset: Option<&Charset>,
+) {
+    let mut usage = [0usize; 16];
+    for chunk in chunks.iter() {
+        if !font.is_solid(chunk.codepoint) {
+            usage[chunk.bg as usize] += 1;
+        }
+    }
+    let mut order: Vec<usize> = (0..16).collect();
+    order.sort_by(|a, b| usage[*b].cmp(&usage[*a]));
+    palettes.reorder(&order);
+    let mut position = [0u8; 16];
+    for (new, old) in order.iter().enumerate() {
+        position[*old] = new as u8;
+    }
+    let ok_palette: Vec<Oklab> = palettes.rgba.iter().map(rgba_to_oklab).collect();
+    // A palette of fewer than eight colors has padding in the low entries.
+    let low = palettes.count.min(8);
+    let nearest_low = |index: u8| -> u8 {
+        let color = &ok_palette[index as usize];
+        (0..low)
+            .min_by(|a: &usize, b: &usize| {
+                let distance = |other: &Oklab| {
+                    (color.l - other.l).powi(2)
+                        + (color.a - other.a).powi(2)
+                        + (color.b - other.b).powi(2)
+                };
+                distance(&ok_palette[*a]).total_cmp(&distance(&ok_palette[*b]))
+            })
+            .expect("palette") as u8
+    };
+    let inverses =
+        font.inverses(|codepoint| charset.is_none_or(|charset| charset.contains(codepoint)));
+    for chunk in chunks.iter_mut() {
+        chunk.fg = position[chunk.fg as usize];
+        chunk.bg = position[chunk.bg as usize];
+        if chunk.bg < 8 {
+            continue;
+        }
+        match inverses[chunk.codepoint as usize] {
+            Some(inverse) if chunk.fg == chunk.bg => {
+                chunk.bg = nearest_low(chunk.fg);
+                chunk.codepoint = inverse;
+            }
+            Some(inverse) if chunk.fg < 8 => {
+                std::mem::swap(&mut chunk.fg, &mut chunk.bg);
+                chunk.codepoint = inverse;
+            }
+            _ => chunk.bg = nearest_low(chunk.bg),
+        }
+    }
+}
+
+/// Gives every block of `rows` pixel mode chunks stacked in a column the one
+/// pair of colors, out of those `allowed` together, that encodes all of its
+/// source pixels with the least error, as attribute-based screens need.
+pub fn clash(
+    image: &DynamicImage,
+    palettes: &Palettes,
+    chunks: &mut [Chunk],
+    rows: usize,
+    allowed: impl Fn(u8, u8) -> bool,
+) {
+    let ok_palette: Vec<Oklab> = palettes.rgba.iter().map(rgba_to_oklab).collect();
+    let pixels = source_pixels(image);
+    let lightness: Vec<f32> = ok_palette.iter().map(|color| color.l).collect();
+    let columns = image.width() as usize / 8;
+    let candidates: Vec<u8> = (0..palettes.count as u8).collect();
+    let pairs: Vec<(u8, u8)> = candidates
+        .iter()
+        .flat_map(|a| candidates.iter().map(move |b| (*a, *b)))
+        .filter(|(a, b)| a <= b && allowed(*a, *b))
+        .collect();
+    for block_row in (0..chunks.len() / columns.max(1)).step_by(rows) {
+        for column in 0..columns {
+            let indexes: Vec<usize> = (block_row..(block_row + rows).min(chunks.len() / columns))
+                .map(|row| row * columns + column)
+                .collect();
+            let distances: Vec<[[f32; 8]; 16]> = indexes
+                .iter()
+                .map(|index| pixel_distances(&ok_palette, &pixels[index * 8..index * 8 + 8]))
+                .collect();
+            let error = |(a, b): (u8, u8)| -> f32 {
+                distances
+                    .iter()
+                    .flat_map(|distances| {
+                        (0..8).map(|pixel| {
+                            distances[a as usize][pixel].min(distances[b as usize][pixel])
+                        })
+                    })
+                    .sum()
+            };
+            let (a, b) = pairs
+                .iter()
+                .copied()
+                .min_by(|x, y| error(*x).total_cmp(&error(*y)))
+                .expect("pair");
+            for (index, distances) in indexes.iter().zip(distances.iter()) {
+                let codepoint = (0..8).fold(0, |codepoint, pixel| {
+                    codepoint << 1
+                        | u8::from(distances[b as usize][pixel] < distances[a as usize][pixel])
+                });
+                let mut chunk = orient(a, b, codepoint);
+                chunk.canonicalize(&lightness);
+                chunks[*index] = chunk;
+            }
+        }
+    }
+}
+
+/// How chunks are chosen from the quantized image.
+pub struct Encoding {
+    pub strategy: ChunkOpt,
+    pub diffuse: Option<f32>,
+    pub dither: bool,
+    pub compress_bias: Option<f32>,
+}
+
+pub fn break_into_chunks(
+    image: &DynamicImage,
+    palettes: &Palettes,
+    indexes: Vec<u8>,
+    encoding: &Encoding,
+) -> Vec<Chunk> {
+    let opt = encoding.strategy;
+    let mut chunks = if let Some(strength) = encoding.diffuse {
+        break_with_diffusion(palettes, image, opt, strength)
+    } else {
+        match opt {
+            ChunkOpt::Frequency => break_by_frequency(palettes, indexes),
+            ChunkOpt::Error => break_by_error(palettes, indexes),
+            ChunkOpt::Exhaustive => break_exhaustively(palettes, image),
+        }
+    };
+    if encoding.dither {
+        dither_chunks(palettes, image, &mut chunks);
+    }
+    let lightness: Vec<f32> = palettes
+        .rgba
+        .iter()
+        .map(|color| rgba_to_oklab(color).l)
+        .collect();
+    for chunk in chunks.iter_mut() {
+        chunk.canonicalize(&lightness);
+    }
+    if let Some(tolerance) = encoding.compress_bias {
+        bias_runs(image, palettes, &mut chunks, tolerance, &lightness);
+    }
+    chunks
+}
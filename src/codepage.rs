@@ -0,0 +1,153 @@
+use crate::chunks::Chunk;
+use crate::cp437;
+use crate::font::Font;
+use clap::ValueEnum;
+
+/// The character set text outputs without a font of their own are encoded
+/// in, for the viewers they're made for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Codepage {
+    /// The IBM PC characters the fonts and conversions are drawn in
+    #[default]
+    Cp437,
+    /// DOS Latin-1, trading some box drawing for accented letters
+    Cp850,
+    /// DOS Cyrillic, trading the accented letters for Russian ones
+    Cp866,
+    /// Unicode, for terminals rather than DOS viewers
+    Utf8,
+}
+
+const CP850: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ', 'Æ',
+    'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', 'ø', '£', 'Ø', '×', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ',
+    'ª', 'º', '¿', '®', '¬', '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', 'Á', 'Â', 'À', '©',
+    '╣', '║', '╗', '╝', '¢', '¥', '┐', '└', '┴', '┬', '├', '─', '┼', 'ã', 'Ã', '╚', '╔', '╩', '╦',
+    '╠', '═', '╬', '¤', 'ð', 'Ð', 'Ê', 'Ë', 'È', 'ı', 'Í', 'Î', 'Ï', '┘', '┌', '█', '▄', '¦', 'Ì',
+    '▀', 'Ó', 'ß', 'Ô', 'Ò', 'õ', 'Õ', 'µ', 'þ', 'Þ', 'Ú', 'Û', 'Ù', 'ý', 'Ý', '¯', '´', '\u{ad}',
+    '±', '‗', '¾', '¶', '§', '÷', '¸', '°', '¨', '·', '¹', '³', '²', '■', '\u{a0}',
+];
+
+const CP866: [char; 128] = [
+    'А', 'Б', 'В', 'Г', 'Д', 'Е', 'Ж', 'З', 'И', 'Й', 'К', 'Л', 'М', 'Н', 'О', 'П', 'Р', 'С', 'Т',
+    'У', 'Ф', 'Х', 'Ц', 'Ч', 'Ш', 'Щ', 'Ъ', 'Ы', 'Ь', 'Э', 'Ю', 'Я', 'а', 'б', 'в', 'г', 'д', 'е',
+    'ж', 'з', 'и', 'й', 'к', 'л', 'м', 'н', 'о', 'п', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕',
+    '╣', '║', '╗', '╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦',
+    '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐',
+    '▀', 'р', 'с', 'т', 'у', 'ф', 'х', 'ц', 'ч', 'ш', 'щ', 'ъ', 'ы', 'ь', 'э', 'ю', 'я', 'Ё', 'ё',
+    'Є', 'є', 'Ї', 'ї', 'Ў', 'ў', '°', '∙', '·', '√', '№', '¤', '■', '\u{a0}',
+];
+
+impl Codepage {
+    /// The number SAUCE font names give a codepage other than 437 by, as in
+    /// "IBM VGA 866".
+    pub fn number(self) -> Option<&'static str> {
+        match self {
+            Codepage::Cp850 => Some("850"),
+            Codepage::Cp866 => Some("866"),
+            Codepage::Cp437 | Codepage::Utf8 => None,
+        }
+    }
+
+    // The upper half of a single-byte codepage other than 437, whose lower
+    // half is shared with it.
+    fn upper_half(self) -> Option<&'static [char; 128]> {
+        match self {
+            Codepage::Cp850 => Some(&CP850),
+            Codepage::Cp866 => Some(&CP866),
+            Codepage::Cp437 | Codepage::Utf8 => None,
+        }
+    }
+
+    /// How to re-encode cells of CP437 codepoints drawn in `font` in this
+    /// codepage.
+    pub fn recoding(self, font: &Font) -> Recoding {
+        let Some(upper_half) = self.upper_half() else {
+            return Recoding {
+                font: font.clone(),
+                bytes: (0..=255).collect(),
+                exact: vec![true; 256],
+            };
+        };
+        // The CP437 codepoint each byte of the codepage draws, if any.
+        let sources: Vec<Option<u8>> = (0..=255u8)
+            .map(|byte| match byte {
+                0x00..=0x7f => Some(byte),
+                _ => cp437::from_char(upper_half[byte as usize - 0x80]),
+            })
+            .collect();
+        let difference = |a: u8, b: u8| -> u32 {
+            font.glyph(a)
+                .iter()
+                .zip(font.glyph(b))
+                .map(|(a, b)| (a ^ b).count_ones())
+                .sum()
+        };
+        let bytes: Vec<u8> = (0..=255u8)
+            .map(|codepoint| {
+                let exact = sources.iter().position(|source| *source == Some(codepoint));
+                exact.map_or_else(
+                    || {
+                        // Substitutes are never control codes.
+                        (0x20..=0xff)
+                            .filter_map(|byte| Some((byte, sources[byte as usize]?)))
+                            .min_by_key(|(_, source)| difference(codepoint, *source))
+                            .expect("codepoints")
+                            .0
+                    },
+                    |byte| byte as u8,
+                )
+            })
+            .collect();
+        let exact = (0..=255u8)
+            .map(|codepoint| {
+                let source = sources[bytes[codepoint as usize] as usize].unwrap();
+                difference(codepoint, source) == 0
+            })
+            .collect();
+        let height = font.height as usize;
+        let glyphs = (0..font.glyph_count())
+            .flat_map(|index| match sources[index % 256] {
+                Some(source) => font.glyph_at(index / 256 * 256 + source as usize).to_vec(),
+                None => vec![0; height],
+            })
+            .collect();
+        Recoding {
+            font: Font {
+                height: font.height,
+                glyphs,
+            },
+            bytes,
+            exact,
+        }
+    }
+}
+
+/// The byte of a codepage for each CP437 codepoint. Characters the codepage
+/// lacks take the printable byte whose glyph differs from theirs in the
+/// fewest pixels.
+pub struct Recoding {
+    /// The font reordered to the codepage, blank where CP437 lacks a
+    /// character.
+    pub font: Font,
+    bytes: Vec<u8>,
+    exact: Vec<bool>,
+}
+
+impl Recoding {
+    /// The cells in the codepage, and how many of them now look different.
+    pub fn cells(&self, chunks: &[Chunk]) -> (Vec<Chunk>, usize) {
+        let cells = chunks
+            .iter()
+            .map(|chunk| Chunk {
+                codepoint: self.bytes[chunk.codepoint as usize],
+                ..*chunk
+            })
+            .collect();
+        let different = chunks
+            .iter()
+            .filter(|chunk| !self.exact[chunk.codepoint as usize])
+            .count();
+        (cells, different)
+    }
+}
@@ -0,0 +1,73 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use imagequant::RGBA;
+use std::path::Path;
+
+// The viewer assembled from stub/view.s, ending in a zeroed header.
+const STUB: &[u8] = include_bytes!("../stub/view.com");
+const HEADER: usize = 83;
+
+/// Where DOS loads a .COM program in its segment.
+const ORIGIN: usize = 0x100;
+
+/// Most bytes a .COM program may hold, leaving its segment room for a stack.
+pub const MAX_SIZE: usize = 0xfe00;
+
+/// Text mode's screen is 80 columns wide and 400 scanlines tall.
+pub const COLUMNS: usize = 80;
+const SCANLINES: usize = 400;
+
+/// Extensions written as DOS programs rather than XBIN.
+pub fn is_com_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("com"))
+}
+
+/// Size of the program showing `cells` cells in `font`.
+pub fn size(font: &Font, cells: usize) -> usize {
+    STUB.len() + font.glyphs.len() + cells * 2
+}
+
+/// Writes a DOS program that sets text mode, loads the font, palette and
+/// 8-pixel characters, and shows the cells until a key other than up or down
+/// is pressed, scrolling them when taller than the screen. A 512-glyph font
+/// maps its foregrounds to entries 0-7, as bit 3 picks the bank, which also
+/// dims bright backgrounds.
+pub fn save_com(
+    path: impl AsRef<Path>,
+    columns: usize,
+    font: &Font,
+    palette_vga: &[RGBA],
+    chunks: &[Chunk],
+) {
+    let height = font.height as usize;
+    let rows = chunks.len() / columns.max(1);
+    let shown_rows = rows.min(SCANLINES / height).max(1);
+    let font_offset = ORIGIN + STUB.len();
+    let banks = font.glyph_count() / 256;
+    let mut com = STUB[..STUB.len() - HEADER].to_vec();
+    com.extend_from_slice(&[font.height, banks as u8]);
+    for word in [
+        (16 / height).max(1),
+        columns * 2,
+        columns.min(COLUMNS),
+        shown_rows,
+        rows - shown_rows,
+        font_offset,
+        font_offset + 256 * height,
+        font_offset + font.glyphs.len(),
+    ] {
+        com.extend_from_slice(&(word as u16).to_le_bytes());
+    }
+    // Attribute controller registers for each entry, then the border.
+    com.extend((0..16).map(|index| if banks > 1 { index & 7 } else { index }));
+    com.push(0);
+    for color in &palette_vga[..16] {
+        com.extend_from_slice(&[color.r, color.g, color.b]);
+    }
+    com.extend_from_slice(&font.glyphs);
+    for chunk in chunks {
+        com.extend_from_slice(&[chunk.codepoint, chunk.bg << 4 | chunk.fg]);
+    }
+    std::fs::write(path, com).unwrap();
+}
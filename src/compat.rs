@@ -0,0 +1,56 @@
+use clap::ValueEnum;
+
+/// A viewer whose XBIN support output should stay within.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Viewer {
+    Pablodraw,
+    Moebius,
+    Syncterm,
+    Ansilove,
+}
+
+/// The optional XBIN features a viewer reliably shows.
+pub struct Support {
+    pub chars_512: bool,
+    pub ice: bool,
+    pub compression: bool,
+    /// Font heights the viewer draws, or None for all of XBIN's 1 to 32.
+    pub heights: Option<&'static [u8]>,
+}
+
+impl Viewer {
+    pub fn support(self) -> Support {
+        match self {
+            Viewer::Pablodraw => Support {
+                chars_512: false,
+                ice: true,
+                compression: true,
+                heights: None,
+            },
+            Viewer::Moebius => Support {
+                chars_512: false,
+                ice: true,
+                compression: true,
+                heights: Some(&[8, 14, 16]),
+            },
+            Viewer::Syncterm => Support {
+                chars_512: false,
+                ice: true,
+                compression: true,
+                heights: Some(&[8, 14, 16]),
+            },
+            Viewer::Ansilove => Support {
+                chars_512: true,
+                ice: true,
+                compression: true,
+                heights: None,
+            },
+        }
+    }
+}
+
+impl Support {
+    pub fn allows_height(&self, height: u8) -> bool {
+        self.heights.is_none_or(|heights| heights.contains(&height))
+    }
+}
@@ -0,0 +1,100 @@
+use clap::ValueEnum;
+use imagequant::RGBA;
+use oklab::{linear_srgb_to_oklab, oklab_to_linear_srgb, oklab_to_srgb, srgb_to_oklab, Oklab};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Deficiency {
+    Deutan,
+    Protan,
+    Tritan,
+}
+
+impl Deficiency {
+    // Machado, Oliveira & Fernandes (2009) simulation matrices at full severity,
+    // applied to linear sRGB.
+    fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            Deficiency::Protan => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Deficiency::Deutan => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Deficiency::Tritan => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+
+    fn simulate(self, color: Oklab) -> Oklab {
+        let rgb = oklab_to_linear_srgb(color);
+        let m = self.matrix();
+        let simulated = oklab::RGB {
+            r: (m[0][0] * rgb.r + m[0][1] * rgb.g + m[0][2] * rgb.b).clamp(0.0, 1.0),
+            g: (m[1][0] * rgb.r + m[1][1] * rgb.g + m[1][2] * rgb.b).clamp(0.0, 1.0),
+            b: (m[2][0] * rgb.r + m[2][1] * rgb.g + m[2][2] * rgb.b).clamp(0.0, 1.0),
+        };
+        linear_srgb_to_oklab(simulated)
+    }
+}
+
+const MIN_DISTANCE: f32 = 0.08;
+const MAX_ITERATIONS: usize = 64;
+
+fn distance(a: &Oklab, b: &Oklab) -> f32 {
+    ((a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)).sqrt()
+}
+
+// Simulation discards a hue axis but keeps lightness, so entries that collapse
+// together for the viewer are pushed apart in lightness until they separate.
+pub fn separate(palette: &mut [RGBA], deficiency: Deficiency) {
+    let mut colors: Vec<Oklab> = palette
+        .iter()
+        .map(|color| {
+            srgb_to_oklab(oklab::RGB {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+            })
+        })
+        .collect();
+    for _ in 0..MAX_ITERATIONS {
+        let simulated: Vec<Oklab> = colors
+            .iter()
+            .map(|color| deficiency.simulate(*color))
+            .collect();
+        let mut moved = false;
+        for i in 0..colors.len() {
+            for j in (i + 1)..colors.len() {
+                let d = distance(&simulated[i], &simulated[j]);
+                if d >= MIN_DISTANCE {
+                    continue;
+                }
+                let step = (MIN_DISTANCE - d) / 2.0;
+                let (lighter, darker) = if colors[i].l >= colors[j].l {
+                    (i, j)
+                } else {
+                    (j, i)
+                };
+                colors[lighter].l = (colors[lighter].l + step).min(1.0);
+                colors[darker].l = (colors[darker].l - step).max(0.0);
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+    for (color, ok) in palette.iter_mut().zip(colors.iter()) {
+        let rgb = oklab_to_srgb(*ok);
+        color.r = rgb.r;
+        color.g = rgb.g;
+        color.b = rgb.b;
+    }
+}
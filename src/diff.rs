@@ -0,0 +1,64 @@
+use crate::chunks::Chunk;
+use crate::render;
+use crate::xbin::Xbin;
+use image::{Rgb, RgbImage};
+
+// The cell at `column` and `row`, if the grid reaches it.
+fn cell(art: &Xbin, column: usize, row: usize) -> Option<&Chunk> {
+    if column < art.columns {
+        art.chunks.get(row * art.columns + column)
+    } else {
+        None
+    }
+}
+
+fn rows(art: &Xbin) -> usize {
+    art.chunks.len() / art.columns.max(1)
+}
+
+/// The size of the grid both fit in, as columns and rows.
+pub fn size(a: &Xbin, b: &Xbin) -> (usize, usize) {
+    (a.columns.max(b.columns), rows(a).max(rows(b)))
+}
+
+/// Where the cells of two grids differ, as a column and row from the top
+/// left, counting those only one grid reaches.
+pub fn differences(a: &Xbin, b: &Xbin) -> Vec<(usize, usize)> {
+    let (columns, rows) = size(a, b);
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .filter(|(column, row)| cell(a, *column, *row) != cell(b, *column, *row))
+        .collect()
+}
+
+/// A cell as its codepoint and attribute in hex, or `none` beyond the grid.
+pub fn describe(art: &Xbin, column: usize, row: usize) -> String {
+    match cell(art, column, row) {
+        Some(chunk) => format!("{:02x}/{:x}{:x}", chunk.codepoint, chunk.bg, chunk.fg),
+        None => "none".to_string(),
+    }
+}
+
+/// The second grid drawn with the cells that match the first's dimmed to a
+/// quarter, and those it doesn't reach in red, so differences stand out.
+pub fn image(a: &Xbin, b: &Xbin) -> RgbImage {
+    let (columns, rows) = size(a, b);
+    let height = b.font.height as usize;
+    let drawn = render::render(b.columns, &b.font, &b.palette, &b.shown());
+    let mut image = RgbImage::new((columns * 8) as u32, (rows * height) as u32);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let (column, row) = (x as usize / 8, y as usize / height);
+        *pixel = match cell(b, column, row) {
+            None => Rgb([255, 0, 0]),
+            Some(chunk) => {
+                let Rgb([red, green, blue]) = *drawn.get_pixel(x, y);
+                if cell(a, column, row) == Some(chunk) {
+                    Rgb([red / 4, green / 4, blue / 4])
+                } else {
+                    Rgb([red, green, blue])
+                }
+            }
+        };
+    }
+    image
+}
@@ -0,0 +1,29 @@
+use crate::chunks::Chunk;
+use crate::cp437;
+
+/// The width FILE_ID.DIZ descriptions are usually kept to, and the most BBS
+/// file lists show.
+pub const COLUMNS: u32 = 44;
+pub const MAX_COLUMNS: u32 = 45;
+
+/// Most lines a description may take, footer included.
+pub const LINES: usize = 30;
+
+/// Rows of cells spelling out `footer` in `fg` on `bg`, each line cut or
+/// padded to `columns` and characters outside CP437 shown as '?'.
+pub fn footer_cells(footer: &[String], columns: usize, fg: u8, bg: u8) -> Vec<Chunk> {
+    footer
+        .iter()
+        .flat_map(|line| {
+            let mut codepoints: Vec<u8> = line
+                .chars()
+                .map(|c| cp437::from_char(c).unwrap_or(b'?'))
+                .take(columns)
+                .collect();
+            codepoints.resize(columns, b' ');
+            codepoints
+                .into_iter()
+                .map(move |codepoint| Chunk { fg, bg, codepoint })
+        })
+        .collect()
+}
@@ -0,0 +1,41 @@
+use crate::chunks::Chunk;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Writes every cell's position, attributes, codepoint and residual error as
+/// JSON, or as CSV when the path ends in `.csv`.
+pub fn dump_cells(path: impl AsRef<Path>, columns: usize, chunks: &[Chunk], errors: &[f32]) {
+    let path = path.as_ref();
+    let csv = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    let columns = columns.max(1);
+    if csv {
+        writeln!(file, "column,row,fg,bg,codepoint,error").unwrap();
+    } else {
+        writeln!(file, "[").unwrap();
+    }
+    for (index, (chunk, error)) in chunks.iter().zip(errors.iter()).enumerate() {
+        let (column, row) = (index % columns, index / columns);
+        if csv {
+            writeln!(
+                file,
+                "{},{},{},{},{},{}",
+                column, row, chunk.fg, chunk.bg, chunk.codepoint, error
+            )
+            .unwrap();
+        } else {
+            let separator = if index + 1 < chunks.len() { "," } else { "" };
+            writeln!(
+                file,
+                "  {{\"column\": {}, \"row\": {}, \"fg\": {}, \"bg\": {}, \"codepoint\": {}, \"error\": {}}}{}",
+                column, row, chunk.fg, chunk.bg, chunk.codepoint, error, separator
+            )
+            .unwrap();
+        }
+    }
+    if !csv {
+        writeln!(file, "]").unwrap();
+    }
+}
@@ -0,0 +1,22 @@
+use image::{Rgb, RgbImage};
+use std::path::Path;
+
+// Black through red and yellow to white, so hot spots stand out even on a
+// mostly well-encoded image.
+fn heat(t: f32) -> Rgb<u8> {
+    let t = t.clamp(0.0, 1.0) * 3.0;
+    let channel = |offset: f32| ((t - offset).clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgb([channel(0.0), channel(1.0), channel(2.0)])
+}
+
+/// Saves a false-color image of per-chunk encoding error, scaled so the worst
+/// chunk is white, with every chunk covering its 8 source pixels.
+pub fn save_error_map(path: impl AsRef<Path>, width: u32, height: u32, errors: &[f32]) {
+    let max = errors.iter().cloned().fold(0.0, f32::max);
+    let mut map = RgbImage::new(width, height);
+    for (index, pixel) in map.pixels_mut().enumerate() {
+        let error = errors.get(index / 8).copied().unwrap_or(0.0);
+        *pixel = heat(if max > 0.0 { (error / max).sqrt() } else { 0.0 });
+    }
+    map.save(path).unwrap();
+}
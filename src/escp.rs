@@ -0,0 +1,87 @@
+use image::RgbImage;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Dots across an 8-inch line in CRT I graphics, which at 80 by 72 dpi
+/// gives dots close to the shape of a VGA text mode pixel.
+pub const MAX_DOTS: usize = 640;
+const DENSITY: u8 = 4;
+
+// The pins fired in one pass of a 9-pin head's graphics.
+const PINS: usize = 8;
+
+/// Extensions written as ESC/P printer data rather than XBIN.
+pub fn is_escp_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("prn"))
+}
+
+// Ink coverage for each dot, Floyd-Steinberg dithered from the darkness of
+// the rendering with rows repeated `repeat` times.
+fn dots(rendering: &RgbImage, repeat: usize) -> Vec<Vec<bool>> {
+    let width = (rendering.width() as usize).min(MAX_DOTS);
+    let mut darkness: Vec<Vec<f32>> = rendering
+        .rows()
+        .flat_map(|row| {
+            let row: Vec<f32> = row
+                .take(width)
+                .map(|pixel| {
+                    let [r, g, b] = pixel.0.map(f32::from);
+                    1.0 - (0.299 * r + 0.587 * g + 0.114 * b) / 255.0
+                })
+                .collect();
+            std::iter::repeat_n(row, repeat)
+        })
+        .collect();
+    let height = darkness.len();
+    let mut dots = vec![vec![false; width]; height];
+    for y in 0..height {
+        for x in 0..width {
+            let value = darkness[y][x];
+            dots[y][x] = value >= 0.5;
+            let error = value - if dots[y][x] { 1.0 } else { 0.0 };
+            if x + 1 < width {
+                darkness[y][x + 1] += error * 7.0 / 16.0;
+            }
+            if y + 1 < height {
+                if x > 0 {
+                    darkness[y + 1][x - 1] += error * 3.0 / 16.0;
+                }
+                darkness[y + 1][x] += error * 5.0 / 16.0;
+                if x + 1 < width {
+                    darkness[y + 1][x + 1] += error / 16.0;
+                }
+            }
+        }
+    }
+    dots
+}
+
+/// Writes a rendering of the cells as Epson ESC/P bit image graphics for
+/// 9-pin dot-matrix printers, dithered to black ink on the paper's white, in
+/// 8-dot passes fed 8/72 inch apart. Rows are printed `pixel_height` times to
+/// keep taller pixels' shape, and dots past the 8-inch line are cut.
+pub fn save_escp(path: impl AsRef<Path>, rendering: &RgbImage, pixel_height: f32) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    let dots = dots(rendering, (pixel_height.round() as usize).max(1));
+    // Reset, then 24/216 inch line spacing.
+    file.write_all(b"\x1b@\x1b3\x18").unwrap();
+    for band in dots.chunks(PINS) {
+        let width = band[0].len();
+        file.write_all(&[0x1b, b'*', DENSITY]).unwrap();
+        file.write_all(&(width as u16).to_le_bytes()).unwrap();
+        // Each column is a byte with the top pin in the high bit.
+        let columns: Vec<u8> = (0..width)
+            .map(|x| {
+                band.iter()
+                    .enumerate()
+                    .filter(|(_, row)| row[x])
+                    .fold(0, |byte, (pin, _)| byte | 0x80 >> pin)
+            })
+            .collect();
+        file.write_all(&columns).unwrap();
+        file.write_all(b"\r\n").unwrap();
+    }
+    // Back to 1/6 inch lines, and eject the page.
+    file.write_all(b"\x1b2\x0c").unwrap();
+}
@@ -0,0 +1,165 @@
+use crate::chunks::Chunk;
+use crate::gz;
+use crate::psf;
+use std::path::Path;
+
+/// An 8-pixel-wide bitmap font of 256 or 512 glyphs, one byte per glyph row,
+/// most significant bit leftmost.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Font {
+    pub height: u8,
+    pub glyphs: Vec<u8>,
+}
+
+impl Font {
+    /// The 1-pixel-high font the pixel mode relies on, where every codepoint's
+    /// single row is its own bit pattern.
+    pub fn pixel() -> Self {
+        Font {
+            height: 1,
+            glyphs: (0..=255).collect(),
+        }
+    }
+
+    pub fn ibm_vga_8x16() -> Self {
+        Font {
+            height: 16,
+            glyphs: include_bytes!("../fonts/ibm-vga-8x16.f16").to_vec(),
+        }
+    }
+
+    pub fn ibm_vga_8x14() -> Self {
+        Font {
+            height: 14,
+            glyphs: include_bytes!("../fonts/ibm-vga-8x14.f14").to_vec(),
+        }
+    }
+
+    pub fn ibm_vga_8x8() -> Self {
+        Font {
+            height: 8,
+            glyphs: include_bytes!("../fonts/ibm-vga-8x8.f08").to_vec(),
+        }
+    }
+
+    /// One of the fonts built into the binary, by name.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "ibm-vga-8x16" => Some(Font::ibm_vga_8x16()),
+            "ibm-vga-8x14" => Some(Font::ibm_vga_8x14()),
+            "ibm-vga-8x8" => Some(Font::ibm_vga_8x8()),
+            _ => None,
+        }
+    }
+
+    /// Looks up a built-in font by name, or reads a Linux console PSF font,
+    /// or else a raw font dump of 256 glyphs as saved by DOS font editors,
+    /// checking the height against a .Fnn extension when there is one. Either
+    /// may be gzipped.
+    pub fn load(path: &str) -> Result<Self, String> {
+        if let Some(font) = Font::named(path) {
+            return Ok(font);
+        }
+        let glyphs = gz::read(path).map_err(|error| format!("{path}: {error}"))?;
+        if psf::is_psf(&glyphs) {
+            return psf::parse(&glyphs).map_err(|error| format!("{path}: {error}"));
+        }
+        let named = gz::inner(Path::new(path))
+            .unwrap_or(Path::new(path).to_path_buf())
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| extension.strip_prefix(['f', 'F']))
+            .and_then(|digits| digits.parse::<u8>().ok())
+            .filter(|height| (1..=32).contains(height));
+        // Only a named height tells a 512-glyph dump from a taller 256 one.
+        if let Some(named) = named {
+            if glyphs.len() == 512 * named as usize {
+                return Ok(Font {
+                    height: named,
+                    glyphs,
+                });
+            }
+        }
+        if glyphs.is_empty() || glyphs.len() % 256 != 0 || glyphs.len() > 256 * 32 {
+            return Err(format!(
+                "{path}: {} bytes is not 256 glyphs of 1 to 32 rows",
+                glyphs.len()
+            ));
+        }
+        let height = (glyphs.len() / 256) as u8;
+        if let Some(named) = named {
+            if named != height {
+                return Err(format!(
+                    "{path}: extension says {named} rows but the file holds {height}"
+                ));
+            }
+        }
+        Ok(Font { height, glyphs })
+    }
+
+    pub fn glyph_count(&self) -> usize {
+        self.glyphs.len() / self.height as usize
+    }
+
+    pub fn glyph_at(&self, index: usize) -> &[u8] {
+        let height = self.height as usize;
+        &self.glyphs[index * height..(index + 1) * height]
+    }
+
+    /// The glyph for a codepoint in the first bank.
+    pub fn glyph(&self, codepoint: u8) -> &[u8] {
+        self.glyph_at(codepoint as usize)
+    }
+
+    /// The glyph and foreground palette entry a viewer draws a cell with. In a
+    /// 512-glyph font bit 3 of the foreground picks the bank rather than the
+    /// color, as in the XBIN attribute byte.
+    pub fn cell_glyph(&self, chunk: &Chunk) -> (&[u8], u8) {
+        if self.glyph_count() > 256 {
+            let index = ((chunk.fg as usize & 0x08) << 5) + chunk.codepoint as usize;
+            (self.glyph_at(index), chunk.fg & 0x07)
+        } else {
+            (self.glyph(chunk.codepoint), chunk.fg)
+        }
+    }
+
+    /// The font with `other`'s first 256 glyphs appended as a second bank.
+    pub fn with_second_bank(mut self, other: &Font) -> Result<Self, String> {
+        if other.height != self.height {
+            return Err(format!(
+                "the second font is {} rows high but the first is {}",
+                other.height, self.height
+            ));
+        }
+        self.glyphs.truncate(256 * self.height as usize);
+        self.glyphs
+            .extend_from_slice(&other.glyphs[..256 * other.height as usize]);
+        Ok(self)
+    }
+
+    /// The font without a second bank.
+    pub fn first_bank(mut self) -> Self {
+        self.glyphs.truncate(256 * self.height as usize);
+        self
+    }
+
+    pub fn is_solid(&self, codepoint: u8) -> bool {
+        self.glyph(codepoint).iter().all(|row| *row == 0xff)
+    }
+
+    /// For every codepoint, the first `allowed` codepoint whose glyph is its
+    /// exact negative, which shows the same cell with fg and bg swapped.
+    pub fn inverses(&self, allowed: impl Fn(u8) -> bool) -> Vec<Option<u8>> {
+        (0..=255)
+            .map(|codepoint| {
+                let glyph = self.glyph(codepoint);
+                (0..=255).filter(|other| allowed(*other)).find(|other| {
+                    self.glyph(*other)
+                        .iter()
+                        .zip(glyph.iter())
+                        .all(|(a, b)| *a == !*b)
+                })
+            })
+            .collect()
+    }
+}
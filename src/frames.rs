@@ -0,0 +1,155 @@
+use crate::gz;
+use image::codecs::gif::GifDecoder;
+use image::io::Reader;
+use image::{AnimationDecoder, DynamicImage, ImageFormat};
+use std::io::Cursor;
+use std::path::Path;
+use std::time::Duration;
+
+/// Opens an image by its contents, or failing that its extension, after
+/// decompressing it if it's gzipped.
+pub fn open(input: &Path) -> DynamicImage {
+    let bytes = gz::read(input).unwrap();
+    let named = gz::inner(input).unwrap_or(input.to_path_buf());
+    let mut reader = Reader::new(Cursor::new(bytes));
+    if let Ok(format) = ImageFormat::from_path(named) {
+        reader.set_format(format);
+    }
+    reader.with_guessed_format().unwrap().decode().unwrap()
+}
+
+/// The frames of a GIF source with how long each shows, or for any other
+/// source its one image shown for no time.
+pub fn source_frames(input: &Path) -> Vec<(Duration, DynamicImage)> {
+    let bytes = gz::read(input).unwrap();
+    match GifDecoder::new(Cursor::new(bytes)) {
+        Ok(decoder) => decoder
+            .into_frames()
+            .map(|frame| {
+                let frame = frame.unwrap();
+                (
+                    Duration::from(frame.delay()),
+                    DynamicImage::ImageRgba8(frame.into_buffer()),
+                )
+            })
+            .collect(),
+        Err(_) => vec![(Duration::ZERO, open(input))],
+    }
+}
+
+/// Which frame shows at each tick of a steady `fps`, repeating frames that
+/// last longer than a tick and dropping those that end before the next one.
+/// Frames that show for no time still get a tick each.
+pub fn at_rate(durations: &[Duration], fps: f64) -> Vec<usize> {
+    let total: Duration = durations.iter().sum();
+    if total.is_zero() {
+        return (0..durations.len()).collect();
+    }
+    let ticks = (total.as_secs_f64() * fps).ceil().max(1.0) as usize;
+    let mut ends = durations.iter().scan(Duration::ZERO, |end, duration| {
+        *end += *duration;
+        Some(*end)
+    });
+    let mut frame = 0;
+    let mut end = ends.next().unwrap();
+    (0..ticks)
+        .map(|tick| {
+            let time = Duration::from_secs_f64(tick as f64 / fps);
+            while time >= end && frame + 1 < durations.len() {
+                frame += 1;
+                end = ends.next().unwrap();
+            }
+            frame
+        })
+        .collect()
+}
+
+/// Delays for particular frames, read from a file of lines each giving a
+/// frame number from 1 and its delay in seconds, with `#` starting a comment.
+#[derive(Clone, Debug)]
+pub struct Timing(pub Vec<(usize, Duration)>);
+
+impl Timing {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut delays = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || format!("line {} of {path} is not FRAME SECONDS", number + 1);
+            let (frame, seconds) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let frame = frame.parse::<usize>().ok().filter(|frame| *frame > 0);
+            let seconds = seconds.trim().parse::<f64>().ok();
+            match (
+                frame,
+                seconds.and_then(|s| Duration::try_from_secs_f64(s).ok()),
+            ) {
+                (Some(frame), Some(delay)) => delays.push((frame - 1, delay)),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Timing(delays))
+    }
+
+    /// The delay given for frame `index`, if any, the last one given winning.
+    pub fn delay(&self, index: usize) -> Option<Duration> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(frame, _)| *frame == index)
+            .map(|(_, delay)| *delay)
+    }
+}
+
+/// Frames paced to a steady `fps` as `at_rate` would show them, each kept for
+/// as many ticks as it shows and dropped if it shows for none.
+pub fn paced<T>(frames: Vec<(Duration, T)>, fps: f64) -> Vec<(Duration, T)> {
+    let durations: Vec<Duration> = frames.iter().map(|(duration, _)| *duration).collect();
+    let ticks = at_rate(&durations, fps);
+    frames
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, (_, frame))| {
+            let count = ticks.iter().filter(|tick| **tick == index).count();
+            (count > 0).then(|| (Duration::from_secs_f64(count as f64 / fps), frame))
+        })
+        .collect()
+}
+
+/// Frames with each run of ones `alike` the first of the run merged into it,
+/// which shows for the whole run.
+pub fn merged<T>(frames: Vec<(Duration, T)>, alike: impl Fn(&T, &T) -> bool) -> Vec<(Duration, T)> {
+    let mut merged: Vec<(Duration, T)> = Vec::with_capacity(frames.len());
+    for (duration, frame) in frames {
+        match merged.last_mut() {
+            Some((kept, first)) if alike(first, &frame) => *kept += duration,
+            _ => merged.push((duration, frame)),
+        }
+    }
+    merged
+}
+
+/// How many times an animation plays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Plays {
+    Times(u16),
+    Forever,
+}
+
+impl Plays {
+    /// Parses a count of at least 1, or `infinite`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if value.eq_ignore_ascii_case("infinite") {
+            return Ok(Plays::Forever);
+        }
+        match value.parse::<u16>() {
+            Ok(times) if times > 0 => Ok(Plays::Times(times)),
+            _ => Err(format!(
+                "{value:?} is not infinite or a count from 1 to {}",
+                u16::MAX
+            )),
+        }
+    }
+}
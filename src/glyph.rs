@@ -0,0 +1,305 @@
+use crate::charset::Charset;
+use crate::chunks::{rgba_to_oklab, Chunk};
+use crate::font::Font;
+use crate::palette::Palettes;
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView};
+use oklab::{srgb_to_oklab, Oklab};
+
+const WIDTH: usize = 8;
+
+// Weight of a cell's lightness SSIM loss, in mean squared Oklab error per
+// pixel, chosen so a clear edge match outweighs a small color drift.
+const STRUCTURE_WEIGHT: f32 = 0.05;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum GlyphMetric {
+    /// Sum of per-pixel Oklab errors
+    #[default]
+    Pixel,
+    /// Per-pixel error plus a per-cell lightness SSIM term, favoring glyphs
+    /// whose edges line up with the source
+    Structure,
+}
+
+fn distance(a: &Oklab, b: &Oklab) -> f32 {
+    (a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)
+}
+
+fn nearest(ok_palette: &[Oklab], color: &Oklab) -> u8 {
+    (0..ok_palette.len())
+        .min_by(|a, b| {
+            distance(&ok_palette[*a], color).total_cmp(&distance(&ok_palette[*b], color))
+        })
+        .expect("palette") as u8
+}
+
+/// Codepoints worth trying, without glyphs that duplicate an earlier one.
+/// Printable characters come first so they win over identical control or
+/// extended glyphs.
+pub fn distinct_glyphs(font: &Font) -> Vec<u8> {
+    let order = (0x20..=0x7e)
+        .chain(0x80..=0xff)
+        .chain(0x01..=0x1f)
+        .chain([0x7f, 0x00]);
+    let mut glyphs: Vec<u8> = Vec::new();
+    for codepoint in order {
+        if !glyphs
+            .iter()
+            .any(|other| font.glyph(*other) == font.glyph(codepoint))
+        {
+            glyphs.push(codepoint);
+        }
+    }
+    glyphs
+}
+
+#[derive(Clone, Copy)]
+struct Sum {
+    l: f32,
+    a: f32,
+    b: f32,
+    count: f32,
+}
+
+impl Sum {
+    fn add(&mut self, pixel: &Oklab) {
+        self.l += pixel.l;
+        self.a += pixel.a;
+        self.b += pixel.b;
+        self.count += 1.0;
+    }
+
+    fn mean(&self) -> Oklab {
+        Oklab {
+            l: self.l / self.count,
+            a: self.a / self.count,
+            b: self.b / self.count,
+        }
+    }
+}
+
+// Lightness statistics of a source block.
+struct Lightness {
+    count: f32,
+    mean: f32,
+    variance: f32,
+}
+
+impl Lightness {
+    fn new(block: &[Oklab]) -> Self {
+        let count = block.len() as f32;
+        let mean = block.iter().map(|pixel| pixel.l).sum::<f32>() / count;
+        let variance = block
+            .iter()
+            .map(|pixel| (pixel.l - mean).powi(2))
+            .sum::<f32>()
+            / count;
+        Lightness {
+            count,
+            mean,
+            variance,
+        }
+    }
+}
+
+// SSIM between the source lightness and the glyph drawn in two flat
+// lightnesses. The drawing only takes two values, so its mean, variance and
+// covariance with the source follow from the count and lightness sum of the
+// source pixels under the glyph.
+fn ssim(source: &Lightness, on: &Sum, fg: f32, bg: f32) -> f32 {
+    const C1: f32 = 0.01 * 0.01;
+    const C2: f32 = 0.03 * 0.03;
+    let coverage = on.count / source.count;
+    let mean = bg + coverage * (fg - bg);
+    let variance = coverage * (1.0 - coverage) * (fg - bg).powi(2);
+    let covariance = (fg - bg) * (on.l - on.count * source.mean) / source.count;
+    ((2.0 * source.mean * mean + C1) * (2.0 * covariance + C2))
+        / ((source.mean.powi(2) + mean.powi(2) + C1) * (source.variance + variance + C2))
+}
+
+// For a fixed glyph, the palette entry minimizing the squared error over a set
+// of pixels is the one nearest the set's mean, so every glyph only needs its
+// two means matched rather than all 16x16 color pairs tried. Candidates are
+// glyph indices; with a 512-glyph font the foreground comes from the first 8
+// entries and bit 3 of fg selects the glyph's bank. A `background` shared by
+// every cell takes the place of the nearest entry.
+fn match_cell(
+    block: &[Oklab],
+    font: &Font,
+    candidates: &[usize],
+    ok_palette: &[Oklab],
+    metric: GlyphMetric,
+    background: Option<u8>,
+) -> (Chunk, f32) {
+    let height = font.height as usize;
+    let mut total = Sum {
+        l: 0.0,
+        a: 0.0,
+        b: 0.0,
+        count: 0.0,
+    };
+    for pixel in block {
+        total.add(pixel);
+    }
+    let stats = Lightness::new(block);
+    let fg_palette = if font.glyph_count() > 256 {
+        &ok_palette[..ok_palette.len().min(8)]
+    } else {
+        ok_palette
+    };
+    let mut best: Option<(Chunk, f32)> = None;
+    for index in candidates {
+        let glyph = font.glyph_at(*index);
+        let mut on = Sum {
+            l: 0.0,
+            a: 0.0,
+            b: 0.0,
+            count: 0.0,
+        };
+        for (y, row) in glyph.iter().enumerate().take(height) {
+            for x in 0..WIDTH {
+                if row & (0x80 >> x) != 0 {
+                    on.add(&block[y * WIDTH + x]);
+                }
+            }
+        }
+        let off = Sum {
+            l: total.l - on.l,
+            a: total.a - on.a,
+            b: total.b - on.b,
+            count: total.count - on.count,
+        };
+        let (fg, fg_error) = if on.count > 0.0 {
+            let mean = on.mean();
+            let fg = nearest(fg_palette, &mean);
+            (fg, on.count * distance(&mean, &ok_palette[fg as usize]))
+        } else {
+            (0, 0.0)
+        };
+        let (bg, bg_error) = if off.count > 0.0 {
+            let mean = off.mean();
+            let bg = background.unwrap_or_else(|| nearest(ok_palette, &mean));
+            (bg, off.count * distance(&mean, &ok_palette[bg as usize]))
+        } else {
+            (background.unwrap_or(fg), 0.0)
+        };
+        let fg = if on.count > 0.0 {
+            fg
+        } else {
+            bg % fg_palette.len() as u8
+        };
+        let mut error = fg_error + bg_error;
+        if metric == GlyphMetric::Structure {
+            let (fg_l, bg_l) = (ok_palette[fg as usize].l, ok_palette[bg as usize].l);
+            error += STRUCTURE_WEIGHT * total.count * (1.0 - ssim(&stats, &on, fg_l, bg_l));
+        }
+        if best.is_none_or(|(_, best_error)| error < best_error) {
+            best = Some((
+                Chunk {
+                    fg: fg | ((*index >> 8) as u8) << 3,
+                    bg,
+                    codepoint: *index as u8,
+                },
+                error,
+            ));
+        }
+    }
+    best.expect("candidates")
+}
+
+/// Encodes each 8-pixel-wide, font-height block of the source as the glyph
+/// and fg/bg pair that reproduces it with the least Oklab error, choosing only
+/// from `charset` when one is given and scoring candidates by `metric`. The
+/// second bank of a 512-glyph font is tried too unless a charset is given.
+/// Every cell gets `background` when one is given, as on machines with a
+/// single background color register.
+pub fn encode(
+    palettes: &Palettes,
+    image: &DynamicImage,
+    font: &Font,
+    charset: Option<&Charset>,
+    metric: GlyphMetric,
+    background: Option<u8>,
+) -> Vec<Chunk> {
+    // Entries past the palette's count are padding, not colors in it.
+    let ok_palette: Vec<Oklab> = palettes.rgba[..palettes.count]
+        .iter()
+        .map(rgba_to_oklab)
+        .collect();
+    let allowed = |codepoint: u8| charset.is_none_or(|charset| charset.contains(codepoint));
+    let mut candidates = distinct_glyphs(font);
+    candidates.retain(|codepoint| allowed(*codepoint));
+    if let Some(charset) = charset {
+        // A charset may deliberately name glyphs that duplicate others.
+        for codepoint in charset.0.iter() {
+            if !candidates.contains(codepoint) {
+                candidates.push(*codepoint);
+            }
+        }
+    }
+    let mut candidates: Vec<usize> = candidates.into_iter().map(usize::from).collect();
+    if charset.is_none() {
+        for index in 256..font.glyph_count() {
+            let glyph = font.glyph_at(index);
+            if !candidates
+                .iter()
+                .any(|other| font.glyph_at(*other) == glyph)
+            {
+                candidates.push(index);
+            }
+        }
+    }
+    let wide = font.glyph_count() > 256;
+    let inverses = font.inverses(allowed);
+    let height = font.height as usize;
+    let (width, image_height) = (image.width() as usize, image.height() as usize);
+    let (columns, rows) = (width.div_ceil(WIDTH), image_height.div_ceil(height));
+    let mut chunks = Vec::with_capacity(columns * rows);
+    let mut block = vec![
+        Oklab {
+            l: 0.0,
+            a: 0.0,
+            b: 0.0
+        };
+        WIDTH * height
+    ];
+    for row in 0..rows {
+        for column in 0..columns {
+            for (i, pixel) in block.iter_mut().enumerate() {
+                let x = (column * WIDTH + i % WIDTH).min(width - 1);
+                let y = (row * height + i / WIDTH).min(image_height - 1);
+                let color = image.get_pixel(x as u32, y as u32);
+                *pixel = srgb_to_oklab(oklab::RGB {
+                    r: color[0],
+                    g: color[1],
+                    b: color[2],
+                });
+            }
+            let (mut chunk, _) =
+                match_cell(&block, font, &candidates, &ok_palette, metric, background);
+            let (glyph, fg) = font.cell_glyph(&chunk);
+            let blank = glyph.iter().all(|row| *row == 0x00);
+            if (fg == chunk.bg || blank) && allowed(0x20) {
+                chunk = Chunk {
+                    fg: if wide { chunk.bg & 0x07 } else { chunk.bg },
+                    bg: chunk.bg,
+                    codepoint: 0x20,
+                };
+            } else if ok_palette[fg as usize].l < ok_palette[chunk.bg as usize].l
+                && (!wide || chunk.fg < 8 && chunk.bg < 8)
+                && background.is_none()
+            {
+                if let Some(inverse) = inverses[chunk.codepoint as usize] {
+                    chunk = Chunk {
+                        fg: chunk.bg,
+                        bg: chunk.fg,
+                        codepoint: inverse,
+                    };
+                }
+            }
+            chunks.push(chunk);
+        }
+    }
+    chunks
+}
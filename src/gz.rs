@@ -0,0 +1,70 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The path a gzipped file would have uncompressed, as `art.xb` for
+/// `art.xb.gz`, if it ends in .gz.
+pub fn inner(path: &Path) -> Option<PathBuf> {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+        .then(|| path.with_extension(""))
+}
+
+/// Reads a file, decompressing it first if it's gzipped.
+pub fn read(path: impl AsRef<Path>) -> std::io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    if !bytes.starts_with(&MAGIC) {
+        return Ok(bytes);
+    }
+    let mut inflated = Vec::new();
+    GzDecoder::new(bytes.as_slice()).read_to_end(&mut inflated)?;
+    Ok(inflated)
+}
+
+/// A gzipped output being written uncompressed to a temporary file first, so
+/// writers can append to it as usual, and compressed to its real path by
+/// `finish`. Dropping it unfinished discards what was written.
+pub struct Pending {
+    pub temporary: PathBuf,
+    pub output: PathBuf,
+}
+
+impl Pending {
+    /// A pending output for `output` if it ends in .gz, its temporary file
+    /// keeping the inner extension that picks a format.
+    pub fn for_output(output: &Path) -> Option<Self> {
+        let name = inner(output)?.file_name()?.to_os_string();
+        let mut temporary = std::env::temp_dir();
+        temporary.push(format!("img2xbin-{}-", std::process::id()));
+        temporary.as_mut_os_string().push(name);
+        Some(Pending {
+            temporary,
+            output: output.to_path_buf(),
+        })
+    }
+
+    /// Compresses whatever was written to the real path, if anything was, and
+    /// removes the temporary file.
+    pub fn finish(self) -> std::io::Result<()> {
+        let bytes = match std::fs::read(&self.temporary) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        let file = std::fs::File::create(&self.output)?;
+        let mut encoder = GzEncoder::new(file, Compression::best());
+        encoder.write_all(&bytes)?;
+        encoder.finish()?;
+        Ok(())
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.temporary);
+    }
+}
@@ -0,0 +1,48 @@
+use crate::chunks::{rgba_to_oklab, Chunk};
+use crate::palette::Palettes;
+
+const SPACE: u8 = 0x20;
+const LOWER_HALF: u8 = 0xdc;
+const UPPER_HALF: u8 = 0xdf;
+
+/// Encodes two vertically adjacent pixels per cell with the half-block
+/// characters, keeping the darker color in the background.
+pub fn encode(palettes: &Palettes, indexes: &[u8], width: usize, height: usize) -> Vec<Chunk> {
+    let lightness: Vec<f32> = palettes
+        .rgba
+        .iter()
+        .map(|color| rgba_to_oklab(color).l)
+        .collect();
+    let rows = height.div_ceil(2);
+    let mut chunks = Vec::with_capacity(width * rows);
+    for row in 0..rows {
+        for x in 0..width {
+            let top = indexes[row * 2 * width + x];
+            let bottom = if row * 2 + 1 < height {
+                indexes[(row * 2 + 1) * width + x]
+            } else {
+                top
+            };
+            chunks.push(if top == bottom {
+                Chunk {
+                    fg: top,
+                    bg: top,
+                    codepoint: SPACE,
+                }
+            } else if lightness[top as usize] >= lightness[bottom as usize] {
+                Chunk {
+                    fg: top,
+                    bg: bottom,
+                    codepoint: UPPER_HALF,
+                }
+            } else {
+                Chunk {
+                    fg: bottom,
+                    bg: top,
+                    codepoint: LOWER_HALF,
+                }
+            });
+        }
+    }
+    chunks
+}
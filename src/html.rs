@@ -0,0 +1,84 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use crate::inline::base64;
+use crate::ttf::ttf;
+use imagequant::RGBA;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Extensions written as HTML rather than XBIN.
+pub fn is_html_path(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+    })
+}
+
+fn escape(character: char) -> String {
+    match character {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        character => character.to_string(),
+    }
+}
+
+/// Writes the cells as a page holding one `<pre>`, with a span around each
+/// run of cells sharing colors and CSS classes for the exact `palette`. The
+/// font's first bank is embedded as a webfont drawn at its own height, so
+/// the page shows the glyphs the cells were chosen for.
+pub fn save_html(
+    path: impl AsRef<Path>,
+    columns: usize,
+    font: &Font,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+    to_char: impl Fn(u8) -> char,
+) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    writeln!(file, "<!DOCTYPE html>").unwrap();
+    writeln!(file, "<html>").unwrap();
+    writeln!(file, "<head>").unwrap();
+    writeln!(file, "<meta charset=\"utf-8\">").unwrap();
+    writeln!(file, "<style>").unwrap();
+    let webfont = base64(&ttf(font, &to_char));
+    writeln!(
+        file,
+        "@font-face {{ font-family: 'img2xbin'; src: url(data:font/ttf;base64,{webfont}) format('truetype'); }}"
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "pre {{ font-family: 'img2xbin', monospace; font-size: {}px; line-height: 1; }}",
+        font.height
+    )
+    .unwrap();
+    for (index, color) in palette.iter().enumerate() {
+        let hex = format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b);
+        writeln!(file, ".f{index} {{ color: {hex}; }}").unwrap();
+        writeln!(file, ".b{index} {{ background: {hex}; }}").unwrap();
+    }
+    writeln!(file, "</style>").unwrap();
+    writeln!(file, "</head>").unwrap();
+    writeln!(file, "<body>").unwrap();
+    write!(file, "<pre>").unwrap();
+    for row in chunks.chunks(columns.max(1)) {
+        let mut start = 0;
+        while start < row.len() {
+            let (fg, bg) = (row[start].fg, row[start].bg);
+            let length = row[start..]
+                .iter()
+                .take_while(|chunk| chunk.fg == fg && chunk.bg == bg)
+                .count();
+            let text: String = row[start..start + length]
+                .iter()
+                .map(|chunk| escape(to_char(chunk.codepoint)))
+                .collect();
+            write!(file, "<span class=\"f{fg} b{bg}\">{text}</span>").unwrap();
+            start += length;
+        }
+        writeln!(file).unwrap();
+    }
+    writeln!(file, "</pre>").unwrap();
+    writeln!(file, "</body>").unwrap();
+    writeln!(file, "</html>").unwrap();
+}
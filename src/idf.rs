@@ -0,0 +1,69 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use crate::xbin::chunks_to_bytes;
+use imagequant::RGBA;
+use std::io::Write;
+use std::path::Path;
+
+// Cells the character 1 on black would be mistaken for a run, so they are
+// written as a run of one.
+const RUN: [u8; 2] = [1, 0];
+
+/// Extensions written as iCE Draw IDF rather than XBIN.
+pub fn is_idf_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("idf"))
+}
+
+fn compress(cells: &[u8]) -> Vec<u8> {
+    let pairs: Vec<&[u8]> = cells.chunks(2).collect();
+    let mut bytes = Vec::with_capacity(cells.len());
+    let mut index = 0;
+    while index < pairs.len() {
+        let pair = pairs[index];
+        let length = pairs[index..]
+            .iter()
+            .take(255)
+            .take_while(|other| **other == pair)
+            .count();
+        // A run takes three cells' worth of bytes.
+        if length > 3 || pair == RUN {
+            bytes.extend_from_slice(&RUN);
+            bytes.extend_from_slice(&[length as u8, 0]);
+            bytes.extend_from_slice(pair);
+            index += length;
+        } else {
+            bytes.extend_from_slice(pair);
+            index += 1;
+        }
+    }
+    bytes
+}
+
+/// Writes an iCE Draw IDF: the version 1.4 header with the bounds of the
+/// screen, the run-length encoded cells, the 8x16 font and the 6-bit
+/// `palette`.
+pub fn save_idf(
+    path: impl AsRef<Path>,
+    columns: usize,
+    font: &Font,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+) {
+    assert!(
+        font.height == 16,
+        "IDF fonts are 16 rows high, not {}",
+        font.height
+    );
+    let rows = chunks.len() / columns;
+    let mut file = std::fs::File::create(path).unwrap();
+    file.write_all(b"\x041.4").unwrap();
+    for bound in [0, 0, columns - 1, rows - 1] {
+        file.write_all(&(bound as u16).to_le_bytes()).unwrap();
+    }
+    file.write_all(&compress(&chunks_to_bytes(chunks))).unwrap();
+    file.write_all(&font.glyphs[..256 * 16]).unwrap();
+    for color in &palette[..16] {
+        file.write_all(&[color.r, color.g, color.b]).unwrap();
+    }
+}
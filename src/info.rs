@@ -0,0 +1,240 @@
+use crate::cast::json_string;
+use crate::font::Font;
+use crate::gz;
+use crate::palette;
+use crate::sauce::{self, Sauce};
+use crate::xbin::{self, Xbin};
+use std::fmt::Write;
+use std::path::Path;
+
+/// What can be told of an art file: its XBIN header and contents if it is
+/// one, and its SAUCE record if it has one.
+pub struct Info {
+    pub file: String,
+    pub bytes: usize,
+    pub xbin: Option<Result<Xbin, String>>,
+    pub sauce: Option<Sauce>,
+}
+
+impl Info {
+    pub fn read(path: &Path) -> Self {
+        let bytes = gz::read(path).unwrap();
+        let xbin = bytes.starts_with(b"XBIN\x1a").then(|| xbin::parse(&bytes));
+        Info {
+            file: path.display().to_string(),
+            bytes: bytes.len(),
+            xbin,
+            sauce: sauce::parse(&bytes),
+        }
+    }
+}
+
+// The built-in font the XBIN's is a copy of, if any.
+fn builtin_font(font: &Font) -> Option<&'static str> {
+    ["ibm-vga-8x16", "ibm-vga-8x14", "ibm-vga-8x8"]
+        .into_iter()
+        .find(|name| Font::named(name).as_ref() == Some(font))
+}
+
+/// Whether the XBIN shows in the VGA's default palette, embedded or not.
+pub fn is_default_palette(xbin: &Xbin) -> bool {
+    !xbin.features.embed_palette
+        || xbin.palette
+            == palette::ANSI.map(|color| imagequant::RGBA {
+                r: color.r / 4 * 4,
+                g: color.g / 4 * 4,
+                b: color.b / 4 * 4,
+                a: 255,
+            })
+}
+
+fn hex(color: &imagequant::RGBA) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn data_type_name(data_type: u8) -> &'static str {
+    match data_type {
+        sauce::CHARACTER => "Character",
+        sauce::BINARY_TEXT => "BinaryText",
+        sauce::XBIN => "XBin",
+        _ => "other",
+    }
+}
+
+fn uncompressed_bytes(xbin: &Xbin) -> usize {
+    xbin.chunks.len() * 2
+}
+
+/// The report as lines of readable text.
+pub fn text(info: &Info) -> String {
+    let mut text = String::new();
+    writeln!(text, "File:         {} ({} bytes)", info.file, info.bytes).unwrap();
+    match &info.xbin {
+        None => writeln!(text, "Format:       not XBIN").unwrap(),
+        Some(Err(error)) => writeln!(text, "Format:       damaged XBIN, {error}").unwrap(),
+        Some(Ok(xbin)) => {
+            let rows = xbin.chunks.len() / xbin.columns.max(1);
+            writeln!(text, "Format:       XBIN").unwrap();
+            writeln!(text, "Size:         {} x {rows} cells", xbin.columns).unwrap();
+            let source = if xbin.features.embed_font {
+                match builtin_font(&xbin.font) {
+                    Some(name) => format!("embedded, the same as {name}"),
+                    None => "embedded".to_string(),
+                }
+            } else {
+                "the viewer's own".to_string()
+            };
+            writeln!(
+                text,
+                "Font:         {} glyphs, {} pixels high, {source}",
+                xbin.font.glyph_count(),
+                xbin.font.height
+            )
+            .unwrap();
+            let colors = if xbin.features.non_blink {
+                "iCE colors, bright backgrounds rather than blinking"
+            } else {
+                "blinking, attribute bit 7 blinks rather than brightens"
+            };
+            writeln!(text, "Backgrounds:  {colors}").unwrap();
+            let cells = uncompressed_bytes(xbin);
+            if xbin.features.compress {
+                writeln!(
+                    text,
+                    "Compression:  {} bytes of cells for {cells} uncompressed, {}%",
+                    xbin.cell_bytes,
+                    xbin.cell_bytes * 100 / cells.max(1)
+                )
+                .unwrap();
+            } else {
+                writeln!(text, "Compression:  none, {cells} bytes of cells").unwrap();
+            }
+            let source = match (xbin.features.embed_palette, is_default_palette(xbin)) {
+                (false, _) => "the VGA default",
+                (true, true) => "embedded, the same as the VGA default",
+                (true, false) => "embedded",
+            };
+            writeln!(text, "Palette:      {source}").unwrap();
+            for (index, color) in xbin.palette.iter().enumerate() {
+                writeln!(
+                    text,
+                    "  {index:2}  {}  {:2} {:2} {:2}",
+                    hex(color),
+                    color.r / 4,
+                    color.g / 4,
+                    color.b / 4
+                )
+                .unwrap();
+            }
+        }
+    }
+    match &info.sauce {
+        None => writeln!(text, "SAUCE:        none").unwrap(),
+        Some(sauce) => {
+            writeln!(text, "SAUCE:").unwrap();
+            writeln!(text, "  Title:      {}", sauce.title).unwrap();
+            writeln!(text, "  Author:     {}", sauce.author).unwrap();
+            writeln!(text, "  Group:      {}", sauce.group).unwrap();
+            writeln!(text, "  Date:       {}", sauce.date).unwrap();
+            writeln!(
+                text,
+                "  Data type:  {} ({}), file type {}",
+                sauce.data_type,
+                data_type_name(sauce.data_type),
+                sauce.file_type
+            )
+            .unwrap();
+            writeln!(text, "  Size:       {} x {}", sauce.width, sauce.height).unwrap();
+            writeln!(text, "  Flags:      {:#010b}", sauce.flags).unwrap();
+            let font_name = sauce.font_name.as_deref().unwrap_or("none");
+            writeln!(text, "  Font name:  {font_name}").unwrap();
+            for comment in &sauce.comments {
+                writeln!(text, "  Comment:    {comment}").unwrap();
+            }
+        }
+    }
+    text
+}
+
+/// The report as a JSON object.
+pub fn json(info: &Info) -> String {
+    let mut json = String::new();
+    writeln!(json, "{{").unwrap();
+    writeln!(json, "  \"file\": {},", json_string(&info.file)).unwrap();
+    writeln!(json, "  \"bytes\": {},", info.bytes).unwrap();
+    match &info.xbin {
+        None => writeln!(json, "  \"xbin\": null,").unwrap(),
+        Some(Err(error)) => {
+            writeln!(json, "  \"xbin\": {{\"error\": {}}},", json_string(error)).unwrap()
+        }
+        Some(Ok(xbin)) => {
+            let features = &xbin.features;
+            writeln!(json, "  \"xbin\": {{").unwrap();
+            writeln!(json, "    \"columns\": {},", xbin.columns).unwrap();
+            writeln!(
+                json,
+                "    \"rows\": {},",
+                xbin.chunks.len() / xbin.columns.max(1)
+            )
+            .unwrap();
+            writeln!(json, "    \"font_height\": {},", xbin.font.height).unwrap();
+            writeln!(json, "    \"glyphs\": {},", xbin.font.glyph_count()).unwrap();
+            writeln!(
+                json,
+                "    \"embedded_palette\": {},",
+                features.embed_palette
+            )
+            .unwrap();
+            writeln!(
+                json,
+                "    \"default_palette\": {},",
+                is_default_palette(xbin)
+            )
+            .unwrap();
+            writeln!(json, "    \"embedded_font\": {},", features.embed_font).unwrap();
+            let builtin = builtin_font(&xbin.font).map_or("null".to_string(), json_string);
+            writeln!(json, "    \"builtin_font\": {builtin},").unwrap();
+            writeln!(json, "    \"compressed\": {},", features.compress).unwrap();
+            writeln!(json, "    \"non_blink\": {},", features.non_blink).unwrap();
+            writeln!(json, "    \"cell_bytes\": {},", xbin.cell_bytes).unwrap();
+            writeln!(
+                json,
+                "    \"uncompressed_cell_bytes\": {},",
+                uncompressed_bytes(xbin)
+            )
+            .unwrap();
+            let palette: Vec<String> = xbin
+                .palette
+                .iter()
+                .map(|color| json_string(&hex(color)))
+                .collect();
+            writeln!(json, "    \"palette\": [{}]", palette.join(", ")).unwrap();
+            writeln!(json, "  }},").unwrap();
+        }
+    }
+    match &info.sauce {
+        None => writeln!(json, "  \"sauce\": null").unwrap(),
+        Some(sauce) => {
+            writeln!(json, "  \"sauce\": {{").unwrap();
+            writeln!(json, "    \"title\": {},", json_string(&sauce.title)).unwrap();
+            writeln!(json, "    \"author\": {},", json_string(&sauce.author)).unwrap();
+            writeln!(json, "    \"group\": {},", json_string(&sauce.group)).unwrap();
+            writeln!(json, "    \"date\": {},", json_string(&sauce.date)).unwrap();
+            writeln!(json, "    \"data_type\": {},", sauce.data_type).unwrap();
+            writeln!(json, "    \"file_type\": {},", sauce.file_type).unwrap();
+            writeln!(json, "    \"width\": {},", sauce.width).unwrap();
+            writeln!(json, "    \"height\": {},", sauce.height).unwrap();
+            writeln!(json, "    \"flags\": {},", sauce.flags).unwrap();
+            let font_name = sauce
+                .font_name
+                .as_deref()
+                .map_or("null".to_string(), json_string);
+            writeln!(json, "    \"font_name\": {font_name},").unwrap();
+            let comments: Vec<String> = sauce.comments.iter().map(|c| json_string(c)).collect();
+            writeln!(json, "    \"comments\": [{}]", comments.join(", ")).unwrap();
+            writeln!(json, "  }}").unwrap();
+        }
+    }
+    writeln!(json, "}}").unwrap();
+    json
+}
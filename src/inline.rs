@@ -0,0 +1,64 @@
+use image::{DynamicImage, ImageOutputFormat, RgbImage};
+use std::io::{Cursor, Write};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Kitty takes base64 in pieces of at most 4096 bytes.
+const KITTY_CHUNK: usize = 4096;
+
+pub fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let bits = group.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= group.len() {
+                encoded.push(BASE64[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// Kitty sets TERM or KITTY_WINDOW_ID; iTerm2's protocol is also read by
+// WezTerm and others, so it is the fallback.
+fn is_kitty() -> bool {
+    std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || std::env::var("TERM").is_ok_and(|term| term == "xterm-kitty")
+}
+
+/// Prints a rendering to stdout as an inline image, with the kitty graphics
+/// protocol in kitty and iTerm2's OSC 1337 elsewhere.
+pub fn show(rendering: &RgbImage) {
+    let mut png = Vec::new();
+    DynamicImage::ImageRgb8(rendering.clone())
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    let encoded = base64(&png);
+    let mut stdout = std::io::stdout().lock();
+    if is_kitty() {
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(i + 1 < chunks.len());
+            let control = if i == 0 {
+                format!("f=100,a=T,m={more}")
+            } else {
+                format!("m={more}")
+            };
+            write!(stdout, "\x1b_G{control};").unwrap();
+            stdout.write_all(chunk).unwrap();
+            write!(stdout, "\x1b\\").unwrap();
+        }
+    } else {
+        write!(
+            stdout,
+            "\x1b]1337;File=inline=1;size={}:{encoded}\x07",
+            png.len()
+        )
+        .unwrap();
+    }
+    writeln!(stdout).unwrap();
+}
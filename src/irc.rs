@@ -0,0 +1,31 @@
+use crate::chunks::Chunk;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const COLOR: char = '\x03';
+
+/// Writes the cells as UTF-8 lines with mIRC color codes, `codes` giving
+/// the mIRC color of each palette entry. Both colors are set at the start of
+/// every line, as IRC clients reset them there, and always with two digits
+/// so that a digit drawn after them isn't read as part of the code.
+pub fn save_irc(
+    path: impl AsRef<Path>,
+    columns: usize,
+    codes: &[u8],
+    chunks: &[Chunk],
+    to_char: impl Fn(u8) -> char,
+) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    for row in chunks.chunks(columns.max(1)) {
+        let mut current = None;
+        for chunk in row {
+            let colors = (codes[chunk.fg as usize], codes[chunk.bg as usize]);
+            if current != Some(colors) {
+                write!(file, "{COLOR}{:02},{:02}", colors.0, colors.1).unwrap();
+                current = Some(colors);
+            }
+            write!(file, "{}", to_char(chunk.codepoint)).unwrap();
+        }
+        writeln!(file).unwrap();
+    }
+}
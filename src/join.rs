@@ -0,0 +1,169 @@
+use crate::chunks::Chunk;
+use crate::xbin::{Features, Xbin};
+use imagequant::RGBA;
+use std::path::Path;
+
+// Colors compare as the 6-bit VGA entries XBIN stores, so the standard
+// palette ANSI art is drawn in matches an XBIN's copy of it.
+fn vga(color: &RGBA) -> [u8; 3] {
+    [color.r / 4, color.g / 4, color.b / 4]
+}
+
+// The palette entries the cells show: backgrounds, and the foregrounds of
+// glyphs with any pixels set.
+fn used(art: &Xbin, chunks: &[Chunk]) -> [bool; 16] {
+    let mut used = [false; 16];
+    for chunk in chunks {
+        let (glyph, fg) = art.font.cell_glyph(chunk);
+        used[chunk.bg as usize] = true;
+        if glyph.iter().any(|row| *row != 0) {
+            used[fg as usize] = true;
+        }
+    }
+    used
+}
+
+// The cells made `columns` wide and `rows` tall, filled out with blank cells
+// in the first palette entry.
+fn padded(art: &Xbin, chunks: Vec<Chunk>, columns: usize, rows: usize) -> Vec<Chunk> {
+    let mut cells = Vec::with_capacity(columns * rows);
+    for row in chunks.chunks(art.columns) {
+        cells.extend_from_slice(row);
+        cells.resize(cells.len() + columns - art.columns, Chunk::solid(0));
+    }
+    cells.resize(columns * rows, Chunk::solid(0));
+    cells
+}
+
+/// Art files stitched into one grid, each below the one before or with
+/// `horizontal` to its right, the narrower or shorter padded with blank
+/// cells. They must share a font. Their palettes merge into one when the
+/// colors they show fit in 16 entries, with the first's kept where it is and
+/// the others' moved only to make room. Blinking backgrounds are kept only
+/// if every file blinks in one palette, and otherwise show as between blinks.
+pub fn join(arts: &[(&Path, Xbin)], horizontal: bool) -> Result<Xbin, String> {
+    let (first_path, first) = &arts[0];
+    for (path, art) in &arts[1..] {
+        if art.font != first.font {
+            return Err(format!(
+                "{} is drawn in another font than {}",
+                path.display(),
+                first_path.display()
+            ));
+        }
+    }
+    let shared = arts.iter().all(|(_, art)| {
+        art.palette
+            .iter()
+            .map(vga)
+            .eq(first.palette.iter().map(vga))
+    });
+    let non_blink = !shared || arts.iter().any(|(_, art)| art.features.non_blink);
+    let mut slots: [Option<[u8; 3]>; 16] = [None; 16];
+    let mut grids = Vec::with_capacity(arts.len());
+    for (path, art) in arts {
+        let mut chunks = if non_blink {
+            art.shown()
+        } else {
+            art.chunks.clone()
+        };
+        if !shared {
+            if art.font.glyph_count() > 256 {
+                return Err(format!(
+                    "{} has a second bank of characters, which leaves no \
+                     foreground bit to merge palettes with",
+                    path.display()
+                ));
+            }
+            let mut indexes = [0; 16];
+            let shows = used(art, &chunks);
+            for index in (0..16).filter(|index| shows[*index]) {
+                let color = vga(&art.palette[index]);
+                let slot = slots
+                    .iter()
+                    .position(|slot| *slot == Some(color))
+                    .or_else(|| slots[index].is_none().then_some(index))
+                    .or_else(|| slots.iter().position(Option::is_none))
+                    .ok_or_else(|| {
+                        format!(
+                            "the colors of {} don't fit in one palette with the files before",
+                            path.display()
+                        )
+                    })?;
+                slots[slot] = Some(color);
+                indexes[index] = slot as u8;
+            }
+            for chunk in &mut chunks {
+                chunk.fg = indexes[chunk.fg as usize];
+                chunk.bg = indexes[chunk.bg as usize];
+            }
+        }
+        grids.push((art, chunks));
+    }
+    let palette = if shared {
+        first.palette.clone()
+    } else {
+        // Entries no file shows keep the first's colors.
+        slots
+            .iter()
+            .zip(&first.palette)
+            .map(|(slot, color)| match slot {
+                Some([r, g, b]) => RGBA {
+                    r: r * 4,
+                    g: g * 4,
+                    b: b * 4,
+                    a: 255,
+                },
+                None => *color,
+            })
+            .collect()
+    };
+    let widest = grids.iter().map(|(art, _)| art.columns).max().unwrap();
+    let tallest = grids
+        .iter()
+        .map(|(art, chunks)| chunks.len() / art.columns)
+        .max()
+        .unwrap();
+    let (columns, chunks): (usize, Vec<Chunk>) = if horizontal {
+        let columns: usize = grids.iter().map(|(art, _)| art.columns).sum();
+        let grids: Vec<Vec<Chunk>> = grids
+            .into_iter()
+            .map(|(art, chunks)| padded(art, chunks, art.columns, tallest))
+            .collect();
+        let chunks = (0..tallest)
+            .flat_map(|row| {
+                grids.iter().zip(arts).flat_map(move |(chunks, (_, art))| {
+                    chunks[row * art.columns..(row + 1) * art.columns].to_vec()
+                })
+            })
+            .collect();
+        (columns, chunks)
+    } else {
+        let chunks = grids
+            .into_iter()
+            .flat_map(|(art, chunks)| {
+                let rows = chunks.len() / art.columns;
+                padded(art, chunks, widest, rows)
+            })
+            .collect();
+        (widest, chunks)
+    };
+    let rows = chunks.len() / columns;
+    if columns > u16::MAX as usize || rows > u16::MAX as usize {
+        return Err(format!("{columns} x {rows} cells is too large for an XBIN"));
+    }
+    let cells = chunks.len();
+    Ok(Xbin {
+        columns,
+        font: first.font.clone(),
+        palette,
+        chunks,
+        features: Features {
+            embed_palette: true,
+            embed_font: true,
+            compress: true,
+            non_blink,
+        },
+        cell_bytes: cells * 2,
+    })
+}
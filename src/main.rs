@@ -1,204 +1,3348 @@
-use clap::Parser;
-use image::{DynamicImage, GenericImageView};
+mod adf;
+mod ansi;
+mod ascii;
+mod atascii;
+mod avatar;
+mod baud;
+mod bin;
+mod braille;
+mod cast;
+mod charset;
+mod checkpoint;
+mod chunks;
+mod codepage;
+mod com;
+mod compat;
+mod cp437;
+mod cvd;
+mod diff;
+mod diz;
+mod dump;
+mod error_map;
+mod escp;
+mod font;
+mod frames;
+mod glyph;
+mod gz;
+mod halfblock;
+mod html;
+mod idf;
+mod info;
+mod inline;
+mod irc;
+mod join;
+mod metrics;
+mod mosaic;
+mod optimize;
+mod palette;
+mod palette_file;
+mod pcboard;
+mod pdf;
+mod petscii;
+mod ps;
+mod psf;
+mod quadrant;
+mod refine;
+mod render;
+mod sauce;
+mod scroll;
+mod sequence;
+mod sextant;
+mod shades;
+mod sheet;
+mod sixel;
+mod slideshow;
+mod source;
+mod spectrum;
+mod svg;
+mod terminal;
+mod text;
+mod tile;
+mod ttf;
+mod tundra;
+mod vga50;
+mod xbin;
+
+use ansi::Colors;
+use charset::Charset;
+use checkpoint::Checkpoint;
+use chunks::{
+    break_into_chunks, chunk_errors, restrict_backgrounds, source_pixels, Chunk, ChunkOpt, Encoding,
+};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use codepage::Codepage;
+use cp437::CP437;
+use cvd::Deficiency;
+use font::Font;
+use glyph::GlyphMetric;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{imageops::FilterType, Delay, DynamicImage, Frame, GenericImageView};
 use imagequant::RGBA;
-use oklab::srgb_to_oklab;
-use std::path::Path;
-use std::{io::Write, path::PathBuf};
+use palette::Palettes;
+use palette_file::PaletteFile;
+use rayon::prelude::*;
+use slideshow::Slideshow;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use xbin::save_xbin;
 
-#[derive(Parser, Debug)]
-struct Args {
-    #[clap(value_name = "INPUT")]
-    input: PathBuf,
-    #[clap(value_name = "OUTPUT")]
-    output: PathBuf,
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+enum Mode {
+    /// One pixel row per cell using a 1-pixel-high font
+    #[default]
+    Pixel,
+    /// Two pixels per cell using the half-block characters of an 8x16 font
+    Halfblock,
+    /// Four pixels per cell using quadrant block characters
+    Quadrant,
+    /// One pixel per cell using space and the shade characters of an 8x16 font
+    Shades,
+    /// One 8x16 block per cell matched against every glyph of an 8x16 font
+    Glyph,
+    /// One pixel per cell drawn from an ASCII luminance ramp
+    Ascii,
+    /// Eight pixels per cell as Unicode braille dots, written as UTF-8 ANSI
+    Braille,
+    /// Six pixels per cell as Unicode sextant blocks, written as UTF-8 ANSI
+    Sextant,
+    /// One pixel per cell using only spaces and full blocks
+    Mosaic,
+    /// One pixel per cell using the glyphs of an 8x16 font as a dither set,
+    /// picking the coverage and color pair nearest each pixel
+    Density,
+    /// Eight pixels per cell in two rows using an 8x8 font, for 80x50 text mode
+    #[value(name = "80x50")]
+    Vga50,
+    /// Try the pixel, halfblock and glyph modes and keep the one whose
+    /// rendering scores the best SSIM against the source
+    Auto,
 }
 
-struct Palettes {
-    vga: Vec<RGBA>,
-    rgba: Vec<RGBA>,
+impl Mode {
+    /// Source pixels covered by one cell of `font`, horizontally and vertically.
+    fn cell_pixels(self, font: &Font) -> (u32, u32) {
+        match self {
+            Mode::Pixel => (8, 1),
+            Mode::Halfblock => (1, 2),
+            Mode::Quadrant => (2, 2),
+            Mode::Shades => (1, 1),
+            Mode::Glyph => (8, font.height as u32),
+            Mode::Ascii | Mode::Mosaic | Mode::Density => (1, 1),
+            Mode::Braille => (2, 4),
+            Mode::Sextant => (2, 3),
+            Mode::Vga50 => (4, 2),
+            Mode::Auto => unreachable!("auto picks another mode"),
+        }
+    }
+
+    /// Height of a source pixel on screen relative to its width, for cells
+    /// 8 pixels wide and as tall as `font`.
+    fn pixel_aspect(self, font: &Font) -> f32 {
+        let (width, height) = self.cell_pixels(font);
+        (font.height as f32 / height as f32) / (8.0 / width as f32)
+    }
+
+    /// Whether the mode draws with an ordinary text font, which `--font` can
+    /// replace, rather than one generated for its own patterns.
+    fn uses_text_font(self) -> bool {
+        matches!(
+            self,
+            Mode::Halfblock
+                | Mode::Shades
+                | Mode::Glyph
+                | Mode::Ascii
+                | Mode::Mosaic
+                | Mode::Density
+        )
+    }
+
+    fn font(self) -> Font {
+        match self {
+            Mode::Pixel => Font::pixel(),
+            Mode::Halfblock
+            | Mode::Shades
+            | Mode::Glyph
+            | Mode::Ascii
+            | Mode::Mosaic
+            | Mode::Density => Font::ibm_vga_8x16(),
+            Mode::Quadrant => quadrant::font(),
+            Mode::Braille => braille::font(),
+            Mode::Sextant => sextant::font(),
+            Mode::Vga50 => vga50::font(),
+            Mode::Auto => unreachable!("auto picks another mode"),
+        }
+    }
 }
 
-impl Palettes {
-    fn new(rgba: &[RGBA]) -> Self {
-        let mut vga: Vec<RGBA> = rgba
-            .iter()
-            .map(|color| RGBA {
-                r: (color.r as f32 / 255.0 * 63.0).round() as u8,
-                g: (color.g as f32 / 255.0 * 63.0).round() as u8,
-                b: (color.b as f32 / 255.0 * 63.0).round() as u8,
-                a: 255,
-            })
-            .collect();
-        while vga.len() < 16 {
-            vga.push(RGBA {
-                r: 0,
-                g: 0,
-                b: 0,
-                a: 255,
-            });
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+enum CellAspect {
+    /// Resample the source vertically to match the cell shape
+    #[default]
+    Squeeze,
+    /// Repeat each pixel in two identical cells across, in modes with one
+    /// pixel per cell
+    Double,
+    /// Map source pixels to cells as they are, stretching the image
+    Stretch,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+enum Target {
+    /// DOS PCs: a generated palette, the IBM VGA font and CRLF line endings
+    #[default]
+    Pc,
+    /// The Amiga: its 16-color ANSI palette, pixels twice as tall as wide for
+    /// the 8x8 Topaz font, and LF line endings
+    Amiga,
+    /// The Commodore 64: the VIC-II palette, one background color for the
+    /// whole screen in glyph mode, and CR line endings; pass a dump of its
+    /// character ROM with --font
+    C64,
+    /// The Atari 8-bit: Graphics 0's background and text colors, the upper
+    /// half of the character set drawn reversed, and ATASCII line endings;
+    /// pass its character set in ATASCII order with --font
+    Atari,
+    /// The ZX Spectrum: its 15 colors, with each 8x8 block of pixel mode kept
+    /// to one ink and paper of the same brightness
+    Spectrum,
+}
+
+impl Target {
+    /// A fixed palette the target's viewers always use.
+    fn palette(self) -> Option<palette::Standard> {
+        match self {
+            Target::Pc => None,
+            Target::Amiga => Some(palette::Standard::Amiga),
+            Target::C64 => Some(palette::Standard::C64),
+            Target::Atari => Some(palette::Standard::Atari),
+            Target::Spectrum => Some(palette::Standard::Spectrum),
         }
-        let rgba: Vec<RGBA> = vga
-            .iter()
-            .map(|color| RGBA {
-                r: color.r * 4,
-                g: color.g * 4,
-                b: color.b * 4,
-                a: 255,
-            })
-            .collect();
-        Palettes { vga, rgba }
     }
+
+    /// Height of the target's pixels relative to their width.
+    fn pixel_height(self) -> f32 {
+        match self {
+            Target::Pc | Target::C64 | Target::Atari | Target::Spectrum => 1.0,
+            Target::Amiga => 2.0,
+        }
+    }
+
+    fn line_ending(self) -> &'static [u8] {
+        match self {
+            Target::Pc => b"\r\n",
+            Target::Amiga => b"\n",
+            Target::C64 => b"\r",
+            Target::Atari => b"\x9b",
+            Target::Spectrum => b"\r",
+        }
+    }
+}
+
+/// File format written to OUTPUT.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Format {
+    /// XBIN with its palette and font embedded
+    Xbin,
+    /// CP437 ANSI with 16-color SGR codes
+    Ans,
+    /// UTF-8 ANSI with 16-color SGR codes, for modern terminals
+    Utf8ans,
+    /// UTF-8 ANSI with 256-color SGR codes from the xterm palette
+    Xterm256,
+    /// UTF-8 ANSI with the palette's own colors as 24-bit SGR codes
+    Truecolor,
+    /// CP437 characters alone
+    Text,
+    /// Bare character/attribute pairs, as BIN screens hold them
+    Bin,
+    /// Artworx ADF, 80 columns with an EGA palette and an 8x16 font
+    Adf,
+    /// iCE Draw IDF, run-length encoded with an 8x16 font
+    Idf,
+    /// TundraDraw, 80 columns with 24-bit colors
+    Tundra,
+    /// A DOS program showing the art in text mode with its font and palette
+    Com,
+    /// CP437 text with PCBoard's @X color codes
+    Pcboard,
+    /// CP437 text with AVATAR/0 codes
+    Avatar,
+    /// A Commodore 64 PETSCII stream, for --target c64
+    Seq,
+    /// An Atari ATASCII stream, for --target atari
+    Atascii,
+    /// A ZX Spectrum screen dump of pixel mode, for --target spectrum
+    Scr,
+    /// A web page of colored text
+    Html,
+    /// A vector drawing of the rendered cells
+    Svg,
+    /// DEC sixel graphics of the rendered cells, for terminals that show them
+    Sixel,
+    /// Epson ESC/P graphics of the rendered cells, for dot-matrix printers
+    Escp,
+    /// A PDF page of the cells typeset in the font, for printing
+    Pdf,
+    /// A PostScript page of the cells typeset in the font, for printing
+    Ps,
+    /// An asciinema recording of truecolor UTF-8 ANSI drawing the art
+    Cast,
+    /// UTF-8 text with mIRC color codes in the 16 classic colors
+    Irc,
+    /// UTF-8 text with mIRC color codes in all 99 colors
+    Irc99,
+    /// The XBIN as a Rust byte array
+    Rs,
+    /// The XBIN as a C byte array
+    C,
+    /// The XBIN as assembler db lines
+    Asm,
+}
+
+#[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[clap(value_name = "INPUT", required = true)]
+    input: Option<PathBuf>,
+    /// File to write, or for XBIN a template like out_%03d.xb naming one for
+    /// each frame of an animated source, listed with their delays in out.json
+    #[clap(value_name = "OUTPUT", required = true)]
+    output: Option<PathBuf>,
+    #[command(flatten)]
+    options: Options,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render the input through every mode into a labeled PNG grid
+    CompareModes {
+        #[clap(value_name = "INPUT")]
+        input: PathBuf,
+        #[clap(value_name = "PNG")]
+        output: PathBuf,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Convert the input and save only a PNG of how a viewer would show it,
+    /// or for an XBIN draw it as it is
+    Render {
+        #[clap(value_name = "INPUT")]
+        input: PathBuf,
+        #[clap(value_name = "PNG")]
+        output: PathBuf,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Convert the input into a FILE_ID.DIZ thumbnail of at most 45 columns
+    /// and 30 lines without SAUCE, in ascii mode unless another text mode is
+    /// asked for
+    Diz {
+        #[clap(value_name = "INPUT")]
+        input: PathBuf,
+        #[clap(value_name = "DIZ")]
+        output: PathBuf,
+        /// A line of text to put under the art, counted in the 30
+        #[clap(long, value_name = "TEXT")]
+        footer: Vec<String>,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Describe an art file: its XBIN header, flags, palette, font and
+    /// compression, and any SAUCE record
+    Info {
+        #[clap(value_name = "FILE")]
+        input: PathBuf,
+        /// Print the description as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Save the palette an image converts with, or an XBIN or ANSI file is
+    /// drawn in, as a GIMP .gpl, JASC .pal or .hex list of colors
+    ExtractPalette {
+        #[clap(value_name = "INPUT")]
+        input: PathBuf,
+        #[clap(value_name = "PALETTE")]
+        output: PathBuf,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Compare the cells of two XBINs or ANSI files, listing where they
+    /// differ, and exit with status 1 if they do
+    Diff {
+        #[clap(value_name = "A")]
+        a: PathBuf,
+        #[clap(value_name = "B")]
+        b: PathBuf,
+        /// Also draw B as a PNG with the cells that match A dimmed, and those
+        /// only A has in red
+        #[clap(long, value_name = "PNG")]
+        png: Option<PathBuf>,
+    },
+    /// Stitch XBINs or ANSI files drawn in one font into one XBIN, one below
+    /// another
+    Join {
+        #[clap(value_name = "INPUT", required = true, num_args = 2..)]
+        inputs: Vec<PathBuf>,
+        #[clap(value_name = "XBIN")]
+        output: PathBuf,
+        /// Put each file to the right of the one before instead
+        #[clap(long)]
+        horizontal: bool,
+    },
+    /// Write an XBIN again as small as it goes, showing the same: optimally
+    /// compressed, without a default palette or font, and with the colors
+    /// glyphs don't show matched to their neighbors'
+    Optimize {
+        #[clap(value_name = "INPUT")]
+        input: PathBuf,
+        #[clap(value_name = "OUTPUT")]
+        output: PathBuf,
+    },
+    /// Convert art from one format to another cell for cell, without
+    /// quantizing again: ANSI to XBIN, or XBIN to ANSI
+    Convert {
+        #[clap(value_name = "INPUT")]
+        input: PathBuf,
+        #[clap(value_name = "OUTPUT")]
+        output: PathBuf,
+        /// Keep an XBIN's own colors as UTF-8 truecolor ANSI rather than
+        /// the nearest of the 16 standard ones
+        #[clap(long)]
+        truecolor: bool,
+    },
+    /// Convert still images into one ANSImation that shows each in turn, at
+    /// the size of the first
+    Slideshow {
+        #[clap(value_name = "INPUT", required = true, num_args = 2..)]
+        inputs: Vec<PathBuf>,
+        #[clap(value_name = "ANS")]
+        output: PathBuf,
+        /// Seconds each slide shows for, unless --timing gives its own
+        #[clap(long, value_name = "SECONDS", default_value_t = 5.0, value_parser = positive)]
+        duration: f64,
+        /// How each slide gives way to the next
+        #[clap(long, value_enum, default_value_t)]
+        transition: slideshow::Transition,
+        /// Seconds each transition takes
+        #[clap(long, value_name = "SECONDS", default_value_t = 1.0, value_parser = positive)]
+        transition_duration: f64,
+        #[command(flatten)]
+        options: Options,
+    },
+}
+
+#[derive(Args, Clone, Debug)]
+struct Options {
+    /// How source pixels are laid out in text cells
+    #[clap(long, value_enum, default_value_t)]
+    mode: Mode,
+    /// Output file format [default: truecolor for braille and sextant, else from
+    /// the extension before any .gz: .ans, .bin, .adf, .idf, .tnd, .com, .pcb,
+    /// .avt, .seq, .ata, .scr, .html, .svg, .six, .prn, .pdf, .ps, .cast, .rs,
+    /// .c/.h, .asm/.inc, .txt/.nfo/.asc/.diz as text, otherwise xbin]
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    format: Option<Format>,
+    /// Character set to encode .ans, .bin, .pcb, .avt and text in, for
+    /// viewers of other codepages; utf8 writes .ans as utf8ans
+    #[clap(long, value_enum, value_name = "CODEPAGE", default_value_t)]
+    codepage: Codepage,
+    /// Platform whose viewers the output is made for
+    #[clap(long, value_enum, value_name = "PLATFORM", default_value_t)]
+    target: Target,
+    /// Resize the source so the output is this many columns wide
+    #[clap(long, value_name = "COLUMNS")]
+    columns: Option<u32>,
+    /// How to keep proportions in modes whose pixels are taller than wide
+    #[clap(long, value_enum, value_name = "HANDLING", default_value_t)]
+    cell_aspect: CellAspect,
+    /// Font to draw and match text-font modes with: ibm-vga-8x16, ibm-vga-8x14,
+    /// ibm-vga-8x8, or a raw (.F08/.F14/.F16) or PSF font file
+    #[clap(long, value_name = "FONT", value_parser = Font::load)]
+    font: Option<Font>,
+    /// Second bank of 256 glyphs for glyph mode, making a 512-character font
+    /// whose foregrounds are limited to palette entries 0-7
+    #[clap(long, value_name = "FONT", value_parser = Font::load)]
+    second_font: Option<Font>,
+    /// Restrict glyph matching to a named set (blocks, shades, ascii, box), a
+    /// file of codepoints, or a literal string of characters
+    #[clap(long, value_name = "CHARSET", value_parser = Charset::parse)]
+    charset: Option<Charset>,
+    /// How glyph matching scores each candidate glyph
+    #[clap(long, value_enum, value_name = "METRIC", default_value_t)]
+    glyph_metric: GlyphMetric,
+    /// Characters from darkest to lightest for the ascii mode
+    #[clap(long, value_name = "RAMP", default_value = ascii::DEFAULT_RAMP, value_parser = ascii::Ramp::parse)]
+    ramp: ascii::Ramp,
+    /// Color each ascii mode character with the nearest palette entry
+    #[clap(long)]
+    ascii_color: bool,
+    /// Keep palette entries distinguishable under a color vision deficiency
+    #[clap(long, value_enum, value_name = "DEFICIENCY")]
+    cb_safe: Option<Deficiency>,
+    /// Encode with a standard palette instead of generating one
+    #[clap(long, value_enum, value_name = "PALETTE")]
+    palette: Option<palette::Standard>,
+    /// Encode with the colors of a GIMP .gpl, JASC .pal or .hex palette file
+    /// instead of generating them
+    #[clap(long, value_name = "FILE", value_parser = PaletteFile::load, conflicts_with = "palette")]
+    palette_file: Option<PaletteFile>,
+    /// Map each pixel straight to the nearest color of the fixed palette, in
+    /// Oklab after any --grain, rather than quantizing with it
+    #[clap(long)]
+    no_quantize: bool,
+    /// Maximum number of palette entries to generate
+    #[clap(long, value_name = "COLORS", default_value_t = 16, value_parser = clap::value_parser!(u32).range(2..=16))]
+    colors: u32,
+    /// Print how many cells use each palette entry
+    #[clap(long)]
+    palette_usage: bool,
+    /// Warn about palette entries used by fewer cells than this
+    #[clap(long, value_name = "CELLS", default_value_t = 16)]
+    min_usage: usize,
+    /// Print the PSNR and SSIM of the rendering against the source resized to
+    /// it
+    #[clap(long)]
+    fidelity: bool,
+    /// Print the fidelity, and exit with an error after writing the output if
+    /// the SSIM is below this
+    #[clap(long, value_name = "SSIM", value_parser = fraction)]
+    min_ssim: Option<f64>,
+    /// Blue-noise perturbation, in 8-bit levels, added before quantization to break up banding
+    #[clap(long, value_name = "AMOUNT", default_value_t = 0.0)]
+    grain: f32,
+    /// How each chunk's foreground and background colors are chosen
+    #[clap(
+        help_heading = "Pixel mode",
+        long,
+        value_enum,
+        value_name = "STRATEGY",
+        default_value_t
+    )]
+    chunk_opt: ChunkOpt,
+    /// Diffuse each chunk's two-color residual into the chunks right and below
+    #[clap(help_heading = "Pixel mode", long, value_name = "STRENGTH", num_args = 0..=1, default_missing_value = "1.0")]
+    diffuse: Option<f32>,
+    /// Mix each chunk's foreground and background with an ordered threshold
+    #[clap(help_heading = "Pixel mode", long)]
+    chunk_dither: bool,
+    /// Passes refitting the palette to the fg/bg-constrained encoding
+    #[clap(
+        help_heading = "Pixel mode",
+        long,
+        value_name = "PASSES",
+        default_value_t = 0
+    )]
+    refine: usize,
+    /// Reuse the previous cell's colors when that costs at most this much mean Oklab error
+    #[clap(help_heading = "Pixel mode", long, value_name = "TOLERANCE", num_args = 0..=1, default_missing_value = "0.02")]
+    compress_bias: Option<f32>,
+    /// Save a false-color PNG of per-chunk encoding error
+    #[clap(help_heading = "Pixel mode", long, value_name = "PNG")]
+    error_map: Option<PathBuf>,
+    /// Dump each cell's colors, codepoint and error as JSON (or CSV for .csv paths)
+    #[clap(help_heading = "Pixel mode", long, value_name = "FILE")]
+    dump_cells: Option<PathBuf>,
+    /// Only use palette entries 0-7 as backgrounds and clear the XBIN non-blink
+    /// flag, for viewers without iCE colors that blink the rest
+    #[clap(long)]
+    blink_safe: bool,
+    /// Set the XBIN non-blink flag even when no background uses entries
+    /// 8-15, which is all that sets it otherwise
+    #[clap(long, conflicts_with = "blink_safe")]
+    ice: bool,
+    /// Leave the font out of XBINs drawn with the IBM VGA 8x16 font, for
+    /// viewers that supply it themselves
+    #[clap(long)]
+    no_embed_font: bool,
+    /// Leave the palette out of XBINs using the standard ansi palette, for
+    /// viewers that supply it themselves
+    #[clap(long)]
+    no_embed_palette: bool,
+    /// Write XBIN cells without run-length compression
+    #[clap(long)]
+    no_compress: bool,
+    /// Read each XBIN back once written and check it draws exactly what was
+    /// converted
+    #[clap(long)]
+    verify: bool,
+    /// Keep to the XBIN features a viewer reliably supports, dropping others
+    /// with a warning
+    #[clap(long, value_enum, value_name = "VIEWER")]
+    compat: Option<compat::Viewer>,
+    /// Split XBIN output into numbered files of at most this many cells, as
+    /// COLUMNSxROWS with either side optional, listed in a JSON manifest;
+    /// grids too large for one XBIN are always split
+    #[clap(long, value_name = "SIZE", value_parser = tile::Size::parse)]
+    tile: Option<tile::Size>,
+    /// Also save a PNG of the cells drawn with the output's font and palette,
+    /// or for .gif paths an animation of every frame of a GIF source
+    #[clap(long, value_name = "PNG")]
+    preview: Option<PathBuf>,
+    /// Also save a PNG of the source beside the rendering, both at the
+    /// target's pixel aspect
+    #[clap(long, value_name = "PNG")]
+    compare: Option<PathBuf>,
+    /// Add a third panel to --compare of how far apart the two are
+    #[clap(long, requires = "compare")]
+    compare_difference: bool,
+    /// Also write the art beside OUTPUT in each of these formats, by extension,
+    /// from the same conversion, which keeps to what all of them can show;
+    /// png saves a preview
+    #[clap(long, value_delimiter = ',', value_name = "EXTENSIONS")]
+    emit: Vec<String>,
+    /// Font pixels per inch on PDF and PostScript pages
+    #[clap(long, value_name = "DPI", default_value_t = 96.0, value_parser = positive)]
+    dpi: f64,
+    /// Show the rendering in the terminal with the kitty or iTerm2 inline
+    /// image protocol
+    #[clap(long)]
+    show_inline: bool,
+    /// Print the art to this terminal, fitted to its width, colors and
+    /// encoding
+    #[clap(long)]
+    show: bool,
+    /// Play the recording asciinema output would hold on stdout as it
+    /// converts, for terminals and pipes; OUTPUT can then be - to write no
+    /// file
+    #[clap(long)]
+    stream: bool,
+    /// Seconds between the lines of an asciinema recording of a still
+    /// source; the frames of an animated one keep their own delays
+    #[clap(long, value_name = "SECONDS", default_value_t = 0.05)]
+    line_delay: f64,
+    /// Pace recordings as though sent over a modem at this many bits a
+    /// second, such as 2400, 9600 or 14400, each line following the last as
+    /// soon as it has arrived
+    #[clap(long, value_name = "RATE", value_parser = clap::value_parser!(u32).range(1..))]
+    baud: Option<u32>,
+    /// Frames per second to pace animated sources at, dropping or holding
+    /// frames to fit; ANSImations, whose viewers have no delays of their own,
+    /// play at 10 unless given, while GIF previews and recordings otherwise
+    /// keep each frame's delay
+    #[clap(long, value_name = "FPS", value_parser = positive)]
+    fps: Option<f64>,
+    /// Seconds every frame of an animated source shows for, in place of its
+    /// own delays
+    #[clap(long, value_name = "SECONDS", value_parser = positive)]
+    frame_delay: Option<f64>,
+    /// File of delays for particular frames, overriding the others, as lines
+    /// of a frame number from 1 and seconds
+    #[clap(long, value_name = "FILE", value_parser = frames::Timing::load)]
+    timing: Option<frames::Timing>,
+    /// Merge each frame of an animation that looks the same as the one
+    /// before into it, which then shows for both
+    #[clap(long)]
+    merge_frames: bool,
+    /// Cells a frame can differ in and still look the same to --merge-frames
+    #[clap(
+        long,
+        value_name = "CELLS",
+        default_value_t = 0,
+        requires = "merge_frames"
+    )]
+    merge_threshold: usize,
+    /// Times animations play, or infinite; GIF previews loop forever unless
+    /// given, and ANSImations and recordings, which can't, play once
+    #[clap(long = "loop", value_name = "N|infinite", value_parser = frames::Plays::parse)]
+    plays: Option<frames::Plays>,
+    /// Make an ANSImation or recording that scrolls through art taller than
+    /// the screen, from its top for up or its bottom for down
+    #[clap(long, value_enum, value_name = "DIRECTION")]
+    scroll: Option<scroll::Direction>,
+    /// Rows a second a scrolling recording reveals; ANSImations scroll as
+    /// fast as they're drawn
+    #[clap(long, value_name = "ROWS", default_value_t = 10.0, value_parser = positive)]
+    speed: f64,
+    /// Carry on converting an animation where an interrupted run with the
+    /// same arguments stopped, from the checkpoint it keeps beside OUTPUT
+    #[clap(long)]
+    resume: bool,
+    /// Title for the SAUCE record
+    #[clap(help_heading = "SAUCE", long, value_name = "TITLE", value_parser = sauce::field(35))]
+    title: Option<String>,
+    /// Author for the SAUCE record
+    #[clap(help_heading = "SAUCE", long, value_name = "AUTHOR", value_parser = sauce::field(20))]
+    author: Option<String>,
+    /// Group for the SAUCE record
+    #[clap(help_heading = "SAUCE", long, value_name = "GROUP", value_parser = sauce::field(20))]
+    group: Option<String>,
+    /// Creation date for the SAUCE record, as YYYYMMDD or YYYY-MM-DD [default: today]
+    #[clap(help_heading = "SAUCE", long, value_name = "DATE", value_parser = sauce::parse_date)]
+    date: Option<String>,
+    /// A line for the SAUCE comment block, up to 64 characters; repeat for up
+    /// to 255 lines
+    #[clap(help_heading = "SAUCE", long = "comment", value_name = "LINE", value_parser = sauce::field(64))]
+    comments: Vec<String>,
+    /// Write no SAUCE record, which XBINs otherwise get and text outputs get
+    /// whenever a SAUCE field is set
+    #[clap(help_heading = "SAUCE", long)]
+    no_sauce: bool,
+    /// The palette found across every frame of an animation, which each is
+    /// then converted with.
+    #[clap(skip)]
+    frame_palette: Option<Vec<RGBA>>,
+    /// Where the frames of an animation are kept as they're converted.
+    #[clap(skip)]
+    checkpoint: Option<Checkpoint>,
+    /// The slides to show after the INPUT, for the slideshow subcommand.
+    #[clap(skip)]
+    slideshow: Option<Slideshow>,
+}
+
+// Interleaved gradient noise: cheap, tileless and with most of its energy in
+// the high frequencies, so it breaks up banding without visible clumping.
+fn blue_noise(x: u32, y: u32) -> f32 {
+    let dot = 0.06711056 * x as f32 + 0.00583715 * y as f32;
+    (52.982918 * dot.fract()).fract()
 }
 
-fn quantize_image_16(image: &DynamicImage) -> (Palettes, Vec<u8>) {
+// With a fixed palette every entry is handed to imagequant as a fixed color,
+// and its output is mapped back onto the fixed palette's own order.
+fn quantize_image_16(
+    image: &DynamicImage,
+    colors: u32,
+    fixed: Option<&[RGBA]>,
+    cb_safe: Option<Deficiency>,
+    grain: f32,
+    remap_only: bool,
+) -> (Palettes, Vec<u8>) {
     let (width, height) = image.dimensions();
     let pixels: Vec<RGBA> = image
         .pixels()
-        .map(|(_, _, pixel)| RGBA {
-            r: pixel[0],
-            g: pixel[1],
-            b: pixel[2],
-            a: 255,
+        .map(|(x, y, pixel)| {
+            let offset = (blue_noise(x, y) - 0.5) * 2.0 * grain;
+            let perturb = |value: u8| (value as f32 + offset).round().clamp(0.0, 255.0) as u8;
+            RGBA {
+                r: perturb(pixel[0]),
+                g: perturb(pixel[1]),
+                b: perturb(pixel[2]),
+                a: 255,
+            }
         })
         .collect();
+    if let (true, Some(fixed)) = (remap_only, fixed) {
+        let palettes = Palettes::new(fixed);
+        let pixels: Vec<_> = pixels.iter().map(chunks::rgba_to_oklab).collect();
+        let indexes = refine::remap(&pixels, &palettes);
+        return (palettes, indexes);
+    }
     let mut liq = imagequant::new();
     liq.set_speed(1).unwrap();
-    liq.set_max_colors(16).unwrap();
+    liq.set_max_colors(fixed.map_or(colors, |fixed| fixed.len() as u32))
+        .unwrap();
     let mut image = liq
         .new_image(&pixels[..], width as usize, height as usize, 0.0)
         .unwrap();
+    for color in fixed.unwrap_or_default() {
+        image.add_fixed_color(*color).unwrap();
+    }
     let mut res = liq.quantize(&mut image).unwrap();
-    let (palette, indexes) = res.remapped(&mut image).unwrap();
+    let (mut palette, mut indexes) = res.remapped(&mut image).unwrap();
+    if let Some(fixed) = fixed {
+        let position: Vec<u8> = palette
+            .iter()
+            .map(|color| {
+                let distance = |other: &RGBA| {
+                    (color.r as i32 - other.r as i32).pow(2)
+                        + (color.g as i32 - other.g as i32).pow(2)
+                        + (color.b as i32 - other.b as i32).pow(2)
+                };
+                (0..fixed.len())
+                    .min_by_key(|index| distance(&fixed[*index]))
+                    .expect("palette") as u8
+            })
+            .collect();
+        indexes = indexes
+            .iter()
+            .map(|index| position[*index as usize])
+            .collect();
+        palette = fixed.to_vec();
+    } else if let Some(deficiency) = cb_safe {
+        cvd::separate(&mut palette, deficiency);
+    }
     let palettes = Palettes::new(&palette);
-    let mut quantized = DynamicImage::new_rgb8(width, height);
-    quantized
-        .as_mut_rgb8()
-        .unwrap()
-        .rchunks_exact_mut(3)
-        .zip(indexes.iter().rev())
-        .for_each(|(dst, index)| {
-            let color = palettes.rgba[*index as usize];
-            dst[0] = color.r;
-            dst[1] = color.g;
-            dst[2] = color.b;
-        });
     (palettes, indexes)
 }
 
-fn find_closest(rgba: &RGBA, palette: &[RGBA]) -> u8 {
-    let mut closest: Option<u8> = None;
-    let mut closest_distance: f32 = 0.0;
-    let ok_palette: Vec<_> = palette
+fn report_palette_usage(palettes: &Palettes, chunks: &[Chunk], font: &Font, min_usage: usize) {
+    let mut usage = [0usize; 16];
+    for chunk in chunks {
+        let (glyph, fg) = font.cell_glyph(chunk);
+        if !glyph.iter().all(|row| *row == 0xff) {
+            usage[chunk.bg as usize] += 1;
+        }
+        if !glyph.iter().all(|row| *row == 0x00) && fg != chunk.bg {
+            usage[fg as usize] += 1;
+        }
+    }
+    eprintln!("Palette usage:");
+    let usage = &usage[..palettes.count];
+    for (index, (cells, color)) in usage.iter().zip(palettes.rgba.iter()).enumerate() {
+        eprintln!(
+            "  {:2}  #{:02x}{:02x}{:02x}  {} cells",
+            index, color.r, color.g, color.b, cells
+        );
+    }
+    let rare = usage.iter().filter(|cells| **cells < min_usage).count();
+    for (index, cells) in usage.iter().enumerate() {
+        if *cells < min_usage {
+            eprintln!("warning: palette entry {index} is used by only {cells} cells");
+        }
+    }
+    if rare > 0 {
+        eprintln!(
+            "warning: consider --colors {} to drop rarely used entries",
+            (palettes.count - rare).max(2)
+        );
+    }
+}
+
+fn encode(
+    options: &Options,
+    image: &DynamicImage,
+    palettes: &Palettes,
+    indexes: Vec<u8>,
+) -> Vec<Chunk> {
+    let encoding = Encoding {
+        strategy: options.chunk_opt,
+        diffuse: options.diffuse,
+        dither: options.chunk_dither,
+        compress_bias: options.compress_bias,
+    };
+    break_into_chunks(image, palettes, indexes, &encoding)
+}
+
+// The SAUCE name of a bundled IBM font.
+fn ibm_font_name(font: &Font) -> Option<&'static str> {
+    if *font == Font::ibm_vga_8x16() {
+        Some("IBM VGA")
+    } else if *font == Font::ibm_vga_8x14() {
+        Some("IBM EGA")
+    } else if *font == Font::ibm_vga_8x8() {
+        Some("IBM VGA50")
+    } else {
+        None
+    }
+}
+
+// The SAUCE name of the font a text output is meant to be viewed with, in the
+// --codepage it's encoded in.
+fn sauce_font_name(options: &Options, font: &Font) -> Option<String> {
+    let name = if options.target == Target::Amiga {
+        "Amiga Topaz 1+"
+    } else if options.target == Target::C64 {
+        "C64 PETSCII unshifted"
+    } else if options.target == Target::Atari {
+        "Atari ATASCII"
+    } else {
+        ibm_font_name(font)?
+    };
+    // Only the IBM fonts come in other codepages.
+    match options.codepage.number() {
+        Some(number) if name.starts_with("IBM") => Some(format!("{name} {number}")),
+        _ => Some(name.to_string()),
+    }
+}
+
+// ANSiFlags for text drawn as the conversion assumed: 8-pixel glyphs on square
+// pixels for PCs, and unset for the Amiga and 8-bit machines, whose viewers ignore them.
+fn text_flags(target: Target, non_blink: bool) -> u8 {
+    match target {
+        Target::Pc => sauce::ansi_flags(non_blink, Some(8), sauce::AspectRatio::Square),
+        Target::Amiga | Target::C64 | Target::Atari | Target::Spectrum => {
+            sauce::ansi_flags(non_blink, None, sauce::AspectRatio::Unset)
+        }
+    }
+}
+
+// The palette chosen with --palette-file or --palette, or else the target's
+// own.
+fn fixed_palette(options: &Options) -> Option<&[RGBA]> {
+    options
+        .frame_palette
+        .as_deref()
+        .or(options
+            .palette_file
+            .as_ref()
+            .map(|file| file.colors.as_slice()))
+        .or(options
+            .palette
+            .or(options.target.palette())
+            .map(palette::Standard::colors))
+}
+
+fn refine_palette(
+    options: &Options,
+    image: &DynamicImage,
+    mut palettes: Palettes,
+    mut chunks: Vec<Chunk>,
+) -> (Palettes, Vec<Chunk>) {
+    let pixels = source_pixels(image);
+    let total_error = |palettes: &Palettes, chunks: &[Chunk]| -> f32 {
+        chunk_errors(&pixels, palettes, chunks).iter().sum()
+    };
+    let mut error = total_error(&palettes, &chunks);
+    if options.refine > 0 && fixed_palette(options).is_some() {
+        eprintln!("warning: --refine can't change a fixed palette");
+        return (palettes, chunks);
+    }
+    for _ in 0..options.refine {
+        let mut palette = refine::refit_palette(&pixels, &palettes, &chunks);
+        if let Some(deficiency) = options.cb_safe {
+            cvd::separate(&mut palette, deficiency);
+        }
+        let candidate = Palettes::new(&palette);
+        let indexes = refine::remap(&pixels, &candidate);
+        let candidate_chunks = encode(options, image, &candidate, indexes);
+        let candidate_error = total_error(&candidate, &candidate_chunks);
+        if candidate_error >= error {
+            break;
+        }
+        (palettes, chunks, error) = (candidate, candidate_chunks, candidate_error);
+    }
+    (palettes, chunks)
+}
+
+// Scales the source to the requested width, and squeezes it vertically by
+// `aspect` so the output keeps the source's proportions on screen. Each cell
+// covers `cell` source pixels, and each source pixel spans `repeat` cells.
+fn fit_to_columns(
+    image: DynamicImage,
+    cell: (u32, u32),
+    columns: Option<u32>,
+    repeat: u32,
+    aspect: f32,
+) -> DynamicImage {
+    if columns.is_none() && aspect == 1.0 {
+        return image;
+    }
+    let (cell_width, cell_height) = cell;
+    let width = columns.map_or(image.width(), |columns| {
+        (columns * cell_width / repeat).max(1)
+    });
+    let height = image.height() as u64 * width as u64 / image.width().max(1) as u64;
+    let height = (height as f32 / aspect) as u32;
+    let height = (height.div_ceil(cell_height) * cell_height).max(cell_height);
+    image.resize_exact(width, height, FilterType::Lanczos3)
+}
+
+// The font a mode draws with, which is the `--font` one for text-font modes.
+// Only glyph mode can pick from a second bank of 256 glyphs.
+fn mode_font(options: &Options, mode: Mode) -> Font {
+    let font = match &options.font {
+        Some(font) if mode.uses_text_font() => font.clone(),
+        _ => mode.font(),
+    };
+    match &options.second_font {
+        _ if mode != Mode::Glyph => font.first_bank(),
+        Some(second) => font.with_second_bank(second).unwrap_or_else(|error| {
+            exit(Cli::command().error(
+                ErrorKind::ValueValidation,
+                format!("--second-font: {error}"),
+            ))
+        }),
+        None => font,
+    }
+}
+
+struct Conversion {
+    image: DynamicImage,
+    columns: usize,
+    palettes: Palettes,
+    chunks: Vec<Chunk>,
+}
+
+// Scales the source to the cells of the mode, `columns` wide if given, with
+// how many times each cell is to be repeated across.
+fn fit(
+    options: &Options,
+    mode: Mode,
+    source: &DynamicImage,
+    columns: Option<u32>,
+) -> (DynamicImage, u32) {
+    let font = mode_font(options, mode);
+    let (cell, pixel_aspect) = (mode.cell_pixels(&font), mode.pixel_aspect(&font));
+    let pixel_aspect = pixel_aspect * options.target.pixel_height();
+    let (repeat, aspect) = match options.cell_aspect {
+        CellAspect::Squeeze => (1, pixel_aspect),
+        CellAspect::Stretch => (1, 1.0),
+        CellAspect::Double if cell == (1, 1) => (2, pixel_aspect / 2.0),
+        CellAspect::Double => {
+            eprintln!("warning: --cell-aspect double needs one pixel per cell, squeezing instead");
+            (1, pixel_aspect)
+        }
+    };
+    let image = fit_to_columns(source.clone(), cell, columns, repeat, aspect);
+    (image, repeat)
+}
+
+fn convert(
+    options: &Options,
+    mode: Mode,
+    source: &DynamicImage,
+    columns: Option<u32>,
+) -> Conversion {
+    let font = mode_font(options, mode);
+    let (image, repeat) = fit(options, mode, source, columns);
+    let (mut palettes, indexes) = quantize_image_16(
+        &image,
+        options.colors,
+        fixed_palette(options),
+        options.cb_safe,
+        options.grain,
+        options.no_quantize,
+    );
+    // Encode with the colors xterm-256 and 99-color mIRC output will really
+    // show.
+    if options.format == Some(Format::Xterm256) {
+        let xterm = palette::xterm_256();
+        let snapped: Vec<RGBA> = palettes.rgba[..palettes.count]
+            .iter()
+            .map(|color| xterm[palette::nearest_xterm_256(color) as usize - 16])
+            .collect();
+        palettes = Palettes::new(&snapped);
+    } else if options.format == Some(Format::Irc99) {
+        let mirc = palette::mirc_99();
+        let snapped: Vec<RGBA> = palettes.rgba[..palettes.count]
+            .iter()
+            .map(|color| mirc[palette::nearest_mirc(color, 99) as usize])
+            .collect();
+        palettes = Palettes::new(&snapped);
+    }
+    let (columns, palettes, chunks) = match mode {
+        Mode::Pixel => {
+            let mut chunks = encode(options, &image, &palettes, indexes);
+            if options.target == Target::Spectrum {
+                // Black is the same either side of the bright bit.
+                let colors: Vec<u8> = palettes
+                    .rgba
+                    .iter()
+                    .map(palette::nearest_spectrum)
+                    .collect();
+                let allowed = |a: u8, b: u8| {
+                    let (a, b) = (colors[a as usize], colors[b as usize]);
+                    a % 8 == 0 || b % 8 == 0 || (a >= 8) == (b >= 8)
+                };
+                chunks::clash(
+                    &image,
+                    &palettes,
+                    &mut chunks,
+                    spectrum::ATTRIBUTE_ROWS,
+                    allowed,
+                );
+            }
+            let (palettes, chunks) = refine_palette(options, &image, palettes, chunks);
+            (image.width() as usize / 8, palettes, chunks)
+        }
+        Mode::Halfblock => {
+            let (width, height) = (image.width() as usize, image.height() as usize);
+            let chunks = halfblock::encode(&palettes, &indexes, width, height);
+            (width, palettes, chunks)
+        }
+        Mode::Quadrant => {
+            let (width, height) = (image.width() as usize, image.height() as usize);
+            let chunks = quadrant::encode(&palettes, &indexes, width, height);
+            (width.div_ceil(2), palettes, chunks)
+        }
+        Mode::Shades => {
+            let chunks = shades::encode(&palettes, &image, &shades::SHADES);
+            (image.width() as usize, palettes, chunks)
+        }
+        Mode::Density => {
+            let library = shades::density_library(&font, options.charset.as_ref());
+            let chunks = shades::encode(&palettes, &image, &library);
+            (image.width() as usize, palettes, chunks)
+        }
+        Mode::Glyph => {
+            // The C64 has one background register, which gets the most
+            // common color, and the Atari draws text on its first color.
+            let background = match options.target {
+                Target::C64 => {
+                    let mut counts = [0usize; 16];
+                    for index in &indexes {
+                        counts[*index as usize] += 1;
+                    }
+                    Some((0..16).max_by_key(|index| counts[*index]).unwrap() as u8)
+                }
+                Target::Atari => Some(0),
+                _ => None,
+            };
+            let chunks = glyph::encode(
+                &palettes,
+                &image,
+                &font,
+                options.charset.as_ref(),
+                options.glyph_metric,
+                background,
+            );
+            (image.width().div_ceil(8) as usize, palettes, chunks)
+        }
+        Mode::Ascii => {
+            let chunks = ascii::encode(&palettes, &image, &options.ramp, options.ascii_color);
+            (image.width() as usize, palettes, chunks)
+        }
+        Mode::Braille => {
+            let (width, height) = (image.width() as usize, image.height() as usize);
+            let chunks = braille::encode(&palettes, &indexes, width, height);
+            (width.div_ceil(2), palettes, chunks)
+        }
+        Mode::Sextant => {
+            let (width, height) = (image.width() as usize, image.height() as usize);
+            let chunks = sextant::encode(&palettes, &indexes, width, height);
+            (width.div_ceil(2), palettes, chunks)
+        }
+        Mode::Vga50 => {
+            let (width, height) = (image.width() as usize, image.height() as usize);
+            let chunks = vga50::encode(&palettes, &indexes, width, height);
+            (width.div_ceil(4), palettes, chunks)
+        }
+        Mode::Mosaic => {
+            let chunks = mosaic::encode(&indexes);
+            (image.width() as usize, palettes, chunks)
+        }
+        Mode::Auto => unreachable!("auto picks another mode"),
+    };
+    let chunks = chunks
+        .into_iter()
+        .flat_map(|chunk| std::iter::repeat_n(chunk, repeat as usize))
+        .collect();
+    Conversion {
+        image,
+        columns: columns * repeat as usize,
+        palettes,
+        chunks,
+    }
+}
+
+// The most columns, up to --columns or the usual 44, at which the art leaves
+// room under a FILE_ID.DIZ's line limit for the footer.
+fn diz_columns(options: &Options, source: &DynamicImage, footer: usize) -> u32 {
+    if footer >= diz::LINES {
+        exit(Cli::command().error(
+            ErrorKind::TooManyValues,
+            format!("a description has room for {} lines at most", diz::LINES),
+        ));
+    }
+    let lines = diz::LINES - footer;
+    let mut columns = options.columns.unwrap_or(diz::COLUMNS);
+    if columns > diz::MAX_COLUMNS {
+        eprintln!(
+            "warning: file lists show descriptions {} columns wide at most",
+            diz::MAX_COLUMNS
+        );
+    }
+    // Auto mode's candidates all fill the same cells, so ascii stands in.
+    let mode = match options.mode {
+        Mode::Auto => Mode::Ascii,
+        mode => mode,
+    };
+    loop {
+        let conversion = convert(options, mode, source, Some(columns));
+        let rows = conversion.chunks.len() / conversion.columns;
+        if rows <= lines || columns == 1 {
+            return columns;
+        }
+        columns = (columns as usize * lines / rows).clamp(1, columns as usize - 1) as u32;
+    }
+}
+
+// Converts with each candidate mode at the same width, renders the result
+// and keeps the mode whose rendering is structurally closest to the source.
+fn pick_mode(options: &Options, source: &DynamicImage, columns: Option<u32>) -> (Mode, Conversion) {
+    let columns = columns.unwrap_or(source.width().div_ceil(8));
+    eprintln!("Mode comparison (SSIM):");
+    let mut best: Option<(Mode, Conversion, f32)> = None;
+    for mode in [Mode::Pixel, Mode::Halfblock, Mode::Glyph] {
+        let conversion = convert(options, mode, source, Some(columns));
+        let rendering = render::render(
+            conversion.columns,
+            &mode_font(options, mode),
+            &conversion.palettes.rgba,
+            &conversion.chunks,
+        );
+        let reference = source
+            .resize_exact(rendering.width(), rendering.height(), FilterType::Lanczos3)
+            .to_rgb8();
+        let score = metrics::ssim(&reference, &rendering);
+        eprintln!(
+            "  {:<10} {:.4}",
+            mode.to_possible_value().unwrap().get_name(),
+            score
+        );
+        if best
+            .as_ref()
+            .is_none_or(|(_, _, best_score)| score > *best_score)
+        {
+            best = Some((mode, conversion, score));
+        }
+    }
+    let (mode, conversion, _) = best.expect("candidates");
+    eprintln!(
+        "Using {} mode",
+        mode.to_possible_value().unwrap().get_name()
+    );
+    (mode, conversion)
+}
+
+// Converts the input through every mode at the same width and saves the
+// renderings side by side.
+fn compare_modes(input: &Path, output: &PathBuf, options: &Options) {
+    let source = open_source(input);
+    let columns = options.columns.unwrap_or(source.width().div_ceil(8));
+    let tiles: Vec<(String, image::RgbImage)> = Mode::value_variants()
         .iter()
-        .map(|color| {
-            srgb_to_oklab(oklab::RGB {
-                r: color.r,
-                g: color.g,
-                b: color.b,
-            })
+        .filter(|mode| **mode != Mode::Auto)
+        .map(|mode| {
+            let conversion = convert(options, *mode, &source, Some(columns));
+            let rendering = render::render(
+                conversion.columns,
+                &mode_font(options, *mode),
+                &conversion.palettes.rgba,
+                &conversion.chunks,
+            );
+            (
+                mode.to_possible_value().unwrap().get_name().to_string(),
+                rendering,
+            )
+        })
+        .collect();
+    sheet::contact_sheet(&tiles, &Font::ibm_vga_8x16())
+        .save(output)
+        .unwrap();
+}
+
+// Saves the source next to the rendering of its cells, both stretched to the
+// target's pixel height, and with --compare-difference their difference.
+fn save_comparison(
+    path: &Path,
+    options: &Options,
+    source: &DynamicImage,
+    font: &Font,
+    columns: usize,
+    palettes: &Palettes,
+    chunks: &[Chunk],
+) {
+    let rendering = render::render(columns, font, &palettes.rgba, chunks);
+    let (width, height) = (
+        rendering.width(),
+        (rendering.height() as f32 * options.target.pixel_height()).round() as u32,
+    );
+    let rendering = image::imageops::resize(&rendering, width, height, FilterType::Nearest);
+    let reference = source
+        .resize_exact(width, height, FilterType::Lanczos3)
+        .to_rgb8();
+    let mut tiles = vec![
+        ("source".to_string(), reference),
+        ("conversion".to_string(), rendering),
+    ];
+    if options.compare_difference {
+        let difference = metrics::difference(&tiles[0].1, &tiles[1].1);
+        tiles.push(("difference".to_string(), difference));
+    }
+    sheet::contact_sheet(&tiles, &Font::ibm_vga_8x16())
+        .save(path)
+        .unwrap();
+}
+
+fn positive(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(number) if number > 0.0 && number.is_finite() => Ok(number),
+        _ => Err(format!("{value:?} is not a positive number")),
+    }
+}
+
+fn fraction(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(number) if (0.0..=1.0).contains(&number) => Ok(number),
+        _ => Err(format!("{value:?} is not a number from 0 to 1")),
+    }
+}
+
+// The Unicode characters a mode's codepoints stand for.
+fn mode_chars(mode: Mode) -> fn(u8) -> char {
+    match mode {
+        Mode::Braille => braille::to_char,
+        Mode::Sextant => sextant::to_char,
+        Mode::Quadrant => quadrant::to_char,
+        _ => |codepoint| CP437[codepoint as usize],
+    }
+}
+
+// Prints the art to this terminal, converting again at the terminal's width
+// with only what it can show: the ansi palette without 256 colors or UTF-8,
+// and halfblocks for modes with generated fonts or characters outside its
+// encoding.
+fn show(options: &Options, mode: Mode, source: &DynamicImage) {
+    let terminal = terminal::detect();
+    let unicode = matches!(mode, Mode::Braille | Mode::Sextant);
+    let mode = if (unicode && terminal.utf8) || mode.uses_text_font() {
+        mode
+    } else {
+        Mode::Halfblock
+    };
+    let mut options = options.clone();
+    if (terminal.colors == Colors::Ansi16 || !terminal.utf8) && fixed_palette(&options).is_none() {
+        options.palette = Some(palette::Standard::Ansi);
+    }
+    options.second_font = None;
+    options.font = options.font.map(|font| {
+        if font.glyph_count() > 256 {
+            font.first_bank()
+        } else {
+            font
+        }
+    });
+    let columns = options
+        .columns
+        .unwrap_or(source.width().div_ceil(8))
+        .min(terminal.columns as u32);
+    let conversion = convert(&options, mode, source, Some(columns));
+    let mut stdout = std::io::stdout().lock();
+    if terminal.utf8 {
+        ansi::write_utf8_ansi(
+            &mut stdout,
+            conversion.columns,
+            &conversion.palettes.rgba,
+            &conversion.chunks,
+            mode_chars(mode),
+            terminal.colors,
+        );
+    } else {
+        // Row by row, as the terminal won't wrap where the art does.
+        let font = mode_font(&options, mode);
+        for row in conversion.chunks.chunks(conversion.columns) {
+            ansi::write_ansi(&mut stdout, conversion.columns, &font, row);
+            std::io::Write::write_all(&mut stdout, b"\r\n").unwrap();
+        }
+    }
+}
+
+// Frames per second ANSImations play at without --fps.
+const DEFAULT_FPS: f64 = 10.0;
+
+// The most frames sampled for the palette of an animation.
+const PALETTE_FRAMES: usize = 32;
+
+// The palette of an animation's frames scaled as they'll be converted, from
+// frames spread evenly through it.
+fn frame_palette(options: &Options, mode: Mode, frames: &[(Duration, DynamicImage)]) -> Vec<RGBA> {
+    let count = frames.len().min(PALETTE_FRAMES);
+    let samples: Vec<DynamicImage> = (0..count)
+        .into_par_iter()
+        .map(|index| {
+            let (_, source) = &frames[index * frames.len() / count];
+            fit(options, mode, source, options.columns).0
         })
         .collect();
-    let ok_rgba = srgb_to_oklab(oklab::RGB {
-        r: rgba.r,
-        g: rgba.g,
-        b: rgba.b,
+    let (width, height) = samples[0].dimensions();
+    let mut montage = image::RgbaImage::new(width, height * count as u32);
+    for (index, sample) in samples.iter().enumerate() {
+        image::imageops::replace(
+            &mut montage,
+            &sample.to_rgba8(),
+            0,
+            height as i64 * index as i64,
+        );
+    }
+    let montage = DynamicImage::ImageRgba8(montage);
+    let (palettes, _) = quantize_image_16(
+        &montage,
+        options.colors,
+        None,
+        options.cb_safe,
+        options.grain,
+        false,
+    );
+    palettes.rgba[..palettes.count].to_vec()
+}
+
+// How long frame `index` of an animation shows: its delay in the --timing
+// file, or else --frame-delay, or else the source's own.
+fn frame_duration(options: &Options, index: usize, duration: Duration) -> Duration {
+    options
+        .timing
+        .as_ref()
+        .and_then(|timing| timing.delay(index))
+        .or(options.frame_delay.map(Duration::from_secs_f64))
+        .unwrap_or(duration)
+}
+
+// Converts every frame of the source the way the first was converted, with
+// how long each shows. Frames share a palette found across them first, which
+// keeps colors from flickering and lets them convert in parallel. Each is
+// kept in the checkpoint as it's done, and frames already kept there are
+// taken back from it. With --merge-frames, frames much like the one before
+// are merged into it.
+fn convert_frames(
+    frames: Vec<(Duration, DynamicImage)>,
+    options: &Options,
+    mode: Mode,
+) -> Vec<(Duration, Conversion)> {
+    let count = frames.len();
+    let mut converted = Vec::with_capacity(count);
+    convert_frames_in_order(frames, options, mode, count, |frame| {
+        converted.push(frame);
+        true
     });
-    for (index, color) in ok_palette.iter().enumerate() {
-        let distance = (color.l - ok_rgba.l).powi(2)
-            + (color.a - ok_rgba.a).powi(2)
-            + (color.b - ok_rgba.b).powi(2);
-        if closest.is_none() || distance < closest_distance {
-            closest_distance = distance;
-            closest = Some(index as u8);
-        }
-    }
-    closest.expect("match")
-}
-
-struct Chunk {
-    fg: u8,
-    bg: u8,
-    codepoint: u8,
-}
-
-fn break_into_chunks(palettes: &Palettes, mut indexes: Vec<u8>) -> Vec<Chunk> {
-    indexes
-        .chunks_exact_mut(8)
-        .map(|chunk| {
-            let mut scores: Vec<(u8, usize)> = (0..16)
-                .map(|index| {
-                    let mut score = 0;
-                    for color_index in chunk.iter() {
-                        if *color_index == index {
-                            score += 1;
-                        }
-                    }
-                    (index, score)
-                })
-                .collect();
-            scores.sort_by(|(_, a), (_, b)| b.cmp(a));
-            scores.resize(2, (0, 0));
-            let common_indexes: Vec<u8> = scores.into_iter().map(|(index, _)| index).collect();
-            let common_palette: Vec<RGBA> = common_indexes
+    if !options.merge_frames {
+        return converted;
+    }
+    frames::merged(converted, |first: &Conversion, frame| {
+        first.palettes.rgba == frame.palettes.rgba
+            && first.chunks.len() == frame.chunks.len()
+            && first
+                .chunks
                 .iter()
-                .map(|index| palettes.rgba[*index as usize])
+                .zip(&frame.chunks)
+                .filter(|(a, b)| a != b)
+                .count()
+                <= options.merge_threshold
+    })
+}
+
+// Converts the frames as `convert_frames` does, `batch` at a time in
+// parallel, handing each in turn to `each` until it returns false.
+fn convert_frames_in_order(
+    frames: Vec<(Duration, DynamicImage)>,
+    options: &Options,
+    mode: Mode,
+    batch: usize,
+    mut each: impl FnMut((Duration, Conversion)) -> bool,
+) {
+    let font = mode_font(options, mode);
+    let mut options = options.clone();
+    // Single frames are quick enough to start over.
+    let checkpoint = options.checkpoint.take().filter(|_| frames.len() > 1);
+    if let Some(checkpoint) = &checkpoint {
+        checkpoint.begin();
+    }
+    if frames.len() > 1 && fixed_palette(&options).is_none() {
+        let kept = checkpoint.as_ref().and_then(Checkpoint::palette);
+        let palette = kept.unwrap_or_else(|| frame_palette(&options, mode, &frames));
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.save_palette(&palette);
+        }
+        options.frame_palette = Some(palette);
+        if options.refine > 0 {
+            eprintln!("warning: --refine can't change the palette an animation's frames share");
+            options.refine = 0;
+        }
+    }
+    let options = &options;
+    let convert_frame = |index: usize, duration: Duration, source: &DynamicImage| {
+        let duration = frame_duration(options, index, duration);
+        let kept = checkpoint
+            .as_ref()
+            .and_then(|checkpoint| checkpoint.frame(index));
+        if let Some((columns, palettes, chunks)) = kept {
+            // Nothing drawing frames looks at their scaled images.
+            let image = DynamicImage::new_rgba8(0, 0);
+            let conversion = Conversion {
+                image,
+                columns,
+                palettes,
+                chunks,
+            };
+            return (duration, conversion);
+        }
+        let mut conversion = convert(options, mode, source, options.columns);
+        if options.blink_safe && font.glyph_count() <= 256 {
+            restrict_backgrounds(
+                &mut conversion.palettes,
+                &mut conversion.chunks,
+                &font,
+                options.charset.as_ref(),
+            );
+        }
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.save_frame(
+                index,
+                conversion.columns,
+                &conversion.palettes,
+                &conversion.chunks,
+            );
+        }
+        (duration, conversion)
+    };
+    for (start, frames) in (0..).step_by(batch.max(1)).zip(frames.chunks(batch.max(1))) {
+        let converted: Vec<(Duration, Conversion)> = frames
+            .par_iter()
+            .enumerate()
+            .map(|(index, (duration, source))| convert_frame(start + index, *duration, source))
+            .collect();
+        for frame in converted {
+            if !each(frame) {
+                return;
+            }
+        }
+    }
+}
+
+// The rows of truecolor UTF-8 ANSI drawing the cells.
+fn terminal_lines(
+    mode: Mode,
+    columns: usize,
+    palettes: &Palettes,
+    chunks: &[Chunk],
+) -> Vec<String> {
+    let mut text = Vec::new();
+    let to_char = mode_chars(mode);
+    ansi::write_utf8_ansi(
+        &mut text,
+        columns,
+        &palettes.rgba,
+        chunks,
+        to_char,
+        Colors::Truecolor,
+    );
+    String::from_utf8(text)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+// The frames of a slideshow starting from the INPUT: its slides converted
+// `columns` wide and made as tall as the tallest, each followed by the frames
+// of its transition to the next.
+fn slideshow_frames(
+    input: &Path,
+    show: &Slideshow,
+    options: &Options,
+    mode: Mode,
+    columns: usize,
+) -> Vec<(Duration, Vec<Chunk>)> {
+    let sources = std::iter::once(input)
+        .chain(show.slides.iter().map(PathBuf::as_path))
+        .map(|path| (show.duration, open_source(path)))
+        .collect();
+    let mut options = options.clone();
+    options.columns = Some(columns as u32);
+    let slides = convert_frames(sources, &options, mode);
+    let rows = slides
+        .iter()
+        .map(|(_, conversion)| conversion.chunks.len() / columns)
+        .max()
+        .unwrap();
+    let slides: Vec<(Duration, Vec<Chunk>)> = slides
+        .into_iter()
+        .map(|(duration, conversion)| {
+            (
+                duration,
+                slideshow::padded(&conversion.chunks, columns, rows),
+            )
+        })
+        .collect();
+    let steps = match show.transition {
+        slideshow::Transition::Cut => 0,
+        _ => {
+            let fps = options.fps.unwrap_or(DEFAULT_FPS);
+            (show.transition_duration.as_secs_f64() * fps)
+                .round()
+                .max(1.0) as usize
+        }
+    };
+    let mut frames = Vec::new();
+    for (index, (duration, chunks)) in slides.iter().enumerate() {
+        frames.push((*duration, chunks.clone()));
+        let Some((_, next)) = slides.get(index + 1) else {
+            continue;
+        };
+        for step in 1..=steps {
+            let progress = step as f64 / (steps + 1) as f64;
+            let cells = slideshow::between(show.transition, columns, chunks, next, progress);
+            frames.push((show.transition_duration / steps as u32, cells));
+        }
+    }
+    frames
+}
+
+// The truecolor UTF-8 ANSI drawing a frame of an animation from the top of
+// the screen, cleared first for the first frame, or redrawing only the cells
+// that changed from the `previous` one.
+fn frame_text(mode: Mode, conversion: &Conversion, previous: Option<&Conversion>) -> String {
+    match previous {
+        Some(previous) if previous.chunks.len() == conversion.chunks.len() => {
+            let mut text = Vec::new();
+            ansi::write_utf8_changes(
+                &mut text,
+                conversion.columns,
+                &conversion.palettes.rgba,
+                &conversion.chunks,
+                &previous.palettes.rgba,
+                &previous.chunks,
+                mode_chars(mode),
+                Colors::Truecolor,
+            );
+            String::from_utf8(text).unwrap()
+        }
+        _ => {
+            let clear = if previous.is_none() { "\x1b[2J" } else { "" };
+            let lines = terminal_lines(
+                mode,
+                conversion.columns,
+                &conversion.palettes,
+                &conversion.chunks,
+            );
+            format!("{clear}\x1b[H{}", lines.join("\r\n"))
+        }
+    }
+}
+
+// Records a still source being drawn line by line, or an animated one frame
+// by frame from the top of the screen, each after the first redrawing only
+// the cells that changed, `plays` times over. No line break follows the last
+// row, which would scroll the first off a terminal as tall as the art. Gives
+// the rows of the screen it's drawn on, the events retimed to the modem
+// --baud simulates if given, and when the last of them finishes showing.
+fn record(
+    input: &Path,
+    options: &Options,
+    mode: Mode,
+    columns: usize,
+    palettes: &Palettes,
+    chunks: &[Chunk],
+    plays: usize,
+) -> (usize, Vec<(Duration, String)>, Duration) {
+    let rows = chunks.len() / columns;
+    let (rows, events, end) = if let Some(direction) = options.scroll {
+        let lines = terminal_lines(mode, columns, palettes, chunks);
+        let events = scroll::events(&lines, direction, options.speed);
+        let rows = if rows > scroll::VIEW_ROWS {
+            scroll::SCREEN_ROWS
+        } else {
+            rows
+        };
+        let end = events.last().map_or(Duration::ZERO, |(time, _)| *time);
+        (rows, events, end)
+    } else {
+        let frames = source_frames(input);
+        if frames.len() > 1 {
+            let mut frames = convert_frames(frames, options, mode);
+            if let Some(fps) = options.fps {
+                frames = frames::paced(frames, fps);
+            }
+            // Frames after the first only redraw the cells that changed,
+            // which for a replay is from the last.
+            let order: Vec<usize> = (0..plays).flat_map(|_| 0..frames.len()).collect();
+            let mut time = Duration::ZERO;
+            let mut events = Vec::new();
+            for (index, frame) in order.iter().enumerate() {
+                let (duration, conversion) = &frames[*frame];
+                let previous = index.checked_sub(1).map(|index| &frames[order[index]].1);
+                let text = frame_text(mode, conversion, previous);
+                if !text.is_empty() {
+                    events.push((time, text));
+                }
+                time += *duration;
+            }
+            (rows, events, time)
+        } else {
+            let delay = match options.baud {
+                Some(_) => Duration::ZERO,
+                None => Duration::from_secs_f64(options.line_delay.max(0.0)),
+            };
+            let lines = terminal_lines(mode, columns, palettes, chunks);
+            let count = lines.len();
+            let events: Vec<(Duration, String)> = lines
+                .into_iter()
+                .enumerate()
+                .map(|(index, line)| {
+                    let end = if index + 1 < count { "\r\n" } else { "" };
+                    (delay * index as u32, format!("{line}{end}"))
+                })
                 .collect();
-            for color_index in chunk.iter_mut() {
-                if !common_indexes.contains(color_index) {
-                    let rgba = &palettes.rgba[*color_index as usize];
-                    *color_index = find_closest(rgba, &common_palette);
+            let end = delay * count.saturating_sub(1) as u32;
+            (rows, events, end)
+        }
+    };
+    match options.baud {
+        Some(baud) => {
+            let events = baud::paced(&events, baud);
+            let arrived = events.last().map_or(Duration::ZERO, |(time, _)| *time);
+            (rows, events, end.max(arrived))
+        }
+        None => (rows, events, end),
+    }
+}
+
+// Saves a recording of the conversion.
+fn save_cast(
+    input: &Path,
+    output: &Path,
+    options: &Options,
+    mode: Mode,
+    columns: usize,
+    palettes: &Palettes,
+    chunks: &[Chunk],
+) {
+    let plays = file_plays(options, "recordings");
+    let (rows, events, _) = record(input, options, mode, columns, palettes, chunks, plays);
+    cast::save_cast(output, columns, rows, &events);
+}
+
+// Writes `text` to stdout no sooner than `time` after `start`, at the pace of
+// the modem `baud` simulates if given, and gives when it has all arrived.
+// Fails once stdout is closed.
+fn play(
+    stdout: &mut impl Write,
+    start: Instant,
+    time: Duration,
+    text: &str,
+    baud: Option<u32>,
+) -> std::io::Result<Duration> {
+    let events = vec![(time, text.to_string())];
+    let events = match baud {
+        Some(baud) => baud::paced(&events, baud),
+        None => events,
+    };
+    let mut arrived = time;
+    for (time, text) in events {
+        std::thread::sleep(time.saturating_sub(start.elapsed()));
+        stdout.write_all(text.as_bytes())?;
+        stdout.flush()?;
+        arrived = time;
+    }
+    Ok(arrived)
+}
+
+// Plays the conversion on stdout as a recording of it would show, until it
+// ends or stdout is closed, with looping forever replaying it from the
+// start. Animated sources play as their frames convert, a frame that isn't
+// ready in time putting back those after it.
+fn stream(
+    input: &Path,
+    options: &Options,
+    mode: Mode,
+    columns: usize,
+    palettes: &Palettes,
+    chunks: &[Chunk],
+) {
+    let (plays, forever) = match options.plays {
+        Some(frames::Plays::Times(times)) => (times as usize, false),
+        Some(frames::Plays::Forever) => (1, true),
+        None => (1, false),
+    };
+    let mut stdout = std::io::stdout().lock();
+    let frames = source_frames(input);
+    if frames.len() > 1 && options.scroll.is_none() {
+        stream_frames(&mut stdout, frames, options, mode, plays, forever);
+    } else {
+        let (_, events, end) = record(input, options, mode, columns, palettes, chunks, plays);
+        loop {
+            let start = Instant::now();
+            for (time, text) in &events {
+                if play(&mut stdout, start, *time, text, None).is_err() {
+                    return;
+                }
+            }
+            std::thread::sleep(end.saturating_sub(start.elapsed()));
+            if !forever {
+                break;
+            }
+        }
+    }
+    // Leaves the prompt below the art, in the terminal's own colors.
+    stdout.write_all(b"\x1b[0m\r\n").ok();
+}
+
+// Plays the frames of an animation as another thread converts them, a few at
+// a time so the first shows soon.
+fn stream_frames(
+    stdout: &mut impl Write,
+    frames: Vec<(Duration, DynamicImage)>,
+    options: &Options,
+    mode: Mode,
+    plays: usize,
+    forever: bool,
+) {
+    // Which frame shows for how long, known before any are converted.
+    let schedule: Vec<(Duration, usize)> = frames
+        .iter()
+        .enumerate()
+        .map(|(index, (duration, _))| (frame_duration(options, index, *duration), index))
+        .collect();
+    let schedule = match options.fps {
+        Some(fps) => frames::paced(schedule, fps),
+        None => schedule,
+    };
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let batch = rayon::current_num_threads();
+            convert_frames_in_order(frames, options, mode, batch, |(_, conversion)| {
+                sender.send(conversion).is_ok()
+            });
+        });
+        // Dropping the receiver once playing stops tells the conversion to.
+        let receiver = receiver;
+        let mut converted: Vec<Conversion> = Vec::new();
+        let start = Instant::now();
+        // When the frame is due, and when the last finished arriving over
+        // the modem --baud simulates, which the next can't begin before.
+        let mut time = Duration::ZERO;
+        let mut arrived = Duration::ZERO;
+        let mut previous = None;
+        for _ in 0..if forever { usize::MAX } else { plays } {
+            for (duration, frame) in &schedule {
+                if converted.len() <= *frame {
+                    while converted.len() <= *frame {
+                        let Ok(conversion) = receiver.recv() else {
+                            return;
+                        };
+                        converted.push(conversion);
+                    }
+                    time = time.max(start.elapsed());
                 }
+                let previous_frame = previous.map(|index| &converted[index]);
+                let text = frame_text(mode, &converted[*frame], previous_frame);
+                previous = Some(*frame);
+                if !text.is_empty() {
+                    match play(stdout, start, time.max(arrived), &text, options.baud) {
+                        Ok(time) => arrived = time,
+                        Err(_) => return,
+                    }
+                }
+                time += *duration;
             }
-            let bg = common_indexes[0];
-            let fg = common_indexes[1];
-            let bitmask = chunk.iter().map(|index| if *index == bg { 0 } else { 1 });
-            let codepoint = bitmask.fold(0, |acc, bit| (acc << 1) + bit);
-            Chunk { fg, bg, codepoint }
+        }
+        std::thread::sleep(time.saturating_sub(start.elapsed()));
+    });
+}
+
+// How many times --loop plays an animation in a file that has no way to loop,
+// once unless given.
+fn file_plays(options: &Options, files: &str) -> usize {
+    match options.plays {
+        Some(frames::Plays::Times(times)) => times as usize,
+        Some(frames::Plays::Forever) => {
+            eprintln!("warning: {files} can't loop forever, playing once");
+            1
+        }
+        None => 1,
+    }
+}
+
+// Saves renderings of every frame of the source as a GIF with the source's
+// delays, looping forever unless --loop says otherwise.
+fn save_gif_preview(input: &Path, path: &Path, options: &Options, mode: Mode) {
+    let font = mode_font(options, mode);
+    let mut frames = convert_frames(source_frames(input), options, mode);
+    if let Some(fps) = options.fps {
+        frames = frames::paced(frames, fps);
+    }
+    let renderings: Vec<Frame> = frames
+        .into_iter()
+        .map(|(duration, conversion)| {
+            let rendering = render::render(
+                conversion.columns,
+                &font,
+                &conversion.palettes.rgba,
+                &conversion.chunks,
+            );
+            let delay = Delay::from_saturating_duration(duration);
+            Frame::from_parts(DynamicImage::ImageRgb8(rendering).to_rgba8(), 0, 0, delay)
         })
-        .collect()
+        .collect();
+    let mut encoder = GifEncoder::new(std::fs::File::create(path).unwrap());
+    let repeat = match options.plays {
+        Some(frames::Plays::Times(times)) => Repeat::Finite(times - 1),
+        Some(frames::Plays::Forever) | None => Repeat::Infinite,
+    };
+    encoder.set_repeat(repeat).unwrap();
+    encoder.encode_frames(renderings).unwrap();
 }
 
-fn palette_to_bytes(palette: &[RGBA]) -> Vec<u8> {
-    let mut bytes = Vec::new();
-    for color in palette {
-        bytes.push(color.r);
-        bytes.push(color.g);
-        bytes.push(color.b);
+// Turns off whatever the --compat viewer can't show before conversion starts,
+// so the encoder works within its limits rather than having output trimmed.
+fn apply_compat(options: &mut Options) {
+    let Some(viewer) = options.compat else {
+        return;
+    };
+    let support = viewer.support();
+    let name = viewer.to_possible_value().unwrap().get_name().to_string();
+    let dropped = |feature: &str| eprintln!("warning: --compat {name} drops {feature}");
+    if !support.chars_512 {
+        if options.second_font.take().is_some() {
+            dropped("--second-font");
+        }
+        if let Some(font) = options.font.take_if(|font| font.glyph_count() > 256) {
+            dropped("the second bank of --font");
+            options.font = Some(font.first_bank());
+        }
+    }
+    if !support.ice && !options.blink_safe {
+        dropped("iCE colors, using --blink-safe");
+        options.ice = false;
+        options.blink_safe = true;
+    }
+    if !support.compression && !options.no_compress {
+        dropped("compression");
+        options.no_compress = true;
+    }
+    if let Some(font) = options
+        .font
+        .take_if(|font| !support.allows_height(font.height))
+    {
+        dropped(&format!("the {}-row --font", font.height));
+    }
+    let height = options.mode.font().height;
+    if options.mode != Mode::Auto && !support.allows_height(height) {
+        eprintln!("warning: --compat {name} may not show the {height}-row font of this mode");
     }
-    bytes
 }
 
-fn chunks_to_bytes(chunks: &[Chunk]) -> Vec<u8> {
-    let mut bytes = Vec::new();
-    for chunk in chunks {
-        bytes.push(chunk.codepoint);
-        bytes.push((chunk.bg << 4) + chunk.fg);
+// The format chosen with --format, or else the one the extension implies. The
+// braille and sextant modes only make sense as UTF-8, so they default to it
+// unless writing HTML, SVG, sixels or recordings.
+fn output_format(options: &Options, output: &Path) -> Format {
+    options
+        .format
+        .unwrap_or_else(|| extension_format(options, output))
+}
+
+fn extension_format(options: &Options, output: &Path) -> Format {
+    if html::is_html_path(output) {
+        Format::Html
+    } else if svg::is_svg_path(output) {
+        Format::Svg
+    } else if sixel::is_sixel_path(output) {
+        Format::Sixel
+    } else if escp::is_escp_path(output) {
+        Format::Escp
+    } else if pdf::is_pdf_path(output) {
+        Format::Pdf
+    } else if ps::is_ps_path(output) {
+        Format::Ps
+    } else if cast::is_cast_path(output) {
+        Format::Cast
+    } else if matches!(options.mode, Mode::Braille | Mode::Sextant) {
+        Format::Truecolor
+    } else if ansi::is_ansi_path(output) {
+        Format::Ans
+    } else if text::is_text_path(output) {
+        Format::Text
+    } else if bin::is_bin_path(output) {
+        Format::Bin
+    } else if adf::is_adf_path(output) {
+        Format::Adf
+    } else if idf::is_idf_path(output) {
+        Format::Idf
+    } else if tundra::is_tundra_path(output) {
+        Format::Tundra
+    } else if com::is_com_path(output) {
+        Format::Com
+    } else if let Some(language) = source::language(output) {
+        match language {
+            source::Language::Rust => Format::Rs,
+            source::Language::C => Format::C,
+            source::Language::Asm => Format::Asm,
+        }
+    } else if pcboard::is_pcboard_path(output) {
+        Format::Pcboard
+    } else if avatar::is_avatar_path(output) {
+        Format::Avatar
+    } else if petscii::is_seq_path(output) {
+        Format::Seq
+    } else if atascii::is_atascii_path(output) {
+        Format::Atascii
+    } else if spectrum::is_scr_path(output) {
+        Format::Scr
+    } else {
+        Format::Xbin
     }
-    bytes
 }
 
-fn save_xbin(path: impl AsRef<Path>, image: &DynamicImage, palette: &[RGBA], chunks: &[Chunk]) {
-    let mut file = std::fs::File::create(path).unwrap();
-    let palette_bytes = palette_to_bytes(palette);
-    let font_bytes: Vec<u8> = (0..=255).collect();
-    let chunk_bytes = chunks_to_bytes(chunks);
-    let columns = image.width() / 8;
-    let rows = image.height();
-    file.write_all(b"XBIN\x1a").unwrap();
-    file.write_all((columns as u16).to_le_bytes().as_ref())
-        .unwrap();
-    file.write_all((rows as u16).to_le_bytes().as_ref())
+// ADF and IDF only hold a single bank of an 8x16 font.
+fn require_vga_font(format: &str, font: &font::Font) {
+    if font.height != 16 {
+        exit(Cli::command().error(
+            ErrorKind::InvalidValue,
+            format!(
+                "{format} needs an 8x16 font, not this mode's 8x{}",
+                font.height
+            ),
+        ));
+    }
+    if font.glyph_count() > 256 {
+        eprintln!("warning: {format} can't hold the second bank of the font");
+    }
+}
+
+// Viewers of ANSI, BIN, PCBoard, AVATAR, TundraDraw, HTML and IRC bring their
+// own font and have no second bank. Most bring their own 16 colors too, so
+// such output is encoded with the ansi palette, or mIRC's for IRC, unless
+// another is chosen.
+fn prepare_ansi(options: &mut Options, format: Format) {
+    // Quadrant patterns have block elements of their own in UTF-8.
+    let utf8 = options.codepage == Codepage::Utf8
+        || matches!(
+            format,
+            Format::Utf8ans | Format::Html | Format::Irc | Format::Irc99
+        );
+    let unicode = matches!(options.mode, Mode::Braille | Mode::Sextant)
+        || (options.mode == Mode::Quadrant && utf8);
+    if options.mode != Mode::Auto && !options.mode.uses_text_font() && !unicode {
+        eprintln!(
+            "warning: viewers of this format draw with their own font, not this mode's generated one"
+        );
+    }
+    if fixed_palette(options).is_none() {
+        options.palette = match format {
+            Format::Tundra | Format::Html | Format::Irc99 => None,
+            Format::Irc => Some(palette::Standard::Mirc),
+            _ => Some(palette::Standard::Ansi),
+        };
+    }
+    if options.second_font.take().is_some() {
+        eprintln!("warning: this format can't use --second-font");
+    }
+    if let Some(font) = options.font.take_if(|font| font.glyph_count() > 256) {
+        eprintln!("warning: this format can't use the second bank of --font");
+        options.font = Some(font.first_bank());
+    }
+}
+
+// Draws the input to a PNG if it's an XBIN, whose blinking backgrounds show
+// as they are between blinks, and says if it was.
+fn render_xbin(input: &Path, output: &Path) -> bool {
+    let bytes = gz::read(input).unwrap();
+    if !bytes.starts_with(b"XBIN\x1a") {
+        return false;
+    }
+    let xbin = xbin::parse(&bytes).unwrap_or_else(|error| {
+        exit(Cli::command().error(
+            ErrorKind::InvalidValue,
+            format!("can't read {}: {error}", input.display()),
+        ))
+    });
+    render::render(xbin.columns, &xbin.font, &xbin.palette, &xbin.shown())
+        .save(output)
         .unwrap();
-    file.write_all(b"\x01\x0b").unwrap();
-    file.write_all(&palette_bytes).unwrap();
-    file.write_all(&font_bytes).unwrap();
-    file.write_all(&chunk_bytes).unwrap();
+    true
+}
+
+// Reads a written XBIN back and exits with an error unless it parses to the
+// cells, font and palette it was written from and renders the same.
+fn verify_xbin(
+    path: &Path,
+    columns: usize,
+    font: &Font,
+    features: &xbin::Features,
+    palettes: &Palettes,
+    chunks: &[Chunk],
+) {
+    let fail = |problem: String| -> ! {
+        exit(Cli::command().error(
+            ErrorKind::Io,
+            format!("{} doesn't read back as written: {problem}", path.display()),
+        ))
+    };
+    let bytes = std::fs::read(path).unwrap_or_else(|error| fail(error.to_string()));
+    let written = xbin::parse(&bytes).unwrap_or_else(|error| fail(error));
+    // Viewers draw an XBIN without a palette in the standard one.
+    let palette: Vec<RGBA> = if features.embed_palette {
+        palettes.vga[..16]
+            .iter()
+            .map(|color| RGBA {
+                r: color.r * 4,
+                g: color.g * 4,
+                b: color.b * 4,
+                a: 255,
+            })
+            .collect()
+    } else {
+        palette::ANSI.to_vec()
+    };
+    if written.columns != columns || written.chunks.len() != chunks.len() {
+        fail(format!(
+            "{} x {} cells rather than {columns} x {}",
+            written.columns,
+            written.chunks.len() / written.columns.max(1),
+            chunks.len() / columns
+        ));
+    }
+    if written.chunks != chunks {
+        let differing = written
+            .chunks
+            .iter()
+            .zip(chunks)
+            .filter(|(a, b)| a != b)
+            .count();
+        fail(format!("{differing} cells differ"));
+    }
+    if written.font != *font {
+        fail("the font differs".to_string());
+    }
+    if written.palette != palette {
+        fail("the palette differs".to_string());
+    }
+    let rendering = render::render(columns, &written.font, &written.palette, &written.shown());
+    if rendering != render::render(columns, font, &palette, chunks) {
+        fail("the rendering differs".to_string());
+    }
+}
+
+// Saves the palette of art files as they're drawn, and of images as converting
+// them with `options` generates it.
+fn extract_palette(input: &Path, output: &Path, options: &Options) {
+    let format = palette_file::PaletteFormat::for_path(output).unwrap_or_else(|| {
+        conversion_error(format!(
+            "{} isn't a palette file: .gpl, .pal or .hex",
+            output.display()
+        ))
+    });
+    let bytes = gz::read(input).unwrap();
+    let colors = if is_art(input, &bytes) {
+        read_art(input).palette
+    } else {
+        let source = frames::open(input);
+        let (_, conversion) = match options.mode {
+            Mode::Auto => pick_mode(options, &source, options.columns),
+            mode => (mode, convert(options, mode, &source, options.columns)),
+        };
+        conversion.palettes.rgba[..conversion.palettes.count].to_vec()
+    };
+    palette_file::save_palette(output, format, &colors);
+}
+
+// Lists the cells that differ between two art files, and exits with status 1
+// if any do.
+fn diff_art(a: &Path, b: &Path, png: Option<&Path>) -> ! {
+    let (art_a, art_b) = (read_art(a), read_art(b));
+    let (columns, rows) = diff::size(&art_a, &art_b);
+    let differences = diff::differences(&art_a, &art_b);
+    if let Some(png) = png {
+        diff::image(&art_a, &art_b).save(png).unwrap();
+    }
+    for (path, art) in [(a, &art_a), (b, &art_b)] {
+        if (art.columns, art.chunks.len()) != (columns, columns * rows) {
+            println!(
+                "{} is {} x {} cells of {columns} x {rows}",
+                path.display(),
+                art.columns,
+                art.chunks.len() / art.columns.max(1)
+            );
+        }
+    }
+    println!("{} of {} cells differ", differences.len(), columns * rows);
+    for (column, row) in &differences {
+        println!(
+            "  column {}, row {}: {} -> {}",
+            column + 1,
+            row + 1,
+            diff::describe(&art_a, *column, *row),
+            diff::describe(&art_b, *column, *row)
+        );
+    }
+    std::process::exit(if differences.is_empty() { 0 } else { 1 })
+}
+
+fn join_art(inputs: &[PathBuf], output: &Path, horizontal: bool) {
+    if !has_extension(output, &["xb", "xbin"]) {
+        conversion_error(format!("art joins into an XBIN, not {}", output.display()));
+    }
+    let arts: Vec<(&Path, xbin::Xbin)> = inputs
+        .iter()
+        .map(|input| (input.as_path(), read_art(input)))
+        .collect();
+    let joined = join::join(&arts, horizontal).unwrap_or_else(|error| conversion_error(error));
+    let palette: Vec<RGBA> = joined
+        .palette
+        .iter()
+        .map(|color| RGBA {
+            r: color.r / 4,
+            g: color.g / 4,
+            b: color.b / 4,
+            a: 255,
+        })
+        .collect();
+    xbin::save_xbin(
+        output,
+        joined.columns,
+        &joined.font,
+        &joined.features,
+        &palette,
+        &joined.chunks,
+    );
+}
+
+// Writes the optimized XBIN with the input's SAUCE record, unless it comes
+// out no smaller, when the input is copied as it is.
+fn optimize_xbin(input: &Path, output: &Path) {
+    let bytes = gz::read(input).unwrap();
+    if !bytes.starts_with(b"XBIN\x1a") {
+        conversion_error(format!("{} is not an XBIN", input.display()));
+    }
+    let xbin = xbin::parse(&bytes).unwrap_or_else(|error| {
+        conversion_error(format!("can't read {}: {error}", input.display()))
+    });
+    std::fs::write(output, optimize::optimized(&xbin)).unwrap();
+    if let Some(record) = sauce::parse(&bytes) {
+        sauce::append(output, &record);
+    }
+    let size = std::fs::metadata(output).unwrap().len() as usize;
+    if size < bytes.len() {
+        println!(
+            "{}: {} bytes, {size} optimized, {} saved",
+            output.display(),
+            bytes.len(),
+            bytes.len() - size
+        );
+    } else {
+        std::fs::write(output, &bytes).unwrap();
+        println!(
+            "{}: {} bytes, already as small as optimizing makes it",
+            output.display(),
+            bytes.len()
+        );
+    }
+}
+
+// The bundled font a SAUCE font name asks for, in any codepage.
+fn sauce_font(name: Option<&str>) -> Font {
+    match name.unwrap_or_default() {
+        name if name.starts_with("IBM VGA50") || name.starts_with("IBM EGA43") => {
+            Font::ibm_vga_8x8()
+        }
+        name if name.starts_with("IBM EGA") => Font::ibm_vga_8x14(),
+        _ => Font::ibm_vga_8x16(),
+    }
+}
+
+// The gzipped output being written, compressed into place by
+// `finish_output` however main ends.
+static PENDING: Mutex<Option<gz::Pending>> = Mutex::new(None);
+
+// Compresses any gzipped output into place, exiting with an error if that
+// can't be written.
+fn finish_output() {
+    let pending = PENDING.lock().unwrap().take();
+    if let Some(pending) = pending {
+        let output = pending.output.clone();
+        if let Err(error) = pending.finish() {
+            eprintln!("error: can't write {}: {error}", output.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+// Exits with the error once any output written so far is in place.
+fn exit(error: clap::Error) -> ! {
+    finish_output();
+    error.exit()
+}
+
+fn conversion_error(message: String) -> ! {
+    exit(Cli::command().error(ErrorKind::InvalidValue, message))
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension().is_some_and(|extension| {
+        extensions
+            .iter()
+            .any(|wanted| extension.eq_ignore_ascii_case(wanted))
+    })
+}
+
+// Converts XBIN art to ANSI, or ANSI art to XBIN, cell for cell.
+fn convert_art(input: &Path, output: &Path, truecolor: bool) {
+    let bytes = gz::read(input).unwrap();
+    if bytes.starts_with(b"XBIN\x1a") {
+        if !has_extension(output, &["ans"]) {
+            conversion_error(format!("XBINs convert to ANSI, not {}", output.display()));
+        }
+        xbin_to_ansi(input, &bytes, output, truecolor);
+    } else if ansi::is_ansi_path(input) {
+        if !has_extension(output, &["xb", "xbin"]) {
+            conversion_error(format!(
+                "ANSI art converts to XBIN, not {}",
+                output.display()
+            ));
+        }
+        if truecolor {
+            eprintln!("warning: --truecolor only applies to converting XBINs");
+        }
+        ansi_to_xbin(input, &bytes, output);
+    } else {
+        conversion_error(format!(
+            "{} is neither an XBIN nor ANSI art (.ans)",
+            input.display()
+        ));
+    }
+}
+
+// The cells of ANSI art at the width, font and iCE colors its SAUCE record
+// gives, in the standard palette.
+fn ansi_art(input: &Path, bytes: &[u8]) -> xbin::Xbin {
+    let record = sauce::parse(bytes).filter(|record| record.data_type == sauce::CHARACTER);
+    let columns = record
+        .as_ref()
+        .map(|record| record.width as usize)
+        .filter(|width| *width > 0)
+        .unwrap_or(80);
+    let non_blink = record
+        .as_ref()
+        .is_some_and(|record| record.flags & 0x01 != 0);
+    let font = sauce_font(
+        record
+            .as_ref()
+            .and_then(|record| record.font_name.as_deref()),
+    );
+    let chunks = ansi::parse(bytes, columns);
+    if chunks.is_empty() {
+        conversion_error(format!("{} draws nothing", input.display()));
+    }
+    xbin::Xbin {
+        columns,
+        font,
+        palette: palette::ANSI.to_vec(),
+        cell_bytes: chunks.len() * 2,
+        chunks,
+        features: xbin::Features {
+            embed_palette: false,
+            embed_font: false,
+            compress: false,
+            non_blink,
+        },
+    }
+}
+
+// The cells of a BIN at the width, font and iCE colors its SAUCE record
+// gives, or 160 columns without one, in the standard palette.
+fn bin_art(input: &Path, bytes: &[u8]) -> xbin::Xbin {
+    let record = sauce::parse(bytes).filter(|record| record.data_type == sauce::BINARY_TEXT);
+    let columns = record
+        .as_ref()
+        .map(|record| record.file_type as usize * 2)
+        .filter(|width| *width > 0)
+        .unwrap_or(160);
+    let non_blink = record
+        .as_ref()
+        .is_some_and(|record| record.flags & 0x01 != 0);
+    let font = sauce_font(
+        record
+            .as_ref()
+            .and_then(|record| record.font_name.as_deref()),
+    );
+    let chunks = bin::parse(sauce::strip(bytes), columns);
+    if chunks.is_empty() {
+        conversion_error(format!("{} draws nothing", input.display()));
+    }
+    xbin::Xbin {
+        columns,
+        font,
+        palette: palette::ANSI.to_vec(),
+        cell_bytes: chunks.len() * 2,
+        chunks,
+        features: xbin::Features {
+            embed_palette: false,
+            embed_font: false,
+            compress: false,
+            non_blink,
+        },
+    }
+}
+
+// Whether the input is art: an XBIN, ANSI art or a BIN.
+fn is_art(input: &Path, bytes: &[u8]) -> bool {
+    bytes.starts_with(b"XBIN\x1a") || ansi::is_ansi_path(input) || bin::is_bin_path(input)
+}
+
+// The cells of an XBIN, of ANSI art or of a BIN.
+fn read_art(input: &Path) -> xbin::Xbin {
+    let bytes = gz::read(input).unwrap();
+    if bytes.starts_with(b"XBIN\x1a") {
+        xbin::parse(&bytes).unwrap_or_else(|error| {
+            conversion_error(format!("can't read {}: {error}", input.display()))
+        })
+    } else if ansi::is_ansi_path(input) {
+        ansi_art(input, &bytes)
+    } else if bin::is_bin_path(input) {
+        bin_art(input, &bytes)
+    } else {
+        conversion_error(format!(
+            "{} is neither an XBIN, ANSI art (.ans) nor a BIN",
+            input.display()
+        ))
+    }
+}
+
+// The input as an image: art drawn as viewers show it between blinks, or an
+// image file opened as it is.
+fn open_source(input: &Path) -> DynamicImage {
+    if is_art(input, &gz::read(input).unwrap()) {
+        let art = read_art(input);
+        let drawn = render::render(art.columns, &art.font, &art.palette, &art.shown());
+        DynamicImage::ImageRgb8(drawn)
+    } else {
+        frames::open(input)
+    }
+}
+
+// The frames of the input, as `frames::source_frames` gives them, with art
+// as its one drawing.
+fn source_frames(input: &Path) -> Vec<(Duration, DynamicImage)> {
+    if is_art(input, &gz::read(input).unwrap()) {
+        vec![(Duration::ZERO, open_source(input))]
+    } else {
+        frames::source_frames(input)
+    }
+}
+
+// Converts ANSI art to an XBIN of the same cells, at the width, font and
+// iCE colors its SAUCE record gives, which the XBIN keeps.
+fn ansi_to_xbin(input: &Path, bytes: &[u8], output: &Path) {
+    let art = ansi_art(input, bytes);
+    let features = xbin::Features {
+        embed_palette: true,
+        embed_font: true,
+        compress: true,
+        ..art.features
+    };
+    let palette = Palettes::new(&palette::ANSI).vga;
+    xbin::save_xbin(
+        output,
+        art.columns,
+        &art.font,
+        &features,
+        &palette,
+        &art.chunks,
+    );
+    let record = sauce::parse(bytes).filter(|record| record.data_type == sauce::CHARACTER);
+    if let Some(record) = record {
+        let rows = art.chunks.len() / art.columns;
+        let record = sauce::Sauce {
+            data_type: sauce::XBIN,
+            file_type: 0,
+            width: art.columns.min(tile::MAX_CELLS) as u16,
+            height: rows.min(tile::MAX_CELLS) as u16,
+            font_name: None,
+            flags: 0,
+            ..record
+        };
+        sauce::append(output, &record);
+    }
+}
+
+// Converts an XBIN to ANSI of the same cells, their colors the nearest of the
+// 16 standard ones, or with `truecolor` to UTF-8 ANSI in the XBIN's own. The
+// XBIN's SAUCE fields carry over to the CP437 ANSI's record.
+fn xbin_to_ansi(input: &Path, bytes: &[u8], output: &Path, truecolor: bool) {
+    let xbin = xbin::parse(bytes).unwrap_or_else(|error| {
+        conversion_error(format!("can't read {}: {error}", input.display()))
+    });
+    let non_blink = xbin.features.non_blink;
+    // The bank bit of a 512-character font's foregrounds goes with the bank.
+    let mut font = xbin.font.clone();
+    let mut chunks = xbin.chunks.clone();
+    if font.glyph_count() > 256 {
+        eprintln!(
+            "warning: ANSI has one bank of 256 characters, so the second's show as the first's"
+        );
+        font = font.first_bank();
+        for chunk in &mut chunks {
+            chunk.fg &= 0x07;
+        }
+    }
+    if ibm_font_name(&font).is_none() && !truecolor {
+        eprintln!("warning: ANSI viewers draw with their own font, not the XBIN's");
+    }
+    if truecolor {
+        // Blinking backgrounds show as they are between blinks.
+        if !non_blink {
+            for chunk in &mut chunks {
+                chunk.bg &= 0x07;
+            }
+        }
+        let to_char = |codepoint: u8| CP437[codepoint as usize];
+        ansi::save_utf8_ansi(
+            output,
+            xbin.columns,
+            &xbin.palette,
+            &chunks,
+            to_char,
+            Colors::Truecolor,
+        );
+        return;
+    }
+    // Without iCE colors, the background's high bit blinks rather than
+    // picking a color.
+    let background_colors = if non_blink { 16 } else { 8 };
+    let foregrounds: Vec<u8> = xbin
+        .palette
+        .iter()
+        .map(|color| palette::nearest_ansi(color, 16))
+        .collect();
+    let backgrounds: Vec<u8> = xbin
+        .palette
+        .iter()
+        .map(|color| palette::nearest_ansi(color, background_colors))
+        .collect();
+    for chunk in &mut chunks {
+        chunk.fg = foregrounds[chunk.fg as usize];
+        chunk.bg = if non_blink {
+            backgrounds[chunk.bg as usize]
+        } else {
+            backgrounds[chunk.bg as usize & 0x07] | chunk.bg & 0x08
+        };
+    }
+    ansi::save_ansi(output, xbin.columns, &font, &chunks);
+    let rows = chunks.len() / xbin.columns.max(1);
+    let ice = non_blink && chunks.iter().any(|chunk| chunk.bg >= 8);
+    let kept = sauce::parse(bytes);
+    let record = sauce::Sauce {
+        title: kept
+            .as_ref()
+            .map(|record| record.title.clone())
+            .unwrap_or_default(),
+        author: kept
+            .as_ref()
+            .map(|record| record.author.clone())
+            .unwrap_or_default(),
+        group: kept
+            .as_ref()
+            .map(|record| record.group.clone())
+            .unwrap_or_default(),
+        date: kept
+            .as_ref()
+            .map_or_else(sauce::today, |record| record.date.clone()),
+        data_type: sauce::CHARACTER,
+        file_type: sauce::ANSI,
+        width: xbin.columns.min(tile::MAX_CELLS) as u16,
+        height: rows.min(tile::MAX_CELLS) as u16,
+        font_name: ibm_font_name(&font).map(str::to_string),
+        comments: kept.map(|record| record.comments).unwrap_or_default(),
+        flags: text_flags(Target::Pc, ice),
+    };
+    sauce::append(output, &record);
 }
 
 fn main() {
-    let args = Args::parse();
-    let image = image::open(args.input).unwrap();
-    let (palettes, indexes) = quantize_image_16(&image);
-    let chunks = break_into_chunks(&palettes, indexes);
-    save_xbin(args.output, &image, &palettes.vga, &chunks);
+    let mut cli = Cli::parse();
+    if let Some(Command::CompareModes {
+        input,
+        output,
+        options,
+    }) = &mut cli.command
+    {
+        apply_compat(options);
+        compare_modes(input, output, options);
+        return;
+    }
+    if let Some(Command::Convert {
+        input,
+        output,
+        truecolor,
+    }) = &cli.command
+    {
+        convert_art(input, output, *truecolor);
+        return;
+    }
+    if let Some(Command::Info { input, json }) = &cli.command {
+        let info = info::Info::read(input);
+        if *json {
+            print!("{}", info::json(&info));
+        } else {
+            print!("{}", info::text(&info));
+        }
+        return;
+    }
+    if let Some(Command::ExtractPalette {
+        input,
+        output,
+        options,
+    }) = &cli.command
+    {
+        extract_palette(input, output, options);
+        return;
+    }
+    if let Some(Command::Diff { a, b, png }) = &cli.command {
+        diff_art(a, b, png.as_deref());
+    }
+    if let Some(Command::Join {
+        inputs,
+        output,
+        horizontal,
+    }) = &cli.command
+    {
+        join_art(inputs, output, *horizontal);
+        return;
+    }
+    if let Some(Command::Optimize { input, output }) = &cli.command {
+        optimize_xbin(input, output);
+        return;
+    }
+    // Rendering converts as usual, taking the format from --format alone, and
+    // stops after the preview.
+    let mut footer = None;
+    let (input, output) = match cli.command.take() {
+        Some(Command::Render {
+            input,
+            output,
+            options,
+        }) => {
+            if render_xbin(&input, &output) {
+                return;
+            }
+            cli.options = options;
+            cli.options.preview = Some(output);
+            (input, None)
+        }
+        Some(Command::Diz {
+            input,
+            output,
+            footer: lines,
+            options,
+        }) => {
+            cli.options = options;
+            // Pixel mode, the default, draws nothing a description can show.
+            if cli.options.mode == Mode::Pixel {
+                cli.options.mode = Mode::Ascii;
+            }
+            cli.options.no_sauce = true;
+            footer = Some(lines);
+            (input, Some(output))
+        }
+        Some(Command::Slideshow {
+            mut inputs,
+            output,
+            duration,
+            transition,
+            transition_duration,
+            options,
+        }) => {
+            cli.options = options;
+            let input = inputs.remove(0);
+            cli.options.slideshow = Some(Slideshow {
+                slides: inputs,
+                duration: Duration::from_secs_f64(duration),
+                transition,
+                transition_duration: Duration::from_secs_f64(transition_duration),
+            });
+            (input, Some(output))
+        }
+        _ => (cli.input.unwrap(), cli.output),
+    };
+    let output = match output {
+        Some(output) if output.as_os_str() == "-" => {
+            if !cli.options.stream {
+                exit(Cli::command().error(
+                    ErrorKind::ValueValidation,
+                    "an OUTPUT of - plays to stdout, which needs --stream",
+                ));
+            }
+            None
+        }
+        output => output,
+    };
+    // Checkpoints are named for the real output, there being no knowing the
+    // temporary file a gzipped one would resume with.
+    let checkpoint = output
+        .as_deref()
+        .or(cli.options.preview.as_deref())
+        .map(Checkpoint::for_output);
+    if let Some(checkpoint) = &checkpoint {
+        if !cli.options.resume {
+            checkpoint.clear();
+        } else {
+            match checkpoint.resumable() {
+                Ok(true) => {}
+                Ok(false) => eprintln!("warning: no checkpoint to resume, starting over"),
+                Err(error) => exit(Cli::command().error(ErrorKind::ArgumentConflict, error)),
+            }
+        }
+    }
+    cli.options.checkpoint = checkpoint.clone();
+    // Gzipped outputs are written as their inner extension asks, then
+    // compressed into place when main returns or exits.
+    let pending = output.as_deref().and_then(gz::Pending::for_output);
+    let gzipped = pending.is_some();
+    let output = pending
+        .as_ref()
+        .map(|pending| pending.temporary.clone())
+        .or(output);
+    *PENDING.lock().unwrap() = pending;
+    if gzipped && cli.options.tile.is_some() {
+        exit(Cli::command().error(
+            ErrorKind::ArgumentConflict,
+            "--tile can't split a gzipped output",
+        ));
+    }
+    apply_compat(&mut cli.options);
+    let format = match &output {
+        Some(output) => output_format(&cli.options, output),
+        None => cli.options.format.unwrap_or(Format::Xbin),
+    };
+    // Emitted files are named after OUTPUT and take their formats from their
+    // extensions alone.
+    if !cli.options.emit.is_empty() && (output.is_none() || gzipped) {
+        exit(Cli::command().error(
+            ErrorKind::ArgumentConflict,
+            "--emit needs an uncompressed OUTPUT to name its files after",
+        ));
+    }
+    let (pngs, extensions): (Vec<&String>, Vec<&String>) = cli
+        .options
+        .emit
+        .iter()
+        .partition(|extension| extension.eq_ignore_ascii_case("png"));
+    let pngs: Vec<PathBuf> = pngs
+        .into_iter()
+        .map(|extension| output.as_ref().unwrap().with_extension(extension))
+        .collect();
+    let mut outputs: Vec<(PathBuf, Format)> = output
+        .iter()
+        .map(|output| (output.clone(), format))
+        .collect();
+    for extension in extensions {
+        let path = output.as_ref().unwrap().with_extension(extension);
+        let format = extension_format(&cli.options, &path);
+        outputs.push((path, format));
+    }
+    if cli.options.slideshow.is_some() && !outputs.iter().any(|(_, format)| *format == Format::Ans)
+    {
+        eprintln!("warning: only .ans outputs show a slideshow, others get its first slide");
+    }
+    if cli.options.no_quantize && fixed_palette(&cli.options).is_none() {
+        exit(Cli::command().error(
+            ErrorKind::MissingRequiredArgument,
+            "--no-quantize maps to a fixed palette: give --palette-file or --palette",
+        ));
+    }
+    if cli.options.verify && !outputs.iter().any(|(_, format)| *format == Format::Xbin) {
+        eprintln!("warning: --verify only checks XBIN outputs");
+    }
+    // The first format needing ANSI's constraints sets them for all.
+    let ansi_format = outputs.iter().map(|(_, format)| *format).find(|format| {
+        matches!(
+            format,
+            Format::Ans
+                | Format::Utf8ans
+                | Format::Bin
+                | Format::Tundra
+                | Format::Pcboard
+                | Format::Avatar
+                | Format::Html
+                | Format::Irc
+                | Format::Irc99
+        )
+    });
+    if let Some(format) = ansi_format {
+        prepare_ansi(&mut cli.options, format);
+    }
+    let options = &cli.options;
+    if options.comments.len() > sauce::MAX_COMMENTS {
+        exit(Cli::command().error(
+            ErrorKind::TooManyValues,
+            format!(
+                "at most {} --comment lines fit in SAUCE",
+                sauce::MAX_COMMENTS
+            ),
+        ));
+    }
+    let source = open_source(&input);
+    let fitted = match &footer {
+        Some(footer) => Some(diz_columns(options, &source, footer.len())),
+        None => options.columns,
+    };
+    let (mode, conversion) = match options.mode {
+        Mode::Auto => pick_mode(options, &source, fitted),
+        mode => (mode, convert(options, mode, &source, fitted)),
+    };
+    let Conversion {
+        image,
+        columns,
+        mut palettes,
+        mut chunks,
+    } = conversion;
+    if let Some(footer) = &footer {
+        let lightness: Vec<f32> = palettes.rgba[..palettes.count]
+            .iter()
+            .map(|color| chunks::rgba_to_oklab(color).l)
+            .collect();
+        let by_lightness = |a: &usize, b: &usize| lightness[*a].total_cmp(&lightness[*b]);
+        let fg = (0..palettes.count).max_by(by_lightness).unwrap() as u8;
+        let bg = (0..palettes.count).min_by(by_lightness).unwrap() as u8;
+        chunks.extend(diz::footer_cells(footer, columns, fg, bg));
+    }
+    if options.font.is_some() && !mode.uses_text_font() {
+        eprintln!("warning: --font only applies to modes drawn with a text font");
+    }
+    if options.target == Target::Amiga && options.font.is_none() && mode.uses_text_font() {
+        eprintln!("warning: Topaz isn't bundled, pass a Topaz PSF with --font for Amiga output");
+    }
+    if options.target == Target::C64 && options.font.is_none() && mode.uses_text_font() {
+        eprintln!("warning: the C64 character ROM isn't bundled, pass a dump of it with --font");
+    }
+    if options.target == Target::Atari && options.font.is_none() && mode.uses_text_font() {
+        eprintln!("warning: the Atari character set isn't bundled, pass it with --font");
+    }
+    if options.target == Target::C64 && mode != Mode::Glyph {
+        eprintln!("warning: only glyph mode keeps to the C64's single background color");
+    }
+    if fixed_palette(options).is_some() && options.cb_safe.is_some() {
+        eprintln!("warning: --cb-safe can't change a fixed palette");
+    }
+    if options.second_font.is_some() && mode != Mode::Glyph {
+        eprintln!("warning: --second-font only applies to glyph mode");
+    }
+    let font = mode_font(options, mode);
+    if options.blink_safe && font.glyph_count() > 256 {
+        eprintln!("warning: --blink-safe can't reorder the palette of a 512-character font");
+    } else if options.blink_safe {
+        restrict_backgrounds(&mut palettes, &mut chunks, &font, options.charset.as_ref());
+    }
+    if mode == Mode::Pixel && (options.error_map.is_some() || options.dump_cells.is_some()) {
+        let errors = chunk_errors(&source_pixels(&image), &palettes, &chunks);
+        if let Some(path) = &options.error_map {
+            error_map::save_error_map(path, image.width(), image.height(), &errors);
+        }
+        if let Some(path) = &options.dump_cells {
+            dump::dump_cells(path, columns, &chunks, &errors);
+        }
+    }
+    if options.palette_usage {
+        report_palette_usage(&palettes, &chunks, &font, options.min_usage);
+    }
+    let mut ssim = None;
+    if options.fidelity || options.min_ssim.is_some() {
+        let rendering = render::render(columns, &font, &palettes.rgba, &chunks);
+        let reference = source
+            .resize_exact(rendering.width(), rendering.height(), FilterType::Lanczos3)
+            .to_rgb8();
+        let score = metrics::ssim(&reference, &rendering);
+        eprintln!("Fidelity:");
+        eprintln!("  PSNR  {:.2} dB", metrics::psnr(&reference, &rendering));
+        eprintln!("  SSIM  {score:.4}");
+        ssim = Some(score);
+    }
+    if let Some(path) = &options.compare {
+        save_comparison(path, options, &source, &font, columns, &palettes, &chunks);
+    }
+    if let Some(path) = options.preview.as_deref() {
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"))
+        {
+            save_gif_preview(&input, path, options, mode);
+        } else {
+            render::render(columns, &font, &palettes.rgba, &chunks)
+                .save(path)
+                .unwrap();
+        }
+    }
+    for path in &pngs {
+        render::render(columns, &font, &palettes.rgba, &chunks)
+            .save(path)
+            .unwrap();
+    }
+    if options.show_inline {
+        inline::show(&render::render(columns, &font, &palettes.rgba, &chunks));
+    }
+    if options.show {
+        show(options, mode, &source);
+    }
+    if options.stream {
+        stream(&input, options, mode, columns, &palettes, &chunks);
+    }
+    for (output, format) in outputs {
+        // UTF-8 art has no SAUCE data type to describe it.
+        let to_char = mode_chars(mode);
+        let unicode = matches!(mode, Mode::Braille | Mode::Sextant);
+        let utf8 = options.codepage == Codepage::Utf8;
+        let format = match format {
+            Format::Ans if utf8 => Format::Utf8ans,
+            Format::Bin | Format::Pcboard | Format::Avatar if utf8 => exit(Cli::command().error(
+                ErrorKind::ArgumentConflict,
+                format!("--codepage utf8 can't encode {format:?}, which holds a byte per cell"),
+            )),
+            format => format,
+        };
+        let format = match format {
+            Format::Text if utf8 => format,
+            Format::Utf8ans
+            | Format::Xterm256
+            | Format::Truecolor
+            | Format::Html
+            | Format::Svg
+            | Format::Sixel
+            | Format::Cast
+            | Format::Irc
+            | Format::Irc99 => format,
+            _ if unicode => {
+                eprintln!("warning: this mode's characters only exist in UTF-8, writing truecolor");
+                Format::Truecolor
+            }
+            format => format,
+        };
+        if options.scroll.is_some() && !matches!(format, Format::Ans | Format::Cast) {
+            eprintln!("warning: --scroll only applies to ANSI and asciinema output");
+        }
+        // Text formats without a font of their own are written in the
+        // codepage their viewers draw with.
+        let font_name = sauce_font_name(options, &font);
+        let recoding = options.codepage.recoding(&font);
+        let (font, chunks) = match format {
+            Format::Ans | Format::Text | Format::Bin | Format::Pcboard | Format::Avatar => {
+                let (cells, different) = recoding.cells(&chunks);
+                if different > 0 {
+                    eprintln!(
+                        "warning: {different} cells use characters CP{} lacks, writing the closest it has",
+                        options.codepage.number().unwrap()
+                    );
+                }
+                (recoding.font.clone(), cells)
+            }
+            _ => (font.clone(), chunks.clone()),
+        };
+        if format == Format::Svg {
+            let rendering = render::render(columns, &font, &palettes.rgba, &chunks);
+            svg::save_svg(&output, &rendering, options.target.pixel_height());
+            continue;
+        }
+        if format == Format::Sixel {
+            let rendering = render::render(columns, &font, &palettes.rgba, &chunks);
+            let pixel_height = options.target.pixel_height();
+            sixel::save_sixel(&output, &rendering, &palettes.rgba, pixel_height);
+            continue;
+        }
+        if format == Format::Escp {
+            let rendering = render::render(columns, &font, &palettes.rgba, &chunks);
+            if rendering.width() as usize > escp::MAX_DOTS {
+                eprintln!(
+                    "warning: the printer's line is {} dots wide, cutting off the rest",
+                    escp::MAX_DOTS
+                );
+            }
+            escp::save_escp(&output, &rendering, options.target.pixel_height());
+            continue;
+        }
+        if let Format::Pdf | Format::Ps = format {
+            let points = (72.0 / options.dpi) as f32;
+            let page = pdf::Page {
+                rows: chunks.len() / columns,
+                pixel: (points, points * options.target.pixel_height()),
+                font_height: font.height as usize,
+            };
+            let palette = &palettes.rgba;
+            if format == Format::Pdf {
+                pdf::save_pdf(&output, columns, &font, palette, &chunks, &page);
+            } else {
+                ps::save_ps(&output, columns, &font, palette, &chunks, &page);
+            }
+            continue;
+        }
+        if format == Format::Html {
+            let palette = &palettes.rgba;
+            html::save_html(&output, columns, &font, palette, &chunks, to_char);
+            continue;
+        }
+        if let Format::Irc | Format::Irc99 = format {
+            let count = if format == Format::Irc { 16 } else { 99 };
+            let codes: Vec<u8> = palettes
+                .rgba
+                .iter()
+                .map(|color| palette::nearest_mirc(color, count))
+                .collect();
+            irc::save_irc(&output, columns, &codes, &chunks, to_char);
+            continue;
+        }
+        if format == Format::Cast {
+            save_cast(&input, &output, options, mode, columns, &palettes, &chunks);
+            continue;
+        }
+        if let Format::Utf8ans | Format::Xterm256 | Format::Truecolor = format {
+            let colors = match format {
+                Format::Utf8ans => Colors::Ansi16,
+                Format::Xterm256 => Colors::Xterm256,
+                _ => Colors::Truecolor,
+            };
+            let palette = &palettes.rgba;
+            ansi::save_utf8_ansi(&output, columns, palette, &chunks, to_char, colors);
+            continue;
+        }
+        let rows = chunks.len() / columns;
+        // Provenance from an input's own SAUCE record carries over unless the
+        // command line overrides it.
+        let input_sauce = gz::read(&input).ok().and_then(|data| sauce::parse(&data));
+        let inherited = |field: fn(&sauce::Sauce) -> &String| {
+            input_sauce.as_ref().map(|sauce| field(sauce).clone())
+        };
+        let title = options.title.clone().or(inherited(|sauce| &sauce.title));
+        let author = options.author.clone().or(inherited(|sauce| &sauce.author));
+        let group = options.group.clone().or(inherited(|sauce| &sauce.group));
+        let comments = if options.comments.is_empty() {
+            input_sauce
+                .as_ref()
+                .map_or_else(Vec::new, |sauce| sauce.comments.clone())
+        } else {
+            options.comments.clone()
+        };
+        let fields_set = title.is_some()
+            || author.is_some()
+            || group.is_some()
+            || options.date.is_some()
+            || !comments.is_empty();
+        let sauce =
+            |(width, height), data_type, file_type, font_name: Option<&str>, flags| sauce::Sauce {
+                title: title.clone().unwrap_or_default(),
+                author: author.clone().unwrap_or_default(),
+                group: group.clone().unwrap_or_default(),
+                date: options.date.clone().unwrap_or_else(sauce::today),
+                data_type,
+                file_type,
+                width,
+                height,
+                font_name: font_name.map(str::to_string),
+                comments: comments.clone(),
+                flags,
+            };
+        if format == Format::Ans {
+            // Animated sources and slideshows become ANSImations, their
+            // frames paced to --fps.
+            let frames = source_frames(&input);
+            let animation: Vec<(Duration, Vec<Chunk>)> = if let Some(show) = &options.slideshow {
+                slideshow_frames(&input, show, options, mode, columns)
+            } else if frames.len() > 1 && options.scroll.is_none() {
+                convert_frames(frames, options, mode)
+                    .into_iter()
+                    .map(|(duration, conversion)| (duration, conversion.chunks))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let durations: Vec<Duration> =
+                animation.iter().map(|(duration, _)| *duration).collect();
+            let ticks: Vec<Vec<Chunk>> =
+                frames::at_rate(&durations, options.fps.unwrap_or(DEFAULT_FPS))
+                    .into_iter()
+                    .map(|index| recoding.cells(&animation[index].1).0)
+                    .collect();
+            let plays = if ticks.is_empty() {
+                1
+            } else {
+                file_plays(options, "ANSImations")
+            };
+            let ticks: Vec<&[Chunk]> = (0..plays)
+                .flat_map(|_| ticks.iter().map(Vec::as_slice))
+                .collect();
+            let file_type = if let Some(direction) = options.scroll {
+                scroll::save_ansi(&output, columns, &font, &chunks, direction);
+                sauce::ANSIMATION
+            } else if ticks.is_empty() {
+                ansi::save_ansi(&output, columns, &font, &chunks);
+                sauce::ANSI
+            } else {
+                ansi::save_ansimation(&output, columns, &font, &ticks);
+                sauce::ANSIMATION
+            };
+            if !options.no_sauce {
+                let ice = std::iter::once(chunks.as_slice())
+                    .chain(ticks.iter().copied())
+                    .flatten()
+                    .any(|chunk| chunk.bg >= 8);
+                let flags = text_flags(options.target, ice);
+                // Slides after the first can be taller.
+                let rows = ticks
+                    .iter()
+                    .map(|tick| tick.len() / columns)
+                    .fold(rows, usize::max);
+                let size = (
+                    columns.min(tile::MAX_CELLS) as u16,
+                    rows.min(tile::MAX_CELLS) as u16,
+                );
+                let record = sauce(
+                    size,
+                    sauce::CHARACTER,
+                    file_type,
+                    font_name.as_deref(),
+                    flags,
+                );
+                sauce::append(&output, &record);
+            }
+        } else if format == Format::Pcboard {
+            pcboard::save_pcboard(&output, columns, &font, &chunks);
+            if !options.no_sauce {
+                let size = (
+                    columns.min(tile::MAX_CELLS) as u16,
+                    rows.min(tile::MAX_CELLS) as u16,
+                );
+                let record = sauce(size, sauce::CHARACTER, sauce::PCBOARD, None, 0);
+                sauce::append(&output, &record);
+            }
+        } else if format == Format::Avatar {
+            avatar::save_avatar(&output, columns, &font, &chunks);
+            if !options.no_sauce {
+                let size = (
+                    columns.min(tile::MAX_CELLS) as u16,
+                    rows.min(tile::MAX_CELLS) as u16,
+                );
+                let record = sauce(size, sauce::CHARACTER, sauce::AVATAR, None, 0);
+                sauce::append(&output, &record);
+            }
+        } else if format == Format::Seq {
+            if options.target != Target::C64 {
+                eprintln!("warning: PETSCII streams are for --target c64");
+            }
+            if !mode.uses_text_font() {
+                eprintln!(
+                    "warning: these cells use CP437 codes, which the C64 draws as other glyphs"
+                );
+            }
+            if columns > petscii::COLUMNS {
+                eprintln!(
+                    "warning: the C64's screen is {} columns wide",
+                    petscii::COLUMNS
+                );
+            }
+            let background = chunks.first().map_or(0, |chunk| chunk.bg);
+            eprintln!("warning: PETSCII can't set the background, show it on color {background}");
+            petscii::save_seq(&output, columns, &chunks);
+        } else if format == Format::Atascii {
+            if options.target != Target::Atari {
+                eprintln!("warning: ATASCII streams are for --target atari");
+            }
+            if !mode.uses_text_font() {
+                eprintln!(
+                    "warning: these cells use CP437 codes, which the Atari draws as other glyphs"
+                );
+            }
+            if palettes.count > 2 {
+                eprintln!("warning: ATASCII shows palette entries 0 and 1 only");
+            }
+            if columns > atascii::COLUMNS {
+                eprintln!(
+                    "warning: the Atari's screen is {} columns wide",
+                    atascii::COLUMNS
+                );
+            }
+            atascii::save_atascii(&output, columns, &font, &chunks);
+        } else if format == Format::Scr {
+            if mode != Mode::Pixel {
+                exit(Cli::command().error(
+                    ErrorKind::ArgumentConflict,
+                    "Spectrum screens are written from --mode pixel",
+                ));
+            }
+            if options.target != Target::Spectrum {
+                eprintln!("warning: Spectrum screens are for --target spectrum");
+            }
+            if columns > spectrum::COLUMNS || rows > spectrum::ROWS {
+                eprintln!(
+                    "warning: the Spectrum's screen is {} by {} pixels, cutting off the rest",
+                    spectrum::COLUMNS * 8,
+                    spectrum::ROWS
+                );
+            }
+            spectrum::save_scr(&output, columns, &palettes.rgba, &chunks);
+        } else if format == Format::Bin {
+            let width = bin::save_bin(&output, columns, &chunks);
+            if width > bin::MAX_COLUMNS {
+                eprintln!(
+                    "warning: SAUCE can't record a BIN width over {}",
+                    bin::MAX_COLUMNS
+                );
+            }
+            if !options.no_sauce {
+                let flags = text_flags(options.target, chunks.iter().any(|chunk| chunk.bg >= 8));
+                let file_type = (width / 2).min(255) as u8;
+                let record = sauce(
+                    (0, 0),
+                    sauce::BINARY_TEXT,
+                    file_type,
+                    font_name.as_deref(),
+                    flags,
+                );
+                sauce::append(&output, &record);
+            }
+        } else if format == Format::Adf {
+            require_vga_font("ADF", &font);
+            if columns > adf::COLUMNS {
+                eprintln!(
+                    "warning: ADF is {} columns wide, cutting off the rest",
+                    adf::COLUMNS
+                );
+            }
+            // ADF has no SAUCE data type of its own.
+            adf::save_adf(&output, columns, &font, &palettes.vga, &chunks);
+        } else if format == Format::Idf {
+            require_vga_font("IDF", &font);
+            // Nor does IDF.
+            idf::save_idf(&output, columns, &font, &palettes.vga, &chunks);
+        } else if format == Format::Com {
+            let size = com::size(&font, chunks.len());
+            if size > com::MAX_SIZE {
+                exit(Cli::command().error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "the program would be {size} bytes, over the {} a .COM file can hold",
+                        com::MAX_SIZE
+                    ),
+                ));
+            }
+            if columns > com::COLUMNS {
+                eprintln!(
+                    "warning: text mode is {} columns wide, cutting off the rest",
+                    com::COLUMNS
+                );
+            }
+            if font.glyph_count() > 256 && chunks.iter().any(|chunk| chunk.bg >= 8) {
+                eprintln!("warning: with 512 glyphs, DOS shows bright backgrounds dark");
+            }
+            com::save_com(&output, columns, &font, &palettes.vga, &chunks);
+        } else if format == Format::Tundra {
+            if columns > tundra::COLUMNS {
+                eprintln!(
+                    "warning: TundraDraw is {} columns wide, cutting off the rest",
+                    tundra::COLUMNS
+                );
+            }
+            tundra::save_tundra(&output, columns, &palettes.rgba, &chunks);
+            if !options.no_sauce {
+                let size = (tundra::COLUMNS as u16, rows.min(tile::MAX_CELLS) as u16);
+                let record = sauce(size, sauce::CHARACTER, sauce::TUNDRA, None, 0);
+                sauce::append(&output, &record);
+            }
+        } else if format == Format::Text {
+            let line_ending = options.target.line_ending();
+            if utf8 {
+                text::save_utf8_text(&output, columns, &chunks, to_char, line_ending);
+            } else {
+                text::save_text(&output, columns, &chunks, line_ending);
+            }
+            if fields_set && !options.no_sauce {
+                let flags = text_flags(options.target, false);
+                let size = (
+                    columns.min(tile::MAX_CELLS) as u16,
+                    rows.min(tile::MAX_CELLS) as u16,
+                );
+                let record = sauce(
+                    size,
+                    sauce::CHARACTER,
+                    sauce::ASCII,
+                    font_name.as_deref(),
+                    flags,
+                );
+                sauce::append(&output, &record);
+            }
+        } else {
+            let embed_font = !options.no_embed_font || font != Font::ibm_vga_8x16();
+            if options.no_embed_font && embed_font {
+                eprintln!("warning: --no-embed-font only applies to the IBM VGA 8x16 font");
+            }
+            // Blink-safe reordering can leave even the ansi palette out of its
+            // standard order.
+            let standard = Palettes::new(&palette::ANSI);
+            let embed_palette = !options.no_embed_palette || palettes.vga != standard.vga;
+            if options.no_embed_palette && embed_palette {
+                eprintln!(
+                    "warning: --no-embed-palette only applies to the ansi palette in its usual order"
+                );
+            }
+            let features = |palettes: &Palettes, chunks: &[Chunk]| xbin::Features {
+                embed_font,
+                embed_palette: !options.no_embed_palette || palettes.vga != standard.vga,
+                compress: !options.no_compress,
+                non_blink: options.ice || chunks.iter().any(|chunk| chunk.bg >= 8),
+            };
+            let language = match format {
+                Format::Rs => Some(source::Language::Rust),
+                Format::C => Some(source::Language::C),
+                Format::Asm => Some(source::Language::Asm),
+                _ => None,
+            };
+            // Embedded XBINs are left without SAUCE, which nothing reads there.
+            if let Some(language) = language {
+                if options.tile.is_some() {
+                    eprintln!("warning: --tile doesn't split source output");
+                }
+                let features = features(&palettes, &chunks);
+                let bytes = xbin::xbin_bytes(columns, &font, &features, &palettes.vga, &chunks);
+                source::save_source(&output, language, &bytes);
+                continue;
+            }
+            let save = |path: &Path, columns: usize, palettes: &Palettes, chunks: &[Chunk]| {
+                let features = features(palettes, chunks);
+                save_xbin(path, columns, &font, &features, &palettes.vga, chunks);
+                if !options.no_sauce {
+                    let size = (columns as u16, (chunks.len() / columns) as u16);
+                    sauce::append(path, &sauce(size, sauce::XBIN, 0, None, 0));
+                }
+                if options.verify {
+                    verify_xbin(path, columns, &font, &features, palettes, chunks);
+                }
+            };
+            // A template names one XBIN for each frame of the source.
+            if sequence::is_template(&output) {
+                if options.tile.is_some() {
+                    eprintln!("warning: --tile doesn't split numbered frames");
+                }
+                let mut frames = convert_frames(source_frames(&input), options, mode);
+                if let Some(fps) = options.fps {
+                    frames = frames::paced(frames, fps);
+                }
+                let mut written = Vec::new();
+                for (index, (duration, conversion)) in frames.iter().enumerate() {
+                    let path = sequence::frame_path(&output, index);
+                    let columns = conversion.columns;
+                    save(&path, columns, &conversion.palettes, &conversion.chunks);
+                    written.push((path, *duration));
+                }
+                let manifest = sequence::manifest_path(&output);
+                sequence::save_manifest(&manifest, &written);
+                eprintln!(
+                    "Wrote {} frames listed in {}",
+                    written.len(),
+                    manifest.display()
+                );
+                continue;
+            }
+            let size = options.tile.unwrap_or(tile::Size {
+                columns: tile::MAX_CELLS,
+                rows: tile::MAX_CELLS,
+            });
+            let tiles = tile::split(columns, rows, size);
+            if tiles.len() == 1 {
+                save(&output, columns, &palettes, &chunks);
+                continue;
+            }
+            let paths: Vec<PathBuf> = (0..tiles.len())
+                .map(|index| tile::tile_path(&output, index, tiles.len()))
+                .collect();
+            for (tile, path) in tiles.iter().zip(paths.iter()) {
+                save(
+                    path,
+                    tile.columns,
+                    &palettes,
+                    &tile::cells(&chunks, columns, tile),
+                );
+            }
+            let manifest = output.with_extension("json");
+            tile::save_manifest(&manifest, columns, rows, &tiles, &paths);
+            eprintln!(
+                "Split into {} tiles listed in {}",
+                tiles.len(),
+                manifest.display()
+            );
+        }
+    }
+    // Everything's written, so there's nothing left to resume.
+    if let Some(checkpoint) = &checkpoint {
+        checkpoint.clear();
+    }
+    if let (Some(score), Some(min_ssim)) = (ssim, options.min_ssim) {
+        if f64::from(score) < min_ssim {
+            exit(Cli::command().error(
+                ErrorKind::ValueValidation,
+                format!("the SSIM of {score:.4} is below --min-ssim {min_ssim}"),
+            ));
+        }
+    }
+    finish_output();
 }
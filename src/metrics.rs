@@ -0,0 +1,61 @@
+use image::RgbImage;
+
+const WINDOW: u32 = 8;
+
+fn luma(image: &RgbImage, x: u32, y: u32) -> f32 {
+    let pixel = image.get_pixel(x, y);
+    (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32) / 255.0
+}
+
+/// Mean SSIM of luma over non-overlapping 8x8 windows of two images of the
+/// same size.
+pub fn ssim(a: &RgbImage, b: &RgbImage) -> f32 {
+    const C1: f32 = 0.01 * 0.01;
+    const C2: f32 = 0.03 * 0.03;
+    let (columns, rows) = (a.width() / WINDOW, a.height() / WINDOW);
+    let mut total = 0.0;
+    for row in 0..rows {
+        for column in 0..columns {
+            let samples: Vec<(f32, f32)> = (0..WINDOW * WINDOW)
+                .map(|i| {
+                    let (x, y) = (column * WINDOW + i % WINDOW, row * WINDOW + i / WINDOW);
+                    (luma(a, x, y), luma(b, x, y))
+                })
+                .collect();
+            let n = samples.len() as f32;
+            let mean_a = samples.iter().map(|(a, _)| a).sum::<f32>() / n;
+            let mean_b = samples.iter().map(|(_, b)| b).sum::<f32>() / n;
+            let (mut variance_a, mut variance_b, mut covariance) = (0.0, 0.0, 0.0);
+            for (a, b) in &samples {
+                variance_a += (a - mean_a).powi(2) / n;
+                variance_b += (b - mean_b).powi(2) / n;
+                covariance += (a - mean_a) * (b - mean_b) / n;
+            }
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a.powi(2) + mean_b.powi(2) + C1) * (variance_a + variance_b + C2));
+        }
+    }
+    total / (columns * rows).max(1) as f32
+}
+
+/// Peak signal-to-noise ratio in decibels over the color channels of two
+/// images of the same size, infinite when they're identical.
+pub fn psnr(a: &RgbImage, b: &RgbImage) -> f32 {
+    let squared: f64 = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(a, b)| (f64::from(*a) - f64::from(*b)).powi(2))
+        .sum();
+    let mean = squared / a.as_raw().len().max(1) as f64;
+    (10.0 * (255.0f64.powi(2) / mean).log10()) as f32
+}
+
+/// The absolute difference of each color channel of two images of the same
+/// size, black where they match.
+pub fn difference(a: &RgbImage, b: &RgbImage) -> RgbImage {
+    RgbImage::from_fn(a.width(), a.height(), |x, y| {
+        let (a, b) = (a.get_pixel(x, y), b.get_pixel(x, y));
+        image::Rgb([0, 1, 2].map(|channel| a[channel].abs_diff(b[channel])))
+    })
+}
@@ -0,0 +1,24 @@
+use crate::chunks::Chunk;
+
+/// Encodes each pixel as a plain colored cell: a space on entries 0-7 and a
+/// full block on entries 8-15, so no viewer can blink or mis-shape it.
+pub fn encode(indexes: &[u8]) -> Vec<Chunk> {
+    indexes
+        .iter()
+        .map(|index| {
+            if *index < 8 {
+                Chunk {
+                    fg: *index,
+                    bg: *index,
+                    codepoint: 0x20,
+                }
+            } else {
+                Chunk {
+                    fg: *index,
+                    bg: 0,
+                    codepoint: 0xdb,
+                }
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,70 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use crate::info;
+use crate::xbin::{self, Features, Xbin};
+use imagequant::RGBA;
+
+/// The cells with the colors their glyphs can't show taken from the cell
+/// before, so more runs share an attribute. A glyph without foreground
+/// pixels leaves its foreground free, and one that is nothing but leaves its
+/// background free, unless that blinks. Blinking shows nothing in a glyph
+/// without foreground pixels, so its blink bit is free too. The bank bit of
+/// a 512-character font's foreground is never free.
+pub fn normalized(xbin: &Xbin) -> Vec<Chunk> {
+    let non_blink = xbin.features.non_blink;
+    let bank = if xbin.font.glyph_count() > 256 {
+        0x08
+    } else {
+        0x00
+    };
+    let mut chunks = xbin.chunks.clone();
+    for row in chunks.chunks_mut(xbin.columns) {
+        for i in 1..row.len() {
+            let before = row[i - 1];
+            let chunk = &mut row[i];
+            let (glyph, _) = xbin.font.cell_glyph(chunk);
+            if glyph.iter().all(|line| *line == 0x00) {
+                chunk.fg = (chunk.fg & bank) | (before.fg & !bank);
+                if !non_blink {
+                    chunk.bg = (chunk.bg & 0x07) | (before.bg & 0x08);
+                }
+            } else if glyph.iter().all(|line| *line == 0xff) && (non_blink || chunk.bg < 0x08) {
+                chunk.bg = if non_blink {
+                    before.bg
+                } else {
+                    before.bg & 0x07
+                };
+            }
+        }
+    }
+    chunks
+}
+
+/// The XBIN written again as small as it goes: its normalized cells run-length
+/// compressed, and without a palette or font that viewers would use anyway.
+pub fn optimized(xbin: &Xbin) -> Vec<u8> {
+    // Viewers fall back to their own 8x16 font, whatever the header's height.
+    let features = Features {
+        embed_palette: !info::is_default_palette(xbin),
+        embed_font: xbin.font != Font::ibm_vga_8x16(),
+        compress: true,
+        non_blink: xbin.features.non_blink,
+    };
+    let palette: Vec<RGBA> = xbin
+        .palette
+        .iter()
+        .map(|color| RGBA {
+            r: color.r / 4,
+            g: color.g / 4,
+            b: color.b / 4,
+            a: 255,
+        })
+        .collect();
+    xbin::xbin_bytes(
+        xbin.columns,
+        &xbin.font,
+        &features,
+        &palette,
+        &normalized(xbin),
+    )
+}
@@ -0,0 +1,260 @@
+use clap::ValueEnum;
+use imagequant::RGBA;
+
+const fn rgb(r: u8, g: u8, b: u8) -> RGBA {
+    RGBA { r, g, b, a: 255 }
+}
+
+/// The 16 colors of the IBM VGA text mode in attribute order, which XBIN
+/// viewers use for files without a palette of their own.
+pub const ANSI: [RGBA; 16] = [
+    rgb(0x00, 0x00, 0x00),
+    rgb(0x00, 0x00, 0xaa),
+    rgb(0x00, 0xaa, 0x00),
+    rgb(0x00, 0xaa, 0xaa),
+    rgb(0xaa, 0x00, 0x00),
+    rgb(0xaa, 0x00, 0xaa),
+    rgb(0xaa, 0x55, 0x00),
+    rgb(0xaa, 0xaa, 0xaa),
+    rgb(0x55, 0x55, 0x55),
+    rgb(0x55, 0x55, 0xff),
+    rgb(0x55, 0xff, 0x55),
+    rgb(0x55, 0xff, 0xff),
+    rgb(0xff, 0x55, 0x55),
+    rgb(0xff, 0x55, 0xff),
+    rgb(0xff, 0xff, 0x55),
+    rgb(0xff, 0xff, 0xff),
+];
+
+/// The 16 ANSI colors as Amiga terminals show them, in 12-bit color and
+/// without the PC's brown in place of dark yellow, in the same order.
+pub const AMIGA: [RGBA; 16] = [
+    rgb(0x00, 0x00, 0x00),
+    rgb(0x00, 0x00, 0xaa),
+    rgb(0x00, 0xaa, 0x00),
+    rgb(0x00, 0xaa, 0xaa),
+    rgb(0xaa, 0x00, 0x00),
+    rgb(0xaa, 0x00, 0xaa),
+    rgb(0xaa, 0xaa, 0x00),
+    rgb(0xaa, 0xaa, 0xaa),
+    rgb(0x55, 0x55, 0x55),
+    rgb(0x55, 0x55, 0xff),
+    rgb(0x55, 0xff, 0x55),
+    rgb(0x55, 0xff, 0xff),
+    rgb(0xff, 0x55, 0x55),
+    rgb(0xff, 0x55, 0xff),
+    rgb(0xff, 0xff, 0x55),
+    rgb(0xff, 0xff, 0xff),
+];
+
+/// The VIC-II's 16 colors in their register order, as measured by Pepto.
+pub const C64: [RGBA; 16] = [
+    rgb(0x00, 0x00, 0x00),
+    rgb(0xff, 0xff, 0xff),
+    rgb(0x68, 0x37, 0x2b),
+    rgb(0x70, 0xa4, 0xb2),
+    rgb(0x6f, 0x3d, 0x86),
+    rgb(0x58, 0x8d, 0x43),
+    rgb(0x35, 0x28, 0x79),
+    rgb(0xb8, 0xc7, 0x6f),
+    rgb(0x6f, 0x4f, 0x25),
+    rgb(0x43, 0x39, 0x00),
+    rgb(0x9a, 0x67, 0x59),
+    rgb(0x44, 0x44, 0x44),
+    rgb(0x6c, 0x6c, 0x6c),
+    rgb(0x9a, 0xd2, 0x84),
+    rgb(0x6c, 0x5e, 0xb5),
+    rgb(0x95, 0x95, 0x95),
+];
+
+/// The ZX Spectrum's colors in its own order, black to white and then the
+/// same again bright, where black stays black.
+pub const SPECTRUM: [RGBA; 16] = [
+    rgb(0x00, 0x00, 0x00),
+    rgb(0x00, 0x00, 0xd7),
+    rgb(0xd7, 0x00, 0x00),
+    rgb(0xd7, 0x00, 0xd7),
+    rgb(0x00, 0xd7, 0x00),
+    rgb(0x00, 0xd7, 0xd7),
+    rgb(0xd7, 0xd7, 0x00),
+    rgb(0xd7, 0xd7, 0xd7),
+    rgb(0x00, 0x00, 0x00),
+    rgb(0x00, 0x00, 0xff),
+    rgb(0xff, 0x00, 0x00),
+    rgb(0xff, 0x00, 0xff),
+    rgb(0x00, 0xff, 0x00),
+    rgb(0x00, 0xff, 0xff),
+    rgb(0xff, 0xff, 0x00),
+    rgb(0xff, 0xff, 0xff),
+];
+
+/// The Atari's Graphics 0 colors as the OS sets them up, background $94
+/// and text $9A, roughly as an NTSC set shows them.
+pub const ATARI: [RGBA; 2] = [rgb(0x1c, 0x4c, 0xb0), rgb(0x80, 0xb4, 0xff)];
+
+/// The colors of xterm's 256-color palette from 16 on: a 6x6x6 color cube
+/// then 24 grays. The first 16 are left out, as terminals theme them.
+pub fn xterm_256() -> Vec<RGBA> {
+    let level = |index: u8| if index == 0 { 0 } else { 55 + 40 * index };
+    let mut colors = Vec::with_capacity(240);
+    for r in 0..6 {
+        for g in 0..6 {
+            for b in 0..6 {
+                colors.push(rgb(level(r), level(g), level(b)));
+            }
+        }
+    }
+    colors.extend((0..24).map(|index| rgb(8 + 10 * index, 8 + 10 * index, 8 + 10 * index)));
+    colors
+}
+
+fn nearest(colors: &[RGBA], color: &RGBA) -> usize {
+    let distance = |other: &RGBA| {
+        (color.r as i32 - other.r as i32).pow(2)
+            + (color.g as i32 - other.g as i32).pow(2)
+            + (color.b as i32 - other.b as i32).pow(2)
+    };
+    (0..colors.len())
+        .min_by_key(|index| distance(&colors[*index]))
+        .expect("colors")
+}
+
+/// The ANSI color nearest `color` out of the first `count`, 8 for those
+/// without bright backgrounds or 16 for them all.
+pub fn nearest_ansi(color: &RGBA, count: usize) -> u8 {
+    nearest(&ANSI[..count], color) as u8
+}
+
+/// The xterm-256 color number nearest `color`.
+pub fn nearest_xterm_256(color: &RGBA) -> u8 {
+    16 + nearest(&xterm_256(), color) as u8
+}
+
+/// The Spectrum color nearest `color`, with 8 added for the bright ones.
+pub fn nearest_spectrum(color: &RGBA) -> u8 {
+    nearest(&SPECTRUM, color) as u8
+}
+
+/// mIRC's 16 colors in its own order, from white and black.
+pub const MIRC: [RGBA; 16] = [
+    rgb(0xff, 0xff, 0xff),
+    rgb(0x00, 0x00, 0x00),
+    rgb(0x00, 0x00, 0x7f),
+    rgb(0x00, 0x93, 0x00),
+    rgb(0xff, 0x00, 0x00),
+    rgb(0x7f, 0x00, 0x00),
+    rgb(0x9c, 0x00, 0x9c),
+    rgb(0xfc, 0x7f, 0x00),
+    rgb(0xff, 0xff, 0x00),
+    rgb(0x00, 0xfc, 0x00),
+    rgb(0x00, 0x93, 0x93),
+    rgb(0x00, 0xff, 0xff),
+    rgb(0x00, 0x00, 0xfc),
+    rgb(0xff, 0x00, 0xff),
+    rgb(0x7f, 0x7f, 0x7f),
+    rgb(0xd2, 0xd2, 0xd2),
+];
+
+// The extended mIRC colors 16 to 98: seven rows of twelve hues from dark to
+// pale, then a gray ramp.
+const MIRC_EXTENDED: [u32; 83] = [
+    0x470000, 0x472100, 0x474700, 0x324700, 0x004700, 0x00472c, 0x004747, 0x002747, 0x000047,
+    0x2e0047, 0x470047, 0x47002a, 0x740000, 0x743a00, 0x747400, 0x517400, 0x007400, 0x007449,
+    0x007474, 0x004074, 0x000074, 0x4b0074, 0x740074, 0x740045, 0xb50000, 0xb56300, 0xb5b500,
+    0x7db500, 0x00b500, 0x00b571, 0x00b5b5, 0x0063b5, 0x0000b5, 0x7500b5, 0xb500b5, 0xb5006b,
+    0xff0000, 0xff8c00, 0xffff00, 0xb2ff00, 0x00ff00, 0x00ffa0, 0x00ffff, 0x008cff, 0x0000ff,
+    0xa500ff, 0xff00ff, 0xff0098, 0xff5959, 0xffb459, 0xffff71, 0xcfff60, 0x6fff6f, 0x65ffc9,
+    0x6dffff, 0x59b4ff, 0x5959ff, 0xc459ff, 0xff66ff, 0xff59bc, 0xff9c9c, 0xffd39c, 0xffff9c,
+    0xe2ff9c, 0x9cff9c, 0x9cffdb, 0x9cffff, 0x9cd3ff, 0x9c9cff, 0xdc9cff, 0xff9cff, 0xff94d3,
+    0x000000, 0x131313, 0x282828, 0x363636, 0x4d4d4d, 0x656565, 0x818181, 0x9f9f9f, 0xbcbcbc,
+    0xe2e2e2, 0xffffff,
+];
+
+/// All 99 mIRC colors, the 16 classic ones first.
+pub fn mirc_99() -> Vec<RGBA> {
+    let extended = MIRC_EXTENDED
+        .iter()
+        .map(|hex| rgb((hex >> 16) as u8, (hex >> 8) as u8, *hex as u8));
+    MIRC.iter().copied().chain(extended).collect()
+}
+
+/// The mIRC color number nearest `color` out of the first `count`, 16 for
+/// the classic colors or 99 for them all.
+pub fn nearest_mirc(color: &RGBA, count: usize) -> u8 {
+    nearest(&mirc_99()[..count], color) as u8
+}
+
+/// A fixed palette to encode with instead of generating one.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Standard {
+    /// The IBM VGA text mode colors
+    Ansi,
+    /// The ANSI colors of Amiga terminals
+    Amiga,
+    /// The 16 classic mIRC colors
+    Mirc,
+    /// The Commodore 64's VIC-II colors
+    C64,
+    /// The Atari's two Graphics 0 colors
+    Atari,
+    /// The ZX Spectrum's colors
+    Spectrum,
+}
+
+impl Standard {
+    pub fn colors(self) -> &'static [RGBA] {
+        match self {
+            Standard::Ansi => &ANSI,
+            Standard::Amiga => &AMIGA,
+            Standard::Mirc => &MIRC,
+            Standard::C64 => &C64,
+            Standard::Atari => &ATARI,
+            Standard::Spectrum => &SPECTRUM,
+        }
+    }
+}
+
+pub struct Palettes {
+    pub vga: Vec<RGBA>,
+    pub rgba: Vec<RGBA>,
+    pub count: usize,
+}
+
+impl Palettes {
+    pub fn new(rgba: &[RGBA]) -> Self {
+        let count = rgba.len();
+        let mut vga: Vec<RGBA> = rgba
+            .iter()
+            .map(|color| RGBA {
+                r: (color.r as f32 / 255.0 * 63.0).round() as u8,
+                g: (color.g as f32 / 255.0 * 63.0).round() as u8,
+                b: (color.b as f32 / 255.0 * 63.0).round() as u8,
+                a: 255,
+            })
+            .collect();
+        while vga.len() < 16 {
+            vga.push(RGBA {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            });
+        }
+        let rgba: Vec<RGBA> = vga
+            .iter()
+            .map(|color| RGBA {
+                r: color.r * 4,
+                g: color.g * 4,
+                b: color.b * 4,
+                a: 255,
+            })
+            .collect();
+        Palettes { vga, rgba, count }
+    }
+
+    /// Rearranges the entries so that entry `order[i]` becomes entry `i`.
+    pub fn reorder(&mut self, order: &[usize]) {
+        self.vga = order.iter().map(|index| self.vga[*index]).collect();
+        self.rgba = order.iter().map(|index| self.rgba[*index]).collect();
+    }
+}
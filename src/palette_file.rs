@@ -0,0 +1,132 @@
+use imagequant::RGBA;
+use std::fmt::Write;
+use std::path::Path;
+
+/// The palette files editors read and write, told apart by extension.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaletteFormat {
+    /// GIMP's .gpl, also read by Inkscape, Krita and Aseprite
+    Gpl,
+    /// JASC-PAL .pal, from Paint Shop Pro, read by most pixel art editors
+    Jasc,
+    /// A .hex list of RRGGBB lines, as Lospec shares palettes
+    Hex,
+}
+
+impl PaletteFormat {
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gpl" => Some(PaletteFormat::Gpl),
+            "pal" => Some(PaletteFormat::Jasc),
+            "hex" => Some(PaletteFormat::Hex),
+            _ => None,
+        }
+    }
+}
+
+/// The colors as a palette file, named `name` where the format has room.
+pub fn palette_text(format: PaletteFormat, name: &str, colors: &[RGBA]) -> String {
+    let mut text = String::new();
+    match format {
+        PaletteFormat::Gpl => {
+            writeln!(text, "GIMP Palette").unwrap();
+            writeln!(text, "Name: {name}").unwrap();
+            writeln!(text, "Columns: {}", colors.len()).unwrap();
+            writeln!(text, "#").unwrap();
+            for (index, color) in colors.iter().enumerate() {
+                writeln!(
+                    text,
+                    "{:3} {:3} {:3}\tIndex {index}",
+                    color.r, color.g, color.b
+                )
+                .unwrap();
+            }
+        }
+        PaletteFormat::Jasc => {
+            // JASC files end their lines DOS style.
+            write!(text, "JASC-PAL\r\n0100\r\n{}\r\n", colors.len()).unwrap();
+            for color in colors {
+                write!(text, "{} {} {}\r\n", color.r, color.g, color.b).unwrap();
+            }
+        }
+        PaletteFormat::Hex => {
+            for color in colors {
+                writeln!(text, "{:02x}{:02x}{:02x}", color.r, color.g, color.b).unwrap();
+            }
+        }
+    }
+    text
+}
+
+/// Writes the colors as a palette file named for the file's stem.
+pub fn save_palette(path: &Path, format: PaletteFormat, colors: &[RGBA]) {
+    let name = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    std::fs::write(path, palette_text(format, &name, colors)).unwrap();
+}
+
+/// Colors read from a palette file, in any of the formats it can be saved in.
+#[derive(Clone, Debug)]
+pub struct PaletteFile {
+    pub colors: Vec<RGBA>,
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> RGBA {
+    RGBA { r, g, b, a: 255 }
+}
+
+// A line of three decimal components, as in GPL and JASC files, which GPL
+// follows with a name.
+fn decimal_color(line: &str) -> Option<RGBA> {
+    let mut values = line.split_whitespace().map(|value| value.parse::<u8>());
+    match (values.next(), values.next(), values.next()) {
+        (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => Some(rgb(r, g, b)),
+        _ => None,
+    }
+}
+
+fn hex_color(line: &str) -> Option<RGBA> {
+    let digits = line.strip_prefix('#').unwrap_or(line);
+    if digits.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(digits, 16).ok()?;
+    Some(rgb((value >> 16) as u8, (value >> 8) as u8, value as u8))
+}
+
+impl PaletteFile {
+    /// Reads a GIMP, JASC or hex palette of 1 to 16 colors, telling them
+    /// apart by their first line.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let first = lines.clone().next().unwrap_or_default();
+        let colors: Option<Vec<RGBA>> = if first == "GIMP Palette" {
+            lines
+                .skip(1)
+                .filter(|line| {
+                    !line.starts_with('#')
+                        && !line.starts_with("Name:")
+                        && !line.starts_with("Columns:")
+                })
+                .map(decimal_color)
+                .collect()
+        } else if first == "JASC-PAL" {
+            let count = lines.nth(2).and_then(|count| count.parse::<usize>().ok());
+            let colors: Option<Vec<RGBA>> = lines.map(decimal_color).collect();
+            colors.filter(|colors| Some(colors.len()) == count)
+        } else {
+            lines.map(hex_color).collect()
+        };
+        let colors = colors.ok_or_else(|| format!("{path}: not a GPL, JASC or hex palette"))?;
+        if colors.is_empty() || colors.len() > 16 {
+            return Err(format!(
+                "{path}: {} colors, where a palette holds 1 to 16",
+                colors.len()
+            ));
+        }
+        Ok(PaletteFile { colors })
+    }
+}
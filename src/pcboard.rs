@@ -0,0 +1,49 @@
+use crate::ansi::{printable, CONTROLS};
+use crate::chunks::Chunk;
+use crate::font::Font;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Extensions written as PCBoard @X codes rather than XBIN.
+pub fn is_pcboard_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pcb"))
+}
+
+/// Writes the cells as CP437 text with PCBoard's `@X<bg><fg>` color codes,
+/// in hex digits of the attribute. As with ANSI, colors a glyph doesn't show
+/// are left as they are and blank cells on black are trimmed from line ends.
+/// `@` starts PCBoard's codes and macros, so it is drawn with its nearest
+/// glyph along with the control codes.
+pub fn save_pcboard(path: impl AsRef<Path>, columns: usize, font: &Font, chunks: &[Chunk]) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    let printable = printable(font, &[&CONTROLS[..], b"@"].concat());
+    let empty = |chunk: &Chunk| font.glyph(chunk.codepoint).iter().all(|row| *row == 0x00);
+    let solid = |chunk: &Chunk| font.glyph(chunk.codepoint).iter().all(|row| *row == 0xff);
+    let unpainted = |chunk: &Chunk| chunk.bg == 0 && empty(chunk);
+    let mut current: Option<(u8, u8)> = None;
+    let rows: Vec<&[Chunk]> = chunks.chunks(columns.max(1)).collect();
+    for (index, row) in rows.iter().enumerate() {
+        let end = row
+            .iter()
+            .rposition(|chunk| !unpainted(chunk))
+            .map_or(0, |i| i + 1);
+        for chunk in &row[..end] {
+            let (fg, bg) = current.unwrap_or((chunk.fg, chunk.bg));
+            let wanted = (
+                if empty(chunk) { fg } else { chunk.fg },
+                if solid(chunk) { bg } else { chunk.bg },
+            );
+            if current != Some(wanted) {
+                write!(file, "@X{:X}{:X}", wanted.1, wanted.0).unwrap();
+                current = Some(wanted);
+            }
+            file.write_all(&[printable[chunk.codepoint as usize]])
+                .unwrap();
+        }
+        if end < row.len() && index + 1 < rows.len() {
+            file.write_all(b"\r\n").unwrap();
+        }
+    }
+    file.write_all(b"@X07").unwrap();
+}
@@ -0,0 +1,232 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use imagequant::RGBA;
+use std::path::Path;
+
+/// Extensions written as PDF rather than XBIN.
+pub fn is_pdf_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+/// Cells next to each other in a row that draw in one color, and for text
+/// from the same bank of the font.
+pub struct Run {
+    pub row: usize,
+    pub column: usize,
+    pub color: u8,
+    pub bank: usize,
+    pub codepoints: Vec<u8>,
+}
+
+/// How the cell grid sits on the page, in points.
+pub struct Page {
+    pub rows: usize,
+    /// Width and height of one font pixel.
+    pub pixel: (f32, f32),
+    pub font_height: usize,
+}
+
+impl Page {
+    pub fn size(&self, columns: usize) -> (f32, f32) {
+        let (width, height) = self.pixel;
+        (
+            columns as f32 * 8.0 * width,
+            (self.rows * self.font_height) as f32 * height,
+        )
+    }
+
+    /// The bottom left corner of a cell, with the first row at the top.
+    pub fn origin(&self, row: usize, column: usize) -> (f32, f32) {
+        let (width, height) = self.pixel;
+        let top = (self.rows - row - 1) * self.font_height;
+        (column as f32 * 8.0 * width, top as f32 * height)
+    }
+}
+
+/// Runs of cells sharing a background.
+pub fn background_runs(columns: usize, chunks: &[Chunk]) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let (row, column) = (index / columns, index % columns);
+        match runs.last_mut() {
+            Some(run) if run.row == row && run.color == chunk.bg => {
+                run.codepoints.push(chunk.codepoint)
+            }
+            _ => runs.push(Run {
+                row,
+                column,
+                color: chunk.bg,
+                bank: 0,
+                codepoints: vec![chunk.codepoint],
+            }),
+        }
+    }
+    runs
+}
+
+/// Runs of cells whose glyphs draw in one foreground and bank, where blank
+/// glyphs in between join the run around them.
+pub fn text_runs(columns: usize, font: &Font, chunks: &[Chunk]) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    let mut blanks = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let (row, column) = (index / columns, index % columns);
+        if column == 0 {
+            blanks.clear();
+        }
+        let (glyph, color) = font.cell_glyph(chunk);
+        if glyph.iter().all(|line| *line == 0) {
+            blanks.push(chunk.codepoint);
+            continue;
+        }
+        let bank = usize::from(font.glyph_count() > 256 && chunk.fg & 0x08 != 0);
+        match runs.last_mut() {
+            Some(run) if run.row == row && run.color == color && run.bank == bank => {
+                run.codepoints.append(&mut blanks);
+                run.codepoints.push(chunk.codepoint);
+            }
+            _ => {
+                blanks.clear();
+                runs.push(Run {
+                    row,
+                    column,
+                    color,
+                    bank,
+                    codepoints: vec![chunk.codepoint],
+                });
+            }
+        }
+    }
+    runs
+}
+
+/// A color as the fractions both PDF and PostScript take.
+pub fn components(color: &RGBA) -> String {
+    let fraction = |value: u8| value as f32 / 255.0;
+    format!(
+        "{:.3} {:.3} {:.3}",
+        fraction(color.r),
+        fraction(color.g),
+        fraction(color.b)
+    )
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+struct Document {
+    bytes: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl Document {
+    fn object(&mut self, id: usize, body: &[u8]) {
+        self.offsets[id] = self.bytes.len();
+        self.bytes
+            .extend_from_slice(format!("{id} 0 obj\n").as_bytes());
+        self.bytes.extend_from_slice(body);
+        self.bytes.extend_from_slice(b"\nendobj\n");
+    }
+
+    fn stream(&mut self, id: usize, data: &[u8]) {
+        let mut body = format!("<< /Length {} >>\nstream\n", data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.object(id, &body);
+    }
+}
+
+/// Writes the cells as a one-page PDF: backgrounds as filled rectangles and
+/// glyphs as text in a Type 3 font made of the font's bitmaps, one for each
+/// bank, so the page scales without blurring.
+pub fn save_pdf(
+    path: impl AsRef<Path>,
+    columns: usize,
+    font: &Font,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+    page: &Page,
+) {
+    let height = font.height as usize;
+    let banks = font.glyph_count() / 256;
+    // The catalog, page tree, page and contents, then each bank's font
+    // followed by its 256 glyph procedures.
+    const FONTS: usize = 5;
+    let font_id = |bank: usize| FONTS + bank * 257;
+    let mut document = Document {
+        bytes: b"%PDF-1.4\n".to_vec(),
+        offsets: vec![0; FONTS + banks * 257],
+    };
+    document.object(1, b"<< /Type /Catalog /Pages 2 0 R >>");
+    document.object(2, b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+    let (width, page_height) = page.size(columns);
+    let fonts: String = (0..banks)
+        .map(|bank| format!("/F{bank} {} 0 R ", font_id(bank)))
+        .collect();
+    let page_object = format!(
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width:.3} {page_height:.3}] \
+         /Contents 4 0 R /Resources << /Font << {fonts}>> >> >>"
+    );
+    document.object(3, page_object.as_bytes());
+    let (pixel_width, pixel_height) = page.pixel;
+    let mut contents = String::new();
+    for run in background_runs(columns, chunks) {
+        let (x, y) = page.origin(run.row, run.column);
+        contents += &format!(
+            "{} rg {x:.3} {y:.3} {:.3} {:.3} re f\n",
+            components(&palette[run.color as usize]),
+            run.codepoints.len() as f32 * 8.0 * pixel_width,
+            height as f32 * pixel_height
+        );
+    }
+    contents += "BT\n";
+    for run in text_runs(columns, font, chunks) {
+        let (x, y) = page.origin(run.row, run.column);
+        contents += &format!(
+            "{} rg /F{} 1 Tf {pixel_width:.3} 0 0 {pixel_height:.3} {x:.3} {y:.3} Tm <{}> Tj\n",
+            components(&palette[run.color as usize]),
+            run.bank,
+            hex(&run.codepoints)
+        );
+    }
+    contents += "ET";
+    document.stream(4, contents.as_bytes());
+    for bank in 0..banks {
+        let id = font_id(bank);
+        let procedures: String = (0..256)
+            .map(|code| format!("/g{code} {} 0 R ", id + 1 + code))
+            .collect();
+        let names: String = (0..256).map(|code| format!("/g{code} ")).collect();
+        let widths = "8 ".repeat(256);
+        let font_object = format!(
+            "<< /Type /Font /Subtype /Type3 /FontBBox [0 0 8 {height}] \
+             /FontMatrix [1 0 0 1 0 0] /CharProcs << {procedures}>> \
+             /Encoding << /Type /Encoding /Differences [0 {names}] >> \
+             /FirstChar 0 /LastChar 255 /Widths [{widths}] /Resources << >> >>"
+        );
+        document.object(id, font_object.as_bytes());
+        for code in 0..256 {
+            let mut procedure = format!(
+                "8 0 0 0 8 {height} d1 q 8 0 0 {height} 0 0 cm \
+                 BI /IM true /W 8 /H {height} /BPC 1 /D [1 0] ID "
+            )
+            .into_bytes();
+            procedure.extend_from_slice(font.glyph_at(bank * 256 + code));
+            procedure.extend_from_slice(b"\nEI Q");
+            document.stream(id + 1 + code, &procedure);
+        }
+    }
+    let xref = document.bytes.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", document.offsets.len());
+    for offset in &document.offsets[1..] {
+        trailer += &format!("{offset:010} 00000 n \n");
+    }
+    trailer += &format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        document.offsets.len()
+    );
+    document.bytes.extend_from_slice(trailer.as_bytes());
+    std::fs::write(path, document.bytes).unwrap();
+}
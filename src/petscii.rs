@@ -0,0 +1,63 @@
+use crate::chunks::Chunk;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The C64's screen is 40 columns wide.
+pub const COLUMNS: usize = 40;
+
+const CLEAR: u8 = 0x93;
+const RETURN: u8 = 0x0d;
+const REVERSE_ON: u8 = 0x12;
+const REVERSE_OFF: u8 = 0x92;
+
+// The PETSCII control codes selecting each VIC-II color, in palette order.
+const COLORS: [u8; 16] = [
+    0x90, 0x05, 0x1c, 0x9f, 0x9c, 0x1e, 0x1f, 0x9e, 0x81, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0x9b,
+];
+
+/// Extensions written as PETSCII streams rather than XBIN.
+pub fn is_seq_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("seq"))
+}
+
+// The PETSCII code printing a screen code below 128, the rest being the same
+// glyphs reversed.
+fn petscii(screen_code: u8) -> u8 {
+    match screen_code {
+        0x00..=0x1f => screen_code + 0x40,
+        0x20..=0x3f => screen_code,
+        0x40..=0x5f => screen_code + 0x80,
+        _ => screen_code + 0x40,
+    }
+}
+
+/// Writes cells whose codepoints are C64 screen codes as a PETSCII stream
+/// that clears the screen and prints them with color and reverse codes. Rows
+/// narrower than the screen end with a return, which also turns reverse off.
+/// The background comes from the viewer, as no code sets it.
+pub fn save_seq(path: impl AsRef<Path>, columns: usize, chunks: &[Chunk]) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    file.write_all(&[CLEAR]).unwrap();
+    let mut color = None;
+    let mut reverse = false;
+    for row in chunks.chunks(columns.max(1)) {
+        for chunk in row {
+            if color != Some(chunk.fg) {
+                file.write_all(&[COLORS[chunk.fg as usize]]).unwrap();
+                color = Some(chunk.fg);
+            }
+            let reversed = chunk.codepoint >= 0x80;
+            if reversed != reverse {
+                let code = if reversed { REVERSE_ON } else { REVERSE_OFF };
+                file.write_all(&[code]).unwrap();
+                reverse = reversed;
+            }
+            file.write_all(&[petscii(chunk.codepoint & 0x7f)]).unwrap();
+        }
+        if row.len() < COLUMNS {
+            file.write_all(&[RETURN]).unwrap();
+            reverse = false;
+        }
+    }
+}
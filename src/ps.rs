@@ -0,0 +1,91 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use crate::pdf::{self, Page};
+use imagequant::RGBA;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Extensions written as PostScript rather than XBIN.
+pub fn is_ps_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ps"))
+}
+
+/// Writes the cells as a one-page PostScript file, drawn as in PDF output
+/// with a Type 3 font whose BuildChar masks in the font's bitmaps.
+pub fn save_ps(
+    path: impl AsRef<Path>,
+    columns: usize,
+    font: &Font,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+    page: &Page,
+) {
+    let height = font.height as usize;
+    let (width, page_height) = page.size(columns);
+    let (pixel_width, pixel_height) = page.pixel;
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    writeln!(file, "%!PS-Adobe-3.0").unwrap();
+    writeln!(
+        file,
+        "%%BoundingBox: 0 0 {} {}",
+        width.ceil() as u32,
+        page_height.ceil() as u32
+    )
+    .unwrap();
+    writeln!(file, "%%HiResBoundingBox: 0 0 {width:.3} {page_height:.3}").unwrap();
+    writeln!(file, "%%Pages: 1").unwrap();
+    writeln!(file, "%%EndComments").unwrap();
+    writeln!(file, "%%BeginProlog").unwrap();
+    for bank in 0..font.glyph_count() / 256 {
+        writeln!(file, "/F{bank} 8 dict begin").unwrap();
+        writeln!(file, "/FontType 3 def").unwrap();
+        writeln!(file, "/FontMatrix [1 0 0 1 0 0] def").unwrap();
+        writeln!(file, "/FontBBox [0 0 8 {height}] def").unwrap();
+        writeln!(file, "/Encoding 256 array def").unwrap();
+        writeln!(file, "0 1 255 {{ Encoding exch /.notdef put }} for").unwrap();
+        writeln!(file, "/Glyphs [").unwrap();
+        for code in 0..256 {
+            writeln!(file, "<{}>", pdf::hex(font.glyph_at(bank * 256 + code))).unwrap();
+        }
+        writeln!(file, "] def").unwrap();
+        writeln!(
+            file,
+            "/BuildChar {{ exch begin Glyphs exch get 8 0 0 0 8 {height} setcachedevice \
+             8 {height} true [1 0 0 -1 0 {height}] 5 -1 roll imagemask end }} bind def"
+        )
+        .unwrap();
+        writeln!(file, "currentdict end definefont").unwrap();
+        writeln!(
+            file,
+            "[{pixel_width:.3} 0 0 {pixel_height:.3} 0 0] makefont /S{bank} exch def"
+        )
+        .unwrap();
+    }
+    writeln!(file, "%%EndProlog").unwrap();
+    writeln!(file, "%%Page: 1 1").unwrap();
+    for run in pdf::background_runs(columns, chunks) {
+        let (x, y) = page.origin(run.row, run.column);
+        writeln!(
+            file,
+            "{} setrgbcolor {x:.3} {y:.3} {:.3} {:.3} rectfill",
+            pdf::components(&palette[run.color as usize]),
+            run.codepoints.len() as f32 * 8.0 * pixel_width,
+            height as f32 * pixel_height
+        )
+        .unwrap();
+    }
+    for run in pdf::text_runs(columns, font, chunks) {
+        let (x, y) = page.origin(run.row, run.column);
+        writeln!(
+            file,
+            "{} setrgbcolor S{} setfont {x:.3} {y:.3} moveto <{}> show",
+            pdf::components(&palette[run.color as usize]),
+            run.bank,
+            pdf::hex(&run.codepoints)
+        )
+        .unwrap();
+    }
+    writeln!(file, "showpage").unwrap();
+    writeln!(file, "%%EOF").unwrap();
+}
@@ -0,0 +1,119 @@
+use crate::cp437::CP437;
+use crate::font::Font;
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+
+pub fn is_psf(data: &[u8]) -> bool {
+    data.starts_with(&PSF1_MAGIC) || data.starts_with(&PSF2_MAGIC)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+// Splits a PSF1 Unicode table of u16 entries into the characters of each glyph.
+// 0xfffe starts combining sequences, which can't be matched to a codepoint.
+fn psf1_table(mut table: &[u8], count: usize) -> Vec<Vec<char>> {
+    let mut glyphs = vec![Vec::new(); count];
+    for chars in glyphs.iter_mut() {
+        let mut in_sequence = false;
+        while table.len() >= 2 {
+            let value = u16::from_le_bytes([table[0], table[1]]);
+            table = &table[2..];
+            match value {
+                0xffff => break,
+                0xfffe => in_sequence = true,
+                _ if !in_sequence => chars.extend(char::from_u32(value as u32)),
+                _ => {}
+            }
+        }
+    }
+    glyphs
+}
+
+// Splits a PSF2 Unicode table of UTF-8 strings, where 0xff ends a glyph's
+// entry and 0xfe starts its combining sequences.
+fn psf2_table(table: &[u8], count: usize) -> Vec<Vec<char>> {
+    let mut entries = table.split(|byte| *byte == 0xff);
+    (0..count)
+        .map(|_| {
+            let entry = entries.next().unwrap_or_default();
+            let singles = entry.split(|byte| *byte == 0xfe).next().unwrap_or_default();
+            String::from_utf8_lossy(singles)
+                .chars()
+                .filter(|c| *c != char::REPLACEMENT_CHARACTER)
+                .collect()
+        })
+        .collect()
+}
+
+/// Reads a Linux console PSF1 or PSF2 font of 8-pixel-wide glyphs. When the
+/// font has a Unicode table its glyphs are rearranged into CP437 order, and
+/// codepoints the table doesn't cover keep the glyph at their own position.
+/// Fonts of 512 or more glyphs keep a second bank of the next glyphs not
+/// already used.
+pub fn parse(data: &[u8]) -> Result<Font, String> {
+    let (count, height, bytes_per_glyph, offset, has_table) = if data.starts_with(&PSF1_MAGIC) {
+        if data.len() < 4 {
+            return Err("truncated PSF1 header".to_string());
+        }
+        let count = if data[2] & 0x01 != 0 { 512 } else { 256 };
+        (
+            count,
+            data[3] as usize,
+            data[3] as usize,
+            4,
+            data[2] & 0x06 != 0,
+        )
+    } else {
+        if data.len() < 32 {
+            return Err("truncated PSF2 header".to_string());
+        }
+        let (offset, flags) = (u32_at(data, 8) as usize, u32_at(data, 12));
+        let (count, bytes_per_glyph) = (u32_at(data, 16) as usize, u32_at(data, 20) as usize);
+        let (height, width) = (u32_at(data, 24) as usize, u32_at(data, 28));
+        if width != 8 {
+            return Err(format!("glyphs are {width} pixels wide, not 8"));
+        }
+        (count, height, bytes_per_glyph, offset, flags & 0x01 != 0)
+    };
+    if !(1..=32).contains(&height) || bytes_per_glyph != height || count < 256 {
+        return Err(format!(
+            "unsupported PSF layout: {count} glyphs of {height} rows"
+        ));
+    }
+    let end = offset + count * bytes_per_glyph;
+    let bitmaps = data.get(offset..end).ok_or("truncated PSF glyph data")?;
+    let glyph = |index: usize| &bitmaps[index * height..(index + 1) * height];
+    let table = if !has_table {
+        None
+    } else if data.starts_with(&PSF1_MAGIC) {
+        Some(psf1_table(&data[end..], count))
+    } else {
+        Some(psf2_table(&data[end..], count))
+    };
+    let mut glyphs = Vec::with_capacity(512 * height);
+    let mut used = vec![false; count];
+    for (codepoint, c) in CP437.iter().enumerate() {
+        let index = table
+            .as_ref()
+            .and_then(|table| table.iter().position(|chars| chars.contains(c)))
+            .unwrap_or(codepoint);
+        used[index] = true;
+        glyphs.extend_from_slice(glyph(index));
+    }
+    if count >= 512 {
+        let rest: Vec<usize> = (0..count).filter(|index| !used[*index]).collect();
+        for index in 0..256 {
+            match rest.get(index) {
+                Some(index) => glyphs.extend_from_slice(glyph(*index)),
+                None => glyphs.extend(std::iter::repeat_n(0, height)),
+            }
+        }
+    }
+    Ok(Font {
+        height: height as u8,
+        glyphs,
+    })
+}
@@ -0,0 +1,74 @@
+use crate::chunks::{best_block, distance_table, rgba_to_oklab, Chunk};
+use crate::cp437::CP437;
+use crate::font::Font;
+use crate::palette::Palettes;
+
+// Codepoint for each 2x2 pattern, indexed by a mask with top-left as bit 3,
+// top-right bit 2, bottom-left bit 1 and bottom-right bit 0. Patterns CP437
+// already has keep their usual codepoints; the rest replace the dingbats at
+// 0x01-0x0a in the embedded font.
+const GLYPHS: [u8; 16] = [
+    0x20, 0x04, 0x03, 0xdc, 0x02, 0xde, 0x06, 0x05, 0x01, 0x0a, 0xdd, 0x08, 0xdf, 0x09, 0x07, 0xdb,
+];
+
+// The Unicode block element for each mask, in the order of `GLYPHS`.
+const CHARS: [char; 16] = [
+    ' ', '▗', '▖', '▄', '▝', '▐', '▞', '▟', '▘', '▚', '▌', '▙', '▀', '▜', '▛', '█',
+];
+
+/// The character for a cell's codepoint, the quadrant block element for the
+/// patterns and CP437 for anything else, so UTF-8 output shows the blocks
+/// rather than the dingbats their codepoints replace.
+pub fn to_char(codepoint: u8) -> char {
+    match GLYPHS.iter().position(|glyph| *glyph == codepoint) {
+        Some(mask) => CHARS[mask],
+        None => CP437[codepoint as usize],
+    }
+}
+
+/// The IBM VGA 8x16 font with every 2x2 quadrant pattern split evenly at 4
+/// pixels across and 8 down.
+pub fn font() -> Font {
+    let mut font = Font::ibm_vga_8x16();
+    for (mask, codepoint) in GLYPHS.iter().enumerate() {
+        let half = |left: usize, right: usize| -> u8 {
+            (if mask & left != 0 { 0xf0 } else { 0 }) | (if mask & right != 0 { 0x0f } else { 0 })
+        };
+        let start = *codepoint as usize * 16;
+        font.glyphs[start..start + 8].fill(half(8, 4));
+        font.glyphs[start + 8..start + 16].fill(half(2, 1));
+    }
+    font
+}
+
+/// Encodes each 2x2 block of pixels as the quadrant pattern and fg/bg pair
+/// with the least Oklab error, keeping the darker color in the background.
+pub fn encode(palettes: &Palettes, indexes: &[u8], width: usize, height: usize) -> Vec<Chunk> {
+    let distances = distance_table(palettes);
+    let lightness: Vec<f32> = palettes
+        .rgba
+        .iter()
+        .map(|color| rgba_to_oklab(color).l)
+        .collect();
+    let pixel = |x: usize, y: usize| indexes[y.min(height - 1) * width + x.min(width - 1)];
+    let (columns, rows) = (width.div_ceil(2), height.div_ceil(2));
+    let mut chunks = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let (x, y) = (column * 2, row * 2);
+            let block = [
+                pixel(x, y),
+                pixel(x + 1, y),
+                pixel(x, y + 1),
+                pixel(x + 1, y + 1),
+            ];
+            let (bg, fg, mask) = best_block(&block, &distances, &lightness);
+            chunks.push(Chunk {
+                fg,
+                bg,
+                codepoint: GLYPHS[mask as usize],
+            });
+        }
+    }
+    chunks
+}
@@ -0,0 +1,61 @@
+use crate::chunks::{rgba_to_oklab, Chunk};
+use crate::palette::Palettes;
+use imagequant::RGBA;
+use oklab::{oklab_to_srgb, Oklab};
+
+// One k-means style step: every palette entry moves to the mean of the source
+// pixels the fg/bg-constrained encoding actually assigned to it.
+pub fn refit_palette(pixels: &[Oklab], palettes: &Palettes, chunks: &[Chunk]) -> Vec<RGBA> {
+    let mut sums = vec![(0.0f32, 0.0f32, 0.0f32, 0usize); palettes.count];
+    for (chunk, pixels) in chunks.iter().zip(pixels.chunks_exact(8)) {
+        for (i, pixel) in pixels.iter().enumerate() {
+            if let Some(sum) = sums.get_mut(chunk.index_at(i) as usize) {
+                sum.0 += pixel.l;
+                sum.1 += pixel.a;
+                sum.2 += pixel.b;
+                sum.3 += 1;
+            }
+        }
+    }
+    sums.iter()
+        .zip(palettes.rgba.iter())
+        .map(|((l, a, b, count), color)| {
+            if *count == 0 {
+                return *color;
+            }
+            let n = *count as f32;
+            let rgb = oklab_to_srgb(Oklab {
+                l: l / n,
+                a: a / n,
+                b: b / n,
+            });
+            RGBA {
+                r: rgb.r,
+                g: rgb.g,
+                b: rgb.b,
+                a: 255,
+            }
+        })
+        .collect()
+}
+
+/// Maps every source pixel to its nearest entry in the palette.
+pub fn remap(pixels: &[Oklab], palettes: &Palettes) -> Vec<u8> {
+    let ok_palette: Vec<Oklab> = palettes.rgba[..palettes.count]
+        .iter()
+        .map(rgba_to_oklab)
+        .collect();
+    pixels
+        .iter()
+        .map(|pixel| {
+            let distance = |color: &Oklab| {
+                (color.l - pixel.l).powi(2)
+                    + (color.a - pixel.a).powi(2)
+                    + (color.b - pixel.b).powi(2)
+            };
+            (0..ok_palette.len())
+                .min_by(|a, b| distance(&ok_palette[*a]).total_cmp(&distance(&ok_palette[*b])))
+                .expect("palette") as u8
+        })
+        .collect()
+}
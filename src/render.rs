@@ -0,0 +1,28 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use image::{Rgb, RgbImage};
+use imagequant::RGBA;
+
+/// Rasterizes the cells with 8-pixel-wide glyphs from `font`, as a viewer
+/// would draw them.
+pub fn render(columns: usize, font: &Font, palette: &[RGBA], chunks: &[Chunk]) -> RgbImage {
+    let height = font.height as usize;
+    let rows = chunks.len() / columns.max(1);
+    let mut image = RgbImage::new((columns * 8) as u32, (rows * height) as u32);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let (column, row) = (i % columns, i / columns);
+        let (glyph, fg) = font.cell_glyph(chunk);
+        let (fg, bg) = (palette[fg as usize], palette[chunk.bg as usize]);
+        for (y, bits) in glyph.iter().enumerate() {
+            for x in 0..8 {
+                let color = if bits & (0x80 >> x) != 0 { fg } else { bg };
+                image.put_pixel(
+                    (column * 8 + x) as u32,
+                    (row * height + y) as u32,
+                    Rgb([color.r, color.g, color.b]),
+                );
+            }
+        }
+    }
+    image
+}
@@ -0,0 +1,221 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const CHARACTER: u8 = 1;
+pub const BINARY_TEXT: u8 = 5;
+pub const XBIN: u8 = 6;
+
+/// File types within the character data type.
+pub const ASCII: u8 = 0;
+pub const ANSI: u8 = 1;
+pub const ANSIMATION: u8 = 2;
+pub const PCBOARD: u8 = 4;
+pub const AVATAR: u8 = 5;
+pub const TUNDRA: u8 = 8;
+
+/// The metadata of a SAUCE v00 record, which artscene viewers and archives
+/// read from the last 128 bytes of a file.
+pub struct Sauce {
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    /// CCYYMMDD.
+    pub date: String,
+    pub data_type: u8,
+    pub file_type: u8,
+    /// Width in characters.
+    pub width: u16,
+    /// Height in lines.
+    pub height: u16,
+    /// The TInfoS font name, for data types whose viewers pick a font.
+    pub font_name: Option<String>,
+    /// Lines of the COMNT block, each up to 64 characters.
+    pub comments: Vec<String>,
+    /// TFlags, which for character data are the ANSiFlags.
+    pub flags: u8,
+}
+
+/// The pixel shape the ANSiFlags ask viewers to show.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AspectRatio {
+    Unset,
+    Square,
+}
+
+/// ANSiFlags for a character file: iCE colors in place of blinking, the 8 or
+/// 9-pixel letter spacing if known, and the aspect ratio.
+pub fn ansi_flags(non_blink: bool, letter_spacing: Option<u8>, aspect: AspectRatio) -> u8 {
+    let spacing = match letter_spacing {
+        None => 0b00,
+        Some(8) => 0b01,
+        Some(_) => 0b10,
+    };
+    // 0b01 would ask for the stretched pixels of a 4:3 CRT, which no
+    // conversion here assumes.
+    let aspect = match aspect {
+        AspectRatio::Unset => 0b00,
+        AspectRatio::Square => 0b10,
+    };
+    u8::from(non_blink) | spacing << 1 | aspect << 3
+}
+
+/// Most lines a COMNT block can hold, as its count is a single byte.
+pub const MAX_COMMENTS: usize = 255;
+
+/// A clap parser for a text field of at most `max` printable ASCII characters,
+/// which CP437 shares.
+pub fn field(max: usize) -> impl Fn(&str) -> Result<String, String> + Clone {
+    move |value: &str| {
+        if !value.bytes().all(|byte| (0x20..0x7f).contains(&byte)) {
+            Err("only printable ASCII characters are allowed".to_string())
+        } else if value.len() > max {
+            Err(format!("at most {max} characters are allowed"))
+        } else {
+            Ok(value.to_string())
+        }
+    }
+}
+
+/// Parses a date given as YYYYMMDD or YYYY-MM-DD into SAUCE's CCYYMMDD.
+pub fn parse_date(value: &str) -> Result<String, String> {
+    let digits: String = value.chars().filter(|c| *c != '-').collect();
+    let valid = digits.len() == 8
+        && digits.bytes().all(|byte| byte.is_ascii_digit())
+        && (1..=12).contains(&digits[4..6].parse::<u8>().unwrap())
+        && (1..=31).contains(&digits[6..8].parse::<u8>().unwrap());
+    if valid {
+        Ok(digits)
+    } else {
+        Err(format!("{value:?} is not a YYYYMMDD or YYYY-MM-DD date"))
+    }
+}
+
+/// Today's UTC date as CCYYMMDD.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        / 86400;
+    // Hinnant's days-to-civil conversion, on eras of 400 years from 0000-03-01.
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}{month:02}{day:02}")
+}
+
+// Fields hold one char per byte, as `text` reads them, so CP437 bytes above
+// 0x7f written back come out as the single bytes they were read from.
+fn padded(value: &str, length: usize, pad: u8) -> Vec<u8> {
+    let mut bytes: Vec<u8> = value
+        .chars()
+        .map(|c| u8::try_from(c).unwrap_or(b'?'))
+        .take(length)
+        .collect();
+    bytes.resize(length, pad);
+    bytes
+}
+
+/// Appends an end-of-file marker, any comment block and the SAUCE record to
+/// the file at `path`.
+pub fn append(path: impl AsRef<Path>, sauce: &Sauce) {
+    let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+    let size = file.metadata().unwrap().len() as u32;
+    let mut record = Vec::with_capacity(134 + 64 * sauce.comments.len());
+    record.push(0x1a);
+    if !sauce.comments.is_empty() {
+        record.extend_from_slice(b"COMNT");
+        for comment in &sauce.comments {
+            record.extend(padded(comment, 64, b' '));
+        }
+    }
+    record.extend_from_slice(b"SAUCE00");
+    record.extend(padded(&sauce.title, 35, b' '));
+    record.extend(padded(&sauce.author, 20, b' '));
+    record.extend(padded(&sauce.group, 20, b' '));
+    record.extend(padded(&sauce.date, 8, b' '));
+    record.extend_from_slice(&size.to_le_bytes());
+    record.extend_from_slice(&[sauce.data_type, sauce.file_type]);
+    record.extend_from_slice(&sauce.width.to_le_bytes());
+    record.extend_from_slice(&sauce.height.to_le_bytes());
+    // TInfo3 and TInfo4, then the comment count and flags.
+    record.extend_from_slice(&[0; 4]);
+    record.extend_from_slice(&[sauce.comments.len() as u8, sauce.flags]);
+    record.extend(padded(
+        sauce.font_name.as_deref().unwrap_or_default(),
+        22,
+        0,
+    ));
+    file.write_all(&record).unwrap();
+}
+
+// Each byte as the char of the same value, with the padding trimmed.
+fn text(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .rposition(|byte| *byte != b' ' && *byte != 0)
+        .map_or(0, |i| i + 1);
+    bytes[..end].iter().map(|byte| *byte as char).collect()
+}
+
+/// Reads the SAUCE record, and any comment block before it, from the end of
+/// `data`.
+pub fn parse(data: &[u8]) -> Option<Sauce> {
+    let record = data.get(data.len().checked_sub(128)?..)?;
+    if !record.starts_with(b"SAUCE00") {
+        return None;
+    }
+    let u16_at = |offset: usize| u16::from_le_bytes([record[offset], record[offset + 1]]);
+    let count = record[104] as usize;
+    let block = data
+        .len()
+        .checked_sub(128 + 5 + 64 * count)
+        .map(|start| &data[start..data.len() - 128]);
+    let comments = match block {
+        Some(block) if count > 0 && block.starts_with(b"COMNT") => {
+            block[5..].chunks(64).map(text).collect()
+        }
+        _ => Vec::new(),
+    };
+    let font_name = text(&record[106..128]);
+    Some(Sauce {
+        title: text(&record[7..42]),
+        author: text(&record[42..62]),
+        group: text(&record[62..82]),
+        date: text(&record[82..90]),
+        data_type: record[94],
+        file_type: record[95],
+        width: u16_at(96),
+        height: u16_at(98),
+        font_name: (!font_name.is_empty()).then_some(font_name),
+        comments,
+        flags: record[105],
+    })
+}
+
+/// `data` without the SAUCE record, comment block and end-of-file marker
+/// `append` adds, for formats like BIN where 0x1a can be a character.
+pub fn strip(data: &[u8]) -> &[u8] {
+    let Some(sauce) = parse(data) else {
+        return data;
+    };
+    let block = if sauce.comments.is_empty() {
+        0
+    } else {
+        5 + 64 * sauce.comments.len()
+    };
+    let data = &data[..data.len() - 128 - block];
+    data.strip_suffix(&[0x1a]).unwrap_or(data)
+}
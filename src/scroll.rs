@@ -0,0 +1,84 @@
+use crate::ansi;
+use crate::chunks::Chunk;
+use crate::font::Font;
+use clap::ValueEnum;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Which way the art moves through the screen as it scrolls.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Direction {
+    /// From the top of the art, each new row entering at the bottom
+    Up,
+    /// From the bottom of the art, each new row entering at the top
+    Down,
+}
+
+/// Lines of the screen a scroll is shown on.
+pub const SCREEN_ROWS: usize = 25;
+
+/// Rows of art in view, leaving the last line of the screen clear so that
+/// drawing the bottom row to its end never scrolls it.
+pub const VIEW_ROWS: usize = SCREEN_ROWS - 1;
+
+impl Direction {
+    /// The rows first in view, then each row revealed in turn.
+    fn order(self, rows: usize) -> (std::ops::Range<usize>, Vec<usize>) {
+        let view = VIEW_ROWS.min(rows);
+        match self {
+            Direction::Up => (0..view, (view..rows).collect()),
+            Direction::Down => (rows - view..rows, (0..rows - view).rev().collect()),
+        }
+    }
+
+    /// The codes that scroll the screen a line and put the cursor at the
+    /// start of the row it reveals: a line feed on the clear last line, or
+    /// a line inserted at the top with the one pushed onto the last erased.
+    fn codes(self) -> String {
+        match self {
+            Direction::Up => format!("\x1b[{SCREEN_ROWS};1H\n\x1b[{VIEW_ROWS};1H"),
+            Direction::Down => format!("\x1b[1;1H\x1b[L\x1b[{SCREEN_ROWS};1H\x1b[K\x1b[1;1H"),
+        }
+    }
+}
+
+/// Writes an ANSImation scrolling through art taller than the screen: the
+/// first screenful is drawn, then each row after it only once the screen has
+/// scrolled to make room. Its speed is left to the viewer's or the modem's.
+pub fn save_ansi(
+    path: impl AsRef<Path>,
+    columns: usize,
+    font: &Font,
+    chunks: &[Chunk],
+    direction: Direction,
+) {
+    let rows: Vec<&[Chunk]> = chunks.chunks(columns).collect();
+    let (view, revealed) = direction.order(rows.len());
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    file.write_all(b"\x1b[2J\x1b[1;1H").unwrap();
+    ansi::write_ansi(
+        &mut file,
+        columns,
+        font,
+        &chunks[view.start * columns..view.end * columns],
+    );
+    for row in revealed {
+        file.write_all(direction.codes().as_bytes()).unwrap();
+        ansi::write_ansi(&mut file, columns, font, rows[row]);
+    }
+}
+
+/// The events of a recording scrolling through the rows of `lines` as
+/// `save_ansi` does, revealing `speed` rows a second.
+pub fn events(lines: &[String], direction: Direction, speed: f64) -> Vec<(Duration, String)> {
+    let (view, revealed) = direction.order(lines.len());
+    let first = format!("\x1b[2J\x1b[H{}", lines[view].join("\r\n"));
+    let tick = Duration::from_secs_f64(1.0 / speed);
+    std::iter::once((Duration::ZERO, first))
+        .chain(revealed.into_iter().enumerate().map(|(index, row)| {
+            let text = format!("{}{}", direction.codes(), lines[row]);
+            (tick * (index as u32 + 1), text)
+        }))
+        .collect()
+}
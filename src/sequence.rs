@@ -0,0 +1,65 @@
+use crate::cast::json_string;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// The span of a `%d` or zero-padded `%03d` placeholder in a file name, and
+// the digits it pads to.
+fn placeholder(name: &str) -> Option<(usize, usize, usize)> {
+    let start = name.find('%')?;
+    let rest = &name[start + 1..];
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if rest.as_bytes().get(digits) != Some(&b'd') {
+        return None;
+    }
+    let width = rest[..digits].parse().unwrap_or(0);
+    Some((start, start + 2 + digits, width))
+}
+
+/// Whether the path's file name is a template like `out_%03d.xb`, with a
+/// placeholder for the frame number.
+pub fn is_template(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| placeholder(&name.to_string_lossy()).is_some())
+}
+
+/// The path of frame `index` by the template, numbering from 1.
+pub fn frame_path(template: &Path, index: usize) -> PathBuf {
+    let name = template.file_name().unwrap().to_string_lossy();
+    let (start, end, width) = placeholder(&name).unwrap();
+    let name = format!("{}{:0width$}{}", &name[..start], index + 1, &name[end..]);
+    template.with_file_name(name)
+}
+
+/// The manifest beside the frames, named for the template without its
+/// placeholder, as `out.json` for `out_%03d.xb`.
+pub fn manifest_path(template: &Path) -> PathBuf {
+    let stem = template.file_stem().unwrap().to_string_lossy();
+    let (start, end, _) = placeholder(&stem).unwrap();
+    let name = format!("{}{}", &stem[..start], &stem[end..]);
+    let name = name.trim_matches(['_', '-', '.', ' ']);
+    let name = if name.is_empty() { "frames" } else { name };
+    template.with_file_name(format!("{name}.json"))
+}
+
+/// Writes a JSON manifest of the frames' files in order, with how many
+/// milliseconds each shows for.
+pub fn save_manifest(path: impl AsRef<Path>, frames: &[(PathBuf, Duration)]) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    writeln!(file, "{{").unwrap();
+    writeln!(file, "  \"frames\": [").unwrap();
+    for (index, (path, delay)) in frames.iter().enumerate() {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let separator = if index + 1 < frames.len() { "," } else { "" };
+        writeln!(
+            file,
+            "    {{\"file\": {}, \"delay\": {}}}{}",
+            json_string(&name),
+            delay.as_millis(),
+            separator
+        )
+        .unwrap();
+    }
+    writeln!(file, "  ]").unwrap();
+    writeln!(file, "}}").unwrap();
+}
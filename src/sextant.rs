@@ -0,0 +1,64 @@
+use crate::chunks::{best_block, distance_table, rgba_to_oklab, Chunk};
+use crate::font::Font;
+use crate::palette::Palettes;
+
+// Offsets within the 2x3 block from bit 5 (bottom-right) down to bit 0
+// (top-left), matching the numbering of the Unicode sextant names.
+const CELLS: [(usize, usize); 6] = [(1, 2), (0, 2), (1, 1), (0, 1), (1, 0), (0, 0)];
+
+/// The character for a cell's sextant mask. Unicode leaves out the patterns
+/// that already exist as space, the half blocks and the full block.
+pub fn to_char(mask: u8) -> char {
+    let code = match mask {
+        0 => 0x20,
+        21 => 0x258c,
+        42 => 0x2590,
+        63 => 0x2588,
+        _ => 0x1fb00 + mask as u32 - 1 - (mask > 21) as u32 - (mask > 42) as u32,
+    };
+    char::from_u32(code).expect("sextant")
+}
+
+/// An 8x16 font with each codepoint below 64 drawn as its sextant pattern,
+/// split at 4 pixels across and rows 5 and 11 down.
+pub fn font() -> Font {
+    let mut font = Font::ibm_vga_8x16();
+    let bands = [0..5, 5..11, 11..16];
+    for mask in 0..64 {
+        font.glyphs[mask * 16..mask * 16 + 16].fill(0);
+        for (bit, (x, y)) in CELLS.iter().rev().enumerate() {
+            if mask & (1 << bit) != 0 {
+                for row in bands[*y].clone() {
+                    font.glyphs[mask * 16 + row] |= 0xf0 >> (x * 4);
+                }
+            }
+        }
+    }
+    font
+}
+
+/// Encodes each 2x3 block of pixels as the sextant pattern and fg/bg pair
+/// with the least Oklab error, keeping the darker color in the background.
+pub fn encode(palettes: &Palettes, indexes: &[u8], width: usize, height: usize) -> Vec<Chunk> {
+    let distances = distance_table(palettes);
+    let lightness: Vec<f32> = palettes
+        .rgba
+        .iter()
+        .map(|color| rgba_to_oklab(color).l)
+        .collect();
+    let pixel = |x: usize, y: usize| indexes[y.min(height - 1) * width + x.min(width - 1)];
+    let (columns, rows) = (width.div_ceil(2), height.div_ceil(3));
+    let mut chunks = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let block = CELLS.map(|(x, y)| pixel(column * 2 + x, row * 3 + y));
+            let (bg, fg, mask) = best_block(&block, &distances, &lightness);
+            chunks.push(Chunk {
+                fg,
+                bg,
+                codepoint: mask,
+            });
+        }
+    }
+    chunks
+}
@@ -0,0 +1,128 @@
+use crate::charset::Charset;
+use crate::chunks::{rgba_to_oklab, source_pixels, Chunk};
+use crate::font::Font;
+use crate::glyph::distinct_glyphs;
+use crate::palette::Palettes;
+use image::DynamicImage;
+use oklab::{linear_srgb_to_oklab, oklab_to_linear_srgb, Oklab};
+
+const SPACE: u8 = 0x20;
+// Glyphs and the share of their pixels drawn in the foreground.
+pub const SHADES: [(u8, f32); 3] = [(0xb0, 0.25), (0xb1, 0.5), (0xb2, 0.75)];
+// Coverage steps the density library keeps one glyph for.
+const LEVELS: f32 = 32.0;
+
+fn distance(a: &Oklab, b: &Oklab) -> f32 {
+    (a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)
+}
+
+// Shades blend optically, so mixes are taken in linear light.
+fn mix(bg: &Oklab, fg: &Oklab, coverage: f32) -> Oklab {
+    let (bg, fg) = (oklab_to_linear_srgb(*bg), oklab_to_linear_srgb(*fg));
+    linear_srgb_to_oklab(oklab::RGB {
+        r: bg.r + (fg.r - bg.r) * coverage,
+        g: bg.g + (fg.g - bg.g) * coverage,
+        b: bg.b + (fg.b - bg.b) * coverage,
+    })
+}
+
+// How unevenly a glyph spreads its pixels, as the variance of coverage over a
+// 4x4 grid of regions. Even glyphs blend into a flat tone at a distance.
+fn unevenness(glyph: &[u8]) -> f32 {
+    let rows = glyph.len().div_ceil(4);
+    let regions: Vec<f32> = (0..16)
+        .map(|region| {
+            let (x, y) = (region % 4 * 2, region / 4 * rows);
+            let bits: usize = glyph
+                .iter()
+                .skip(y)
+                .take(rows)
+                .map(|row| ((row << x) >> 6).count_ones() as usize)
+                .sum();
+            bits as f32 / (2 * rows) as f32
+        })
+        .collect();
+    let mean = regions.iter().sum::<f32>() / 16.0;
+    regions.iter().map(|r| (r - mean).powi(2)).sum::<f32>() / 16.0
+}
+
+/// Glyphs of `font` sorted by coverage, keeping the most evenly spread glyph
+/// at each of 32 coverage steps and only glyphs in `charset` when one is given.
+pub fn density_library(font: &Font, charset: Option<&Charset>) -> Vec<(u8, f32)> {
+    let pixels = 8.0 * font.height as f32;
+    let mut levels: Vec<Option<(u8, f32, f32)>> = vec![None; LEVELS as usize + 1];
+    for codepoint in distinct_glyphs(font) {
+        if charset.is_some_and(|charset| !charset.contains(codepoint)) {
+            continue;
+        }
+        let glyph = font.glyph(codepoint);
+        let bits: u32 = glyph.iter().map(|row| row.count_ones()).sum();
+        let coverage = bits as f32 / pixels;
+        if coverage == 0.0 || coverage == 1.0 {
+            continue;
+        }
+        let score = unevenness(glyph);
+        let level = &mut levels[(coverage * LEVELS).round() as usize];
+        if level.is_none_or(|(_, _, best)| score < best) {
+            *level = Some((codepoint, coverage, score));
+        }
+    }
+    levels
+        .into_iter()
+        .flatten()
+        .map(|(codepoint, coverage, _)| (codepoint, coverage))
+        .collect()
+}
+
+/// Encodes each source pixel as one cell drawn with a space or one of the
+/// `shades`, glyphs paired with their coverage, whichever fg/bg blend lands
+/// nearest in Oklab.
+pub fn encode(palettes: &Palettes, image: &DynamicImage, shades: &[(u8, f32)]) -> Vec<Chunk> {
+    let ok_palette: Vec<Oklab> = palettes.rgba.iter().map(rgba_to_oklab).collect();
+    // Entries past the palette's count are padding, not colors in it.
+    let count = palettes.count as u8;
+    let mut candidates: Vec<(Chunk, Oklab)> = (0..count)
+        .map(|index| {
+            let chunk = Chunk {
+                fg: index,
+                bg: index,
+                codepoint: SPACE,
+            };
+            (chunk, ok_palette[index as usize])
+        })
+        .collect();
+    for bg in 0..count {
+        for fg in 0..count {
+            // The darker color always takes the background; the lighter
+            // combinations are the same blends with the shades reversed.
+            if fg == bg || ok_palette[fg as usize].l < ok_palette[bg as usize].l {
+                continue;
+            }
+            for (codepoint, coverage) in shades {
+                let color = mix(
+                    &ok_palette[bg as usize],
+                    &ok_palette[fg as usize],
+                    *coverage,
+                );
+                candidates.push((
+                    Chunk {
+                        fg,
+                        bg,
+                        codepoint: *codepoint,
+                    },
+                    color,
+                ));
+            }
+        }
+    }
+    source_pixels(image)
+        .iter()
+        .map(|pixel| {
+            let (chunk, _) = candidates
+                .iter()
+                .min_by(|(_, a), (_, b)| distance(a, pixel).total_cmp(&distance(b, pixel)))
+                .expect("candidates");
+            *chunk
+        })
+        .collect()
+}
@@ -0,0 +1,46 @@
+use crate::font::Font;
+use image::{imageops, Rgb, RgbImage};
+
+const COLUMNS: usize = 3;
+const GAP: u32 = 8;
+
+fn draw_label(image: &mut RgbImage, font: &Font, x: u32, y: u32, label: &str) {
+    let height = font.height as u32;
+    for (i, c) in label.bytes().enumerate() {
+        for (row, bits) in font.glyph(c).iter().enumerate() {
+            for column in 0..8 {
+                let (px, py) = (x + i as u32 * 8 + column, y + row as u32);
+                if bits & (0x80 >> column) != 0 && px < image.width() && py < y + height {
+                    image.put_pixel(px, py, Rgb([255, 255, 255]));
+                }
+            }
+        }
+    }
+}
+
+/// Lays the images out in a grid on black, each under its label drawn in
+/// `font`.
+pub fn contact_sheet(tiles: &[(String, RgbImage)], font: &Font) -> RgbImage {
+    let label_height = font.height as u32 + GAP / 2;
+    let tile_width = tiles
+        .iter()
+        .map(|(_, tile)| tile.width())
+        .max()
+        .unwrap_or(0);
+    let tile_height = tiles
+        .iter()
+        .map(|(_, tile)| tile.height())
+        .max()
+        .unwrap_or(0);
+    let (cell_width, cell_height) = (tile_width + GAP, tile_height + label_height + GAP);
+    let columns = tiles.len().clamp(1, COLUMNS) as u32;
+    let rows = tiles.len().div_ceil(COLUMNS) as u32;
+    let mut sheet = RgbImage::new(columns * cell_width + GAP, rows * cell_height + GAP);
+    for (i, (label, tile)) in tiles.iter().enumerate() {
+        let x = GAP + (i as u32 % columns) * cell_width;
+        let y = GAP + (i as u32 / columns) * cell_height;
+        draw_label(&mut sheet, font, x, y, label);
+        imageops::replace(&mut sheet, tile, x as i64, (y + label_height) as i64);
+    }
+    sheet
+}
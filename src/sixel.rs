@@ -0,0 +1,88 @@
+use image::RgbImage;
+use imagequant::RGBA;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Extensions written as DEC sixel graphics rather than XBIN.
+pub fn is_sixel_path(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("six") || extension.eq_ignore_ascii_case("sixel")
+    })
+}
+
+// Sixel colors are percentages.
+fn percent(value: u8) -> u32 {
+    (value as u32 * 100 + 127) / 255
+}
+
+fn write_run(file: &mut impl Write, sixel: u8, length: usize) {
+    let character = (0x3f + sixel) as char;
+    if length > 3 {
+        write!(file, "!{length}{character}").unwrap();
+    } else {
+        for _ in 0..length {
+            write!(file, "{character}").unwrap();
+        }
+    }
+}
+
+/// Writes a rendering of the cells as a sixel image, with `palette` as its
+/// color registers and pixels `pixel_height` times as tall as they are wide.
+pub fn save_sixel(
+    path: impl AsRef<Path>,
+    rendering: &RgbImage,
+    palette: &[RGBA],
+    pixel_height: f32,
+) {
+    let (width, height) = (rendering.width() as usize, rendering.height() as usize);
+    let indexes: Vec<usize> = rendering
+        .pixels()
+        .map(|pixel| {
+            palette
+                .iter()
+                .position(|color| [color.r, color.g, color.b] == pixel.0)
+                .expect("rendered colors come from the palette")
+        })
+        .collect();
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    write!(
+        file,
+        "\x1bPq\"{};1;{width};{height}",
+        pixel_height.round() as u32
+    )
+    .unwrap();
+    for (index, color) in palette.iter().enumerate() {
+        let (r, g, b) = (percent(color.r), percent(color.g), percent(color.b));
+        write!(file, "#{index};2;{r};{g};{b}").unwrap();
+    }
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        // The six-pixel column at x that shows `color`, as the bits of a sixel.
+        let sixel = |x: usize, color: usize| -> u8 {
+            (0..rows)
+                .filter(|row| indexes[(band + row) * width + x] == color)
+                .map(|row| 1 << row)
+                .sum()
+        };
+        let colors: Vec<usize> = (0..palette.len())
+            .filter(|color| indexes[band * width..(band + rows) * width].contains(color))
+            .collect();
+        for (i, color) in colors.iter().enumerate() {
+            write!(file, "#{color}").unwrap();
+            let mut x = 0;
+            while x < width {
+                let bits = sixel(x, *color);
+                let length = (x..width)
+                    .take_while(|other| sixel(*other, *color) == bits)
+                    .count();
+                write_run(&mut file, bits, length);
+                x += length;
+            }
+            if i + 1 < colors.len() {
+                write!(file, "$").unwrap();
+            }
+        }
+        write!(file, "-").unwrap();
+    }
+    write!(file, "\x1b\\").unwrap();
+}
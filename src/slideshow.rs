@@ -0,0 +1,72 @@
+use crate::chunks::Chunk;
+use clap::ValueEnum;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How one slide gives way to the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Transition {
+    /// Straight to the next slide
+    #[default]
+    Cut,
+    /// Cells change over to the next slide in a scattered order
+    Dissolve,
+    /// Columns change over to the next slide from left to right
+    Wipe,
+}
+
+/// Still images shown one after another in an ANSImation.
+#[derive(Clone, Debug)]
+pub struct Slideshow {
+    /// The slides after the first, which is the INPUT.
+    pub slides: Vec<PathBuf>,
+    pub duration: Duration,
+    pub transition: Transition,
+    pub transition_duration: Duration,
+}
+
+// Where in a dissolve, from 0 to 1, the cell at `index` changes over: a
+// hash of its index, so the order looks scattered but is always the same.
+fn dissolve_threshold(index: usize) -> f64 {
+    let mut hash = index as u32;
+    hash = (hash ^ 61) ^ (hash >> 16);
+    hash = hash.wrapping_mul(9);
+    hash ^= hash >> 4;
+    hash = hash.wrapping_mul(0x27d4eb2d);
+    hash ^= hash >> 15;
+    f64::from(hash) / f64::from(u32::MAX)
+}
+
+/// The cells `progress` of the way, from 0 to 1, through the transition
+/// between two slides of the same size.
+pub fn between(
+    transition: Transition,
+    columns: usize,
+    from: &[Chunk],
+    to: &[Chunk],
+    progress: f64,
+) -> Vec<Chunk> {
+    from.iter()
+        .zip(to)
+        .enumerate()
+        .map(|(index, (from, to))| {
+            let changed = match transition {
+                Transition::Cut => true,
+                Transition::Dissolve => dissolve_threshold(index) < progress,
+                Transition::Wipe => ((index % columns) as f64) < progress * columns as f64,
+            };
+            if changed {
+                *to
+            } else {
+                *from
+            }
+        })
+        .collect()
+}
+
+/// The cells of a slide made `rows` tall, blank below its own.
+pub fn padded(chunks: &[Chunk], columns: usize, rows: usize) -> Vec<Chunk> {
+    let mut chunks = chunks.to_vec();
+    chunks.resize(columns * rows, Chunk::solid(0));
+    chunks
+}
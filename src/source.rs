@@ -0,0 +1,78 @@
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// A language to embed a file's bytes in as source code.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Language {
+    Rust,
+    C,
+    Asm,
+}
+
+const PER_LINE: usize = 16;
+
+/// The language of a source path's extension, if it's one.
+pub fn language(path: &Path) -> Option<Language> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "rs" => Some(Language::Rust),
+        "c" | "h" => Some(Language::C),
+        "asm" | "inc" => Some(Language::Asm),
+        _ => None,
+    }
+}
+
+// An identifier from the file name, in the case the language expects of
+// constant data.
+fn identifier(path: &Path, language: Language) -> String {
+    let stem = path.file_stem().unwrap().to_string_lossy();
+    let mut name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    match language {
+        Language::Rust => name.to_ascii_uppercase(),
+        Language::C | Language::Asm => name.to_ascii_lowercase(),
+    }
+}
+
+/// Writes `bytes` as an array named after the file: a Rust static, a C
+/// array, or an assembler label over `db` lines with its length as an
+/// `equ`.
+pub fn save_source(path: impl AsRef<Path>, language: Language, bytes: &[u8]) {
+    let path = path.as_ref();
+    let name = identifier(path, language);
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    let lines = bytes.chunks(PER_LINE).map(|line| {
+        line.iter()
+            .map(|byte| format!("0x{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    });
+    match language {
+        Language::Rust => {
+            writeln!(file, "pub static {name}: [u8; {}] = [", bytes.len()).unwrap();
+            for line in lines {
+                writeln!(file, "    {line},").unwrap();
+            }
+            writeln!(file, "];").unwrap();
+        }
+        Language::C => {
+            writeln!(file, "const unsigned char {name}[{}] = {{", bytes.len()).unwrap();
+            for line in lines {
+                writeln!(file, "    {line},").unwrap();
+            }
+            writeln!(file, "}};").unwrap();
+        }
+        Language::Asm => {
+            writeln!(file, "{name}:").unwrap();
+            for line in lines {
+                writeln!(file, "    db {line}").unwrap();
+            }
+            writeln!(file, "{name}_len equ $ - {name}").unwrap();
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::chunks::Chunk;
+use crate::palette::nearest_spectrum;
+use imagequant::RGBA;
+use std::path::Path;
+
+/// The Spectrum's screen in pixel mode cells: 32 bytes across and 192 pixel
+/// rows, with an attribute for every 8 of them.
+pub const COLUMNS: usize = 32;
+pub const ROWS: usize = 192;
+pub const ATTRIBUTE_ROWS: usize = 8;
+
+/// Extensions written as Spectrum screens rather than XBIN.
+pub fn is_scr_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("scr"))
+}
+
+// The offset of a pixel row's bytes, which the ULA interleaves by thirds of
+// the screen, then character rows, then pixel rows within them.
+fn bitmap_offset(row: usize) -> usize {
+    (row & 0xc0) << 5 | (row & 0x07) << 8 | (row & 0x38) << 2
+}
+
+/// Writes pixel mode cells as a 6912-byte screen dump: the bitmap, then an
+/// attribute of ink, paper and brightness for each 8x8 block. Cells beyond
+/// the screen are cut and the rest of it is left black. Blocks are expected
+/// to show two colors at most, as `chunks::clash` leaves them; any others
+/// are drawn in ink.
+pub fn save_scr(path: impl AsRef<Path>, columns: usize, palette: &[RGBA], chunks: &[Chunk]) {
+    let mut screen = vec![0u8; COLUMNS * ROWS + COLUMNS * ROWS / ATTRIBUTE_ROWS];
+    let rows = chunks.len() / columns.max(1);
+    let colors: Vec<u8> = palette.iter().map(nearest_spectrum).collect();
+    for block_row in (0..rows.min(ROWS)).step_by(ATTRIBUTE_ROWS) {
+        for column in 0..columns.min(COLUMNS) {
+            let block: Vec<&Chunk> = (block_row..(block_row + ATTRIBUTE_ROWS).min(rows))
+                .map(|row| &chunks[row * columns + column])
+                .collect();
+            let paper = block[0].bg;
+            let ink = block
+                .iter()
+                .flat_map(|chunk| (0..8).map(|pixel| chunk.index_at(pixel)))
+                .find(|index| *index != paper)
+                .unwrap_or(paper);
+            for (row, chunk) in (block_row..).zip(block.iter()) {
+                let byte = (0..8).fold(0, |byte, pixel| {
+                    byte << 1 | u8::from(chunk.index_at(pixel) != paper)
+                });
+                screen[bitmap_offset(row) + column] = byte;
+            }
+            let (ink, paper) = (colors[ink as usize], colors[paper as usize]);
+            let bright = ink >= 8 || paper >= 8;
+            screen[COLUMNS * ROWS + block_row / ATTRIBUTE_ROWS * COLUMNS + column] =
+                u8::from(bright) << 6 | (paper & 7) << 3 | ink & 7;
+        }
+    }
+    std::fs::write(path, screen).unwrap();
+}
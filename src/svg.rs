@@ -0,0 +1,45 @@
+use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Extensions written as SVG rather than XBIN.
+pub fn is_svg_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"))
+}
+
+/// Writes a rendering of the cells as SVG, with one path per color made of
+/// the runs of that color along each pixel row. Pixels are drawn
+/// `pixel_height` times as tall as they are wide.
+pub fn save_svg(path: impl AsRef<Path>, rendering: &RgbImage, pixel_height: f32) {
+    let mut paths: Vec<(Rgb<u8>, String)> = Vec::new();
+    let mut indexes: HashMap<Rgb<u8>, usize> = HashMap::new();
+    for y in 0..rendering.height() {
+        let mut x = 0;
+        while x < rendering.width() {
+            let color = *rendering.get_pixel(x, y);
+            let length = (x..rendering.width())
+                .take_while(|other| *rendering.get_pixel(*other, y) == color)
+                .count();
+            let index = *indexes.entry(color).or_insert_with(|| {
+                paths.push((color, String::new()));
+                paths.len() - 1
+            });
+            paths[index].1 += &format!("M{x} {y}h{length}v1h-{length}z");
+            x += length as u32;
+        }
+    }
+    let (width, height) = (rendering.width(), rendering.height());
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    writeln!(
+        file,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{}\" viewBox=\"0 0 {width} {height}\" preserveAspectRatio=\"none\" shape-rendering=\"crispEdges\">",
+        height as f32 * pixel_height
+    )
+    .unwrap();
+    for (Rgb([r, g, b]), data) in &paths {
+        writeln!(file, "<path fill=\"#{r:02x}{g:02x}{b:02x}\" d=\"{data}\"/>").unwrap();
+    }
+    writeln!(file, "</svg>").unwrap();
+}
@@ -0,0 +1,60 @@
+use crate::ansi::Colors;
+
+/// What the terminal running the command can show.
+pub struct Capabilities {
+    pub colors: Colors,
+    pub utf8: bool,
+    pub columns: usize,
+}
+
+// Terminals advertise 24-bit color with COLORTERM and 256 colors in TERM.
+fn colors() -> Colors {
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    let term = std::env::var("TERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        Colors::Truecolor
+    } else if term.contains("256color") {
+        Colors::Xterm256
+    } else {
+        Colors::Ansi16
+    }
+}
+
+// The first of the locale variables that is set decides the encoding.
+fn utf8() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+// The width from COLUMNS, else from asking stty about the controlling
+// terminal, else 80.
+fn columns() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .or_else(|| {
+            let tty = std::fs::File::open("/dev/tty").ok()?;
+            let output = std::process::Command::new("stty")
+                .arg("size")
+                .stdin(tty)
+                .output()
+                .ok()?;
+            let size = String::from_utf8(output.stdout).ok()?;
+            size.split_whitespace().nth(1)?.parse().ok()
+        })
+        .filter(|columns| *columns > 0)
+        .unwrap_or(80)
+}
+
+pub fn detect() -> Capabilities {
+    Capabilities {
+        colors: colors(),
+        utf8: utf8(),
+        columns: columns(),
+    }
+}
@@ -0,0 +1,43 @@
+use crate::chunks::Chunk;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Extensions written as plain text rather than XBIN.
+pub fn is_text_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ["txt", "nfo", "asc", "diz"].contains(&extension.to_ascii_lowercase().as_str())
+        })
+}
+
+/// Writes the cell characters alone, one row per line with trailing spaces
+/// trimmed and each line ended with `line_ending`: CRLF as NFO readers expect,
+/// or LF for Amiga viewers.
+pub fn save_text(path: impl AsRef<Path>, columns: usize, chunks: &[Chunk], line_ending: &[u8]) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    for row in chunks.chunks(columns.max(1)) {
+        let line: Vec<u8> = row.iter().map(|chunk| chunk.codepoint).collect();
+        let end = line.iter().rposition(|c| *c != b' ').map_or(0, |i| i + 1);
+        file.write_all(&line[..end]).unwrap();
+        file.write_all(line_ending).unwrap();
+    }
+}
+
+/// Writes the cell characters as `save_text` does, but as the Unicode
+/// characters `to_char` gives encoded in UTF-8.
+pub fn save_utf8_text(
+    path: impl AsRef<Path>,
+    columns: usize,
+    chunks: &[Chunk],
+    to_char: fn(u8) -> char,
+    line_ending: &[u8],
+) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    for row in chunks.chunks(columns.max(1)) {
+        let line: String = row.iter().map(|chunk| to_char(chunk.codepoint)).collect();
+        file.write_all(line.trim_end_matches(' ').as_bytes())
+            .unwrap();
+        file.write_all(line_ending).unwrap();
+    }
+}
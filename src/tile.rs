@@ -0,0 +1,113 @@
+use crate::cast::json_string;
+use crate::chunks::Chunk;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Largest width or height an XBIN header can hold.
+pub const MAX_CELLS: usize = u16::MAX as usize;
+
+/// The most columns and rows of cells one output file may hold.
+#[derive(Clone, Copy, Debug)]
+pub struct Size {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl Size {
+    /// Parses COLUMNSxROWS, where either side may be left empty for no limit.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (columns, rows) = value
+            .split_once(['x', 'X'])
+            .ok_or(format!("{value:?} is not COLUMNSxROWS"))?;
+        let side = |side: &str| -> Result<usize, String> {
+            if side.is_empty() {
+                return Ok(MAX_CELLS);
+            }
+            match side.parse::<usize>() {
+                Ok(cells) if (1..=MAX_CELLS).contains(&cells) => Ok(cells),
+                _ => Err(format!("{side:?} is not a size from 1 to {MAX_CELLS}")),
+            }
+        };
+        Ok(Size {
+            columns: side(columns)?,
+            rows: side(rows)?,
+        })
+    }
+}
+
+/// One piece of the cell grid, by its first cell and size.
+pub struct Tile {
+    pub column: usize,
+    pub row: usize,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+/// Splits a grid into tiles no larger than `size`, row by row.
+pub fn split(columns: usize, rows: usize, size: Size) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for row in (0..rows).step_by(size.rows) {
+        for column in (0..columns).step_by(size.columns) {
+            tiles.push(Tile {
+                column,
+                row,
+                columns: size.columns.min(columns - column),
+                rows: size.rows.min(rows - row),
+            });
+        }
+    }
+    tiles
+}
+
+/// The cells of `tile` out of a grid `columns` wide.
+pub fn cells(chunks: &[Chunk], columns: usize, tile: &Tile) -> Vec<Chunk> {
+    (tile.row..tile.row + tile.rows)
+        .flat_map(|row| {
+            let start = row * columns + tile.column;
+            chunks[start..start + tile.columns].iter().copied()
+        })
+        .collect()
+}
+
+/// The numbered path of tile `index`, as `name-01.xb` beside `path`.
+pub fn tile_path(path: &Path, index: usize, count: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap().to_string_lossy();
+    let digits = count.to_string().len().max(2);
+    let mut name = format!("{stem}-{:0digits$}", index + 1);
+    if let Some(extension) = path.extension() {
+        name = format!("{name}.{}", extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// Writes a JSON manifest of where each tile's file sits in the whole grid.
+pub fn save_manifest(
+    path: impl AsRef<Path>,
+    columns: usize,
+    rows: usize,
+    tiles: &[Tile],
+    paths: &[PathBuf],
+) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    writeln!(file, "{{").unwrap();
+    writeln!(file, "  \"columns\": {columns},").unwrap();
+    writeln!(file, "  \"rows\": {rows},").unwrap();
+    writeln!(file, "  \"tiles\": [").unwrap();
+    for (index, (tile, path)) in tiles.iter().zip(paths.iter()).enumerate() {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let separator = if index + 1 < tiles.len() { "," } else { "" };
+        writeln!(
+            file,
+            "    {{\"file\": {}, \"column\": {}, \"row\": {}, \"columns\": {}, \"rows\": {}}}{}",
+            json_string(&name),
+            tile.column,
+            tile.row,
+            tile.columns,
+            tile.rows,
+            separator
+        )
+        .unwrap();
+    }
+    writeln!(file, "  ]").unwrap();
+    writeln!(file, "}}").unwrap();
+}
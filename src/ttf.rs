@@ -0,0 +1,258 @@
+use crate::font::Font;
+
+// Font units to a font pixel, so every edge lands on a whole pixel at the
+// font's own height in CSS pixels.
+const UNITS: i16 = 64;
+
+const NAME: &str = "img2xbin";
+
+fn u16s(values: &[u16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+fn checksum(table: &[u8]) -> u32 {
+    table.chunks(4).fold(0u32, |sum, word| {
+        let mut bytes = [0; 4];
+        bytes[..word.len()].copy_from_slice(word);
+        sum.wrapping_add(u32::from_be_bytes(bytes))
+    })
+}
+
+// A glyph's set pixels as one square contour for each run of them in a row,
+// with its bounds, or None for a blank glyph.
+struct Outline {
+    contours: Vec<[(i16, i16); 4]>,
+    bounds: [i16; 4],
+}
+
+fn outline(glyph: &[u8]) -> Option<Outline> {
+    let height = glyph.len() as i16;
+    let mut contours = Vec::new();
+    for (row, bits) in glyph.iter().enumerate() {
+        let top = (height - row as i16) * UNITS;
+        let bottom = top - UNITS;
+        let mut column = 0;
+        while column < 8 {
+            if bits & (0x80 >> column) == 0 {
+                column += 1;
+                continue;
+            }
+            let start = column;
+            while column < 8 && bits & (0x80 >> column) != 0 {
+                column += 1;
+            }
+            let (left, right) = (start as i16 * UNITS, column as i16 * UNITS);
+            // Clockwise, as TrueType fills outer contours.
+            contours.push([(left, top), (right, top), (right, bottom), (left, bottom)]);
+        }
+    }
+    let points = || contours.iter().flatten();
+    let bounds = [
+        points().map(|(x, _)| *x).min()?,
+        points().map(|(_, y)| *y).min()?,
+        points().map(|(x, _)| *x).max()?,
+        points().map(|(_, y)| *y).max()?,
+    ];
+    Some(Outline { contours, bounds })
+}
+
+fn glyf(outline: &Outline) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend((outline.contours.len() as i16).to_be_bytes());
+    for bound in outline.bounds {
+        data.extend(bound.to_be_bytes());
+    }
+    for index in 0..outline.contours.len() {
+        data.extend((index as u16 * 4 + 3).to_be_bytes());
+    }
+    // No instructions, then every point on the curve, with coordinates as
+    // full 16-bit deltas.
+    data.extend([0, 0]);
+    data.extend(std::iter::repeat_n(0x01, outline.contours.len() * 4));
+    for axis in [0, 1] {
+        let mut last = 0i16;
+        for point in outline.contours.iter().flatten() {
+            let value = if axis == 0 { point.0 } else { point.1 };
+            data.extend((value - last).to_be_bytes());
+            last = value;
+        }
+    }
+    data
+}
+
+// A format 4 character map of one segment per character, each to its own
+// glyph.
+fn cmap(mapping: &[(u16, u16)]) -> Vec<u8> {
+    let mut segments: Vec<(u16, u16)> = mapping.to_vec();
+    segments.push((0xffff, 0));
+    let count = segments.len() as u16;
+    let search_range = 2 * (1 << count.ilog2());
+    let mut subtable = u16s(&[
+        4,
+        16 + 8 * count,
+        0,
+        count * 2,
+        search_range,
+        count.ilog2() as u16,
+        count * 2 - search_range,
+    ]);
+    subtable.extend(u16s(&segments.iter().map(|(c, _)| *c).collect::<Vec<_>>()));
+    subtable.extend(u16s(&[0]));
+    subtable.extend(u16s(&segments.iter().map(|(c, _)| *c).collect::<Vec<_>>()));
+    let deltas: Vec<u16> = segments
+        .iter()
+        .map(|(c, glyph)| {
+            if *c == 0xffff {
+                1
+            } else {
+                glyph.wrapping_sub(*c)
+            }
+        })
+        .collect();
+    subtable.extend(u16s(&deltas));
+    subtable.extend(u16s(&vec![0; segments.len()]));
+    let mut table = u16s(&[0, 1, 3, 1]);
+    table.extend(12u32.to_be_bytes());
+    table.extend(subtable);
+    table
+}
+
+fn name() -> Vec<u8> {
+    let records = [(1, NAME), (2, "Regular"), (3, NAME), (4, NAME), (6, NAME)];
+    let strings: Vec<Vec<u8>> = records
+        .iter()
+        .map(|(_, text)| u16s(&text.encode_utf16().collect::<Vec<_>>()))
+        .collect();
+    let mut table = u16s(&[0, records.len() as u16, 6 + 12 * records.len() as u16]);
+    let mut offset = 0;
+    for ((id, _), string) in records.iter().zip(&strings) {
+        table.extend(u16s(&[3, 1, 0x409, *id, string.len() as u16, offset]));
+        offset += string.len() as u16;
+    }
+    table.extend(strings.concat());
+    table
+}
+
+/// The first bank of the font as a TrueType font of square pixels, one em
+/// the height of a cell and every glyph the width of one. Each codepoint's
+/// glyph is mapped from the character `to_char` gives it.
+pub fn ttf(font: &Font, to_char: impl Fn(u8) -> char) -> Vec<u8> {
+    let em = font.height as i16 * UNITS;
+    let advance = 8 * UNITS;
+    let outlines: Vec<Option<Outline>> = (0..=255u8)
+        .map(|codepoint| outline(font.glyph(codepoint)))
+        .collect();
+    // Glyph 0 is the blank .notdef, so codepoints follow one after.
+    let mut mapping: Vec<(u16, u16)> = Vec::new();
+    for codepoint in 0..=255u8 {
+        if let Ok(character) = u16::try_from(to_char(codepoint) as u32) {
+            if character != 0xffff && !mapping.iter().any(|(c, _)| *c == character) {
+                mapping.push((character, codepoint as u16 + 1));
+            }
+        }
+    }
+    mapping.sort_unstable();
+    let glyph_count = outlines.len() as u16 + 1;
+
+    let mut glyf_table = Vec::new();
+    let mut loca = vec![0u32, 0];
+    let mut hmtx = u16s(&[advance as u16, 0]);
+    for outline in &outlines {
+        if let Some(outline) = outline {
+            glyf_table.extend(glyf(outline));
+            glyf_table.resize(glyf_table.len().next_multiple_of(4), 0);
+        }
+        loca.push(glyf_table.len() as u32);
+        let left = outline.as_ref().map_or(0, |outline| outline.bounds[0]);
+        hmtx.extend(u16s(&[advance as u16, left as u16]));
+    }
+    let loca: Vec<u8> = loca
+        .iter()
+        .flat_map(|offset| offset.to_be_bytes())
+        .collect();
+    let points = outlines
+        .iter()
+        .flatten()
+        .map(|outline| outline.contours.len() * 4)
+        .max()
+        .unwrap_or(0) as u16;
+
+    let mut head = u16s(&[1, 0, 1, 0]);
+    head.extend([0; 4]);
+    head.extend(0x5f0f3cf5u32.to_be_bytes());
+    head.extend(u16s(&[0x000b, em as u16]));
+    head.extend([0; 16]);
+    head.extend(u16s(&[0, 0, advance as u16, em as u16, 0, 8, 2, 1, 0]));
+    let mut hhea = u16s(&[1, 0, em as u16, 0, 0, advance as u16, 0, 0]);
+    hhea.extend(u16s(&[advance as u16, 1, 0, 0, 0, 0, 0, 0, 0, glyph_count]));
+    let maxp = u16s(&[
+        1,
+        0,
+        glyph_count,
+        points,
+        points / 4,
+        0,
+        0,
+        2,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ]);
+    let (first, last) = (mapping[0].0, mapping[mapping.len() - 1].0);
+    let mut os2 = u16s(&[4, advance as u16, 400, 5, 0]);
+    os2.extend([0; 20 + 2 + 10 + 16]);
+    os2.extend(b"NONE");
+    os2.extend(u16s(&[0x00c0, first, last, em as u16, 0, 0, em as u16, 0]));
+    os2.extend(1u32.to_be_bytes());
+    os2.extend([0; 4]);
+    os2.extend(u16s(&[0, 0, 0, 0x20, 0]));
+    let mut post = u16s(&[3, 0, 0, 0]);
+    post.extend(u16s(&[(-UNITS) as u16, UNITS as u16]));
+    post.extend(1u32.to_be_bytes());
+    post.extend([0; 16]);
+
+    // Tables in the order of their tags, as the directory lists them.
+    let tables: [(&[u8; 4], Vec<u8>); 10] = [
+        (b"OS/2", os2),
+        (b"cmap", cmap(&mapping)),
+        (b"glyf", glyf_table),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"hmtx", hmtx),
+        (b"loca", loca),
+        (b"maxp", maxp),
+        (b"name", name()),
+        (b"post", post),
+    ];
+    let count = tables.len() as u16;
+    let search_range = 16 * (1 << count.ilog2());
+    let mut data = u16s(&[1, 0, count, search_range, count.ilog2() as u16]);
+    data.extend(u16s(&[count * 16 - search_range]));
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        data.extend(*tag);
+        data.extend(checksum(table).to_be_bytes());
+        data.extend((offset as u32).to_be_bytes());
+        data.extend((table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    let mut head_offset = 0;
+    for (tag, table) in &tables {
+        if *tag == b"head" {
+            head_offset = data.len();
+        }
+        data.extend(table);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    let adjustment = 0xb1b0afbau32.wrapping_sub(checksum(&data));
+    data[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    data
+}
@@ -0,0 +1,61 @@
+use crate::chunks::Chunk;
+use imagequant::RGBA;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// TundraDraw screens are always 80 columns wide.
+pub const COLUMNS: usize = 80;
+
+const FOREGROUND: u8 = 2;
+const BACKGROUND: u8 = 4;
+const BOTH: u8 = 6;
+
+/// Extensions written as TundraDraw rather than XBIN.
+pub fn is_tundra_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("tnd"))
+}
+
+fn color(color: &RGBA) -> [u8; 4] {
+    [0, color.r, color.g, color.b]
+}
+
+/// Writes a TundraDraw file, giving each cell the 24-bit colors of its
+/// `palette` entries. Rows narrower than 80 columns are padded with blanks
+/// and wider ones cut short.
+pub fn save_tundra(path: impl AsRef<Path>, columns: usize, palette: &[RGBA], chunks: &[Chunk]) {
+    let mut file = BufWriter::new(std::fs::File::create(path).unwrap());
+    file.write_all(b"\x18TUNDRA24").unwrap();
+    let blank = Chunk {
+        fg: 0,
+        bg: 0,
+        codepoint: 0x20,
+    };
+    let mut current = None;
+    for row in chunks.chunks(columns.max(1)) {
+        let mut row = row.to_vec();
+        row.resize(COLUMNS, blank);
+        for chunk in row {
+            let fg = palette[chunk.fg as usize];
+            let bg = palette[chunk.bg as usize];
+            let fg_changed = current.is_none_or(|(old, _)| old != fg);
+            let bg_changed = current.is_none_or(|(_, old)| old != bg);
+            // The codes of the commands are only read as characters after one.
+            let command = matches!(chunk.codepoint, 1 | FOREGROUND | BACKGROUND | BOTH);
+            if fg_changed && bg_changed {
+                file.write_all(&[BOTH, chunk.codepoint]).unwrap();
+                file.write_all(&color(&fg)).unwrap();
+                file.write_all(&color(&bg)).unwrap();
+            } else if bg_changed {
+                file.write_all(&[BACKGROUND, chunk.codepoint]).unwrap();
+                file.write_all(&color(&bg)).unwrap();
+            } else if fg_changed || command {
+                file.write_all(&[FOREGROUND, chunk.codepoint]).unwrap();
+                file.write_all(&color(&fg)).unwrap();
+            } else {
+                file.write_all(&[chunk.codepoint]).unwrap();
+            }
+            current = Some((fg, bg));
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::chunks::{best_block, distance_table, rgba_to_oklab, Chunk};
+use crate::font::Font;
+use crate::palette::Palettes;
+
+/// An 8x8 font where each codepoint draws its own bits as a 4x2 grid of
+/// 2x4-pixel blocks: the high nibble across the top half and the low nibble
+/// across the bottom, extending the 1-pixel font trick to two rows per cell.
+pub fn font() -> Font {
+    let mut glyphs = vec![0; 256 * 8];
+    for codepoint in 0..256 {
+        let widen = |nibble: usize| -> u8 {
+            (0..4)
+                .filter(|bit| nibble & (8 >> bit) != 0)
+                .map(|bit| 0xc0u8 >> (bit * 2))
+                .sum()
+        };
+        let glyph = &mut glyphs[codepoint * 8..codepoint * 8 + 8];
+        glyph[..4].fill(widen(codepoint >> 4));
+        glyph[4..].fill(widen(codepoint & 0x0f));
+    }
+    Font { height: 8, glyphs }
+}
+
+/// Encodes each 4x2 block of pixels as the fg/bg pair with the least Oklab
+/// error, the block's pattern being the codepoint itself.
+pub fn encode(palettes: &Palettes, indexes: &[u8], width: usize, height: usize) -> Vec<Chunk> {
+    let distances = distance_table(palettes);
+    let lightness: Vec<f32> = palettes
+        .rgba
+        .iter()
+        .map(|color| rgba_to_oklab(color).l)
+        .collect();
+    let pixel = |x: usize, y: usize| indexes[y.min(height - 1) * width + x.min(width - 1)];
+    let (columns, rows) = (width.div_ceil(4), height.div_ceil(2));
+    let mut chunks = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let block: [u8; 8] =
+                std::array::from_fn(|i| pixel(column * 4 + i % 4, row * 2 + i / 4));
+            let (bg, fg, mask) = best_block(&block, &distances, &lightness);
+            chunks.push(Chunk {
+                fg,
+                bg,
+                codepoint: mask,
+            });
+        }
+    }
+    chunks
+}
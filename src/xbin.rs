@@ -0,0 +1,351 @@
+use crate::chunks::Chunk;
+use crate::font::Font;
+use imagequant::RGBA;
+use std::path::Path;
+
+fn palette_to_bytes(palette: &[RGBA]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for color in palette {
+        bytes.push(color.r);
+        bytes.push(color.g);
+        bytes.push(color.b);
+    }
+    bytes
+}
+
+/// Cells as the character/attribute byte pairs of textmode memory.
+pub fn chunks_to_bytes(chunks: &[Chunk]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for chunk in chunks {
+        bytes.push(chunk.codepoint);
+        bytes.push((chunk.bg << 4) + chunk.fg);
+    }
+    bytes
+}
+
+// The header can't describe a ragged last row, a font outside XBIN's 1-32
+// rows, or glyph data that isn't exactly one or two banks.
+fn validate(columns: usize, font: &Font, chunks: &[Chunk]) {
+    assert!(
+        columns > 0 && chunks.len().is_multiple_of(columns),
+        "{} cells don't fill rows of {columns} columns",
+        chunks.len()
+    );
+    assert!(
+        columns <= u16::MAX as usize && chunks.len() / columns <= u16::MAX as usize,
+        "{columns}x{} cells is too large for an XBIN",
+        chunks.len() / columns
+    );
+    assert!(
+        (1..=32).contains(&font.height),
+        "XBIN fonts are 1 to 32 rows high, not {}",
+        font.height
+    );
+    assert!(
+        font.glyphs.len() == 256 * font.height as usize
+            || font.glyphs.len() == 512 * font.height as usize,
+        "{} bytes of glyph data isn't 256 or 512 glyphs of {} rows",
+        font.glyphs.len(),
+        font.height
+    );
+}
+
+// Run types in the top two bits of a compressed run's count byte.
+const RAW: u8 = 0x00;
+const SAME_CHAR: u8 = 0x40;
+const SAME_ATTR: u8 = 0x80;
+const SAME_BOTH: u8 = 0xc0;
+const MAX_RUN: usize = 64;
+
+// The fewest bytes a row can be coded in, found from its end back: each cell
+// starts whichever kind and length of run, followed by the best coding of
+// the cells after it, comes to the fewest bytes.
+fn compress_row(row: &[[u8; 2]], bytes: &mut Vec<u8>) {
+    let mut costs = vec![0; row.len() + 1];
+    let mut runs = vec![(RAW, 0); row.len()];
+    for i in (0..row.len()).rev() {
+        let (mut chars, mut attrs) = (true, true);
+        let mut best = (usize::MAX, RAW, 0);
+        for count in 1..=MAX_RUN.min(row.len() - i) {
+            let cell = row[i + count - 1];
+            chars &= cell[0] == row[i][0];
+            attrs &= cell[1] == row[i][1];
+            let mut consider = |kind: u8, size: usize| {
+                if size + costs[i + count] < best.0 {
+                    best = (size + costs[i + count], kind, count);
+                }
+            };
+            if chars && attrs {
+                consider(SAME_BOTH, 3);
+            }
+            if chars {
+                consider(SAME_CHAR, 2 + count);
+            }
+            if attrs {
+                consider(SAME_ATTR, 2 + count);
+            }
+            consider(RAW, 1 + 2 * count);
+        }
+        costs[i] = best.0;
+        runs[i] = (best.1, best.2);
+    }
+    let mut i = 0;
+    while i < row.len() {
+        let (kind, count) = runs[i];
+        let cells = &row[i..i + count];
+        bytes.push(kind | (count - 1) as u8);
+        match kind {
+            RAW => bytes.extend(cells.iter().flatten()),
+            SAME_CHAR => {
+                bytes.push(cells[0][0]);
+                bytes.extend(cells.iter().map(|cell| cell[1]));
+            }
+            SAME_ATTR => {
+                bytes.push(cells[0][1]);
+                bytes.extend(cells.iter().map(|cell| cell[0]));
+            }
+            _ => bytes.extend_from_slice(&cells[0]),
+        }
+        i += count;
+    }
+}
+
+// XBIN's run-length scheme, in runs that never cross the end of a row.
+fn compress(chunk_bytes: &[u8], columns: usize) -> Vec<u8> {
+    let cells: Vec<[u8; 2]> = chunk_bytes
+        .chunks_exact(2)
+        .map(|cell| [cell[0], cell[1]])
+        .collect();
+    let mut bytes = Vec::new();
+    for row in cells.chunks(columns) {
+        compress_row(row, &mut bytes);
+    }
+    bytes
+}
+
+/// Which optional parts of the format an XBIN is written with.
+pub struct Features {
+    /// Embed the palette rather than leave viewers to use the VGA default.
+    pub embed_palette: bool,
+    /// Embed the font rather than leave viewers to use their own 8x16 font.
+    pub embed_font: bool,
+    /// Run-length compress the cells, when that makes them smaller.
+    pub compress: bool,
+    /// Mark the attributes' high bit as a bright background (iCE colors)
+    /// rather than blinking.
+    pub non_blink: bool,
+}
+
+/// An XBIN with its palette, font and compression as chosen by `features`.
+/// The header's font height is taken from `font`, so rows of cells are
+/// `font.height` pixels.
+pub fn xbin_bytes(
+    columns: usize,
+    font: &Font,
+    features: &Features,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+) -> Vec<u8> {
+    validate(columns, font, chunks);
+    let palette_bytes = palette_to_bytes(palette);
+    let mut chunk_bytes = chunks_to_bytes(chunks);
+    // Noisy art can come out of run-length coding larger than it went in.
+    let mut compressed = false;
+    if features.compress {
+        let runs = compress(&chunk_bytes, columns);
+        if runs.len() < chunk_bytes.len() {
+            (chunk_bytes, compressed) = (runs, true);
+        }
+    }
+    let rows = chunks.len() / columns;
+    let mut bytes = b"XBIN\x1a".to_vec();
+    bytes.extend_from_slice(&(columns as u16).to_le_bytes());
+    bytes.extend_from_slice(&(rows as u16).to_le_bytes());
+    // The palette, font, compression, non-blink and 512-character flags as
+    // used.
+    let mut flags = 0x00;
+    if features.embed_palette {
+        flags |= 0x01;
+    }
+    if features.embed_font {
+        flags |= 0x02;
+    }
+    if compressed {
+        flags |= 0x04;
+    }
+    if features.non_blink {
+        flags |= 0x08;
+    }
+    if font.glyph_count() > 256 {
+        flags |= 0x10;
+    }
+    bytes.extend_from_slice(&[font.height, flags]);
+    if features.embed_palette {
+        bytes.extend_from_slice(&palette_bytes);
+    }
+    if features.embed_font {
+        bytes.extend_from_slice(&font.glyphs);
+    }
+    bytes.extend_from_slice(&chunk_bytes);
+    bytes
+}
+
+/// Writes the XBIN `xbin_bytes` makes of the cells.
+pub fn save_xbin(
+    path: impl AsRef<Path>,
+    columns: usize,
+    font: &Font,
+    features: &Features,
+    palette: &[RGBA],
+    chunks: &[Chunk],
+) {
+    let bytes = xbin_bytes(columns, font, features, palette, chunks);
+    std::fs::write(path, bytes).unwrap();
+}
+
+/// An XBIN read back, with the palette and font it leaves to viewers filled
+/// in as the VGA's.
+pub struct Xbin {
+    pub columns: usize,
+    pub font: Font,
+    /// The palette scaled up from the 6-bit entries XBIN stores.
+    pub palette: Vec<RGBA>,
+    pub chunks: Vec<Chunk>,
+    /// Which optional parts of the format the file was written with.
+    pub features: Features,
+    /// Bytes the cells take up in the file, compressed or not.
+    pub cell_bytes: usize,
+}
+
+impl Xbin {
+    /// The cells as viewers show them, outside of blinking backgrounds'
+    /// blinks.
+    pub fn shown(&self) -> Vec<Chunk> {
+        self.chunks
+            .iter()
+            .map(|chunk| Chunk {
+                bg: if self.features.non_blink {
+                    chunk.bg
+                } else {
+                    chunk.bg & 0x07
+                },
+                ..*chunk
+            })
+            .collect()
+    }
+}
+
+// Undoes `compress`, whose runs decoders shouldn't assume end with rows,
+// with how many bytes the runs took.
+fn decompress(bytes: &[u8], cells: usize) -> Result<(Vec<u8>, usize), String> {
+    let truncated = || "the compressed cells end early".to_string();
+    let mut chunk_bytes = Vec::with_capacity(cells * 2);
+    let mut i = 0;
+    while chunk_bytes.len() < cells * 2 {
+        let control = *bytes.get(i).ok_or_else(truncated)?;
+        let count = (control & 0x3f) as usize + 1;
+        i += 1;
+        match control & 0xc0 {
+            RAW => {
+                let run = bytes.get(i..i + count * 2).ok_or_else(truncated)?;
+                chunk_bytes.extend_from_slice(run);
+                i += count * 2;
+            }
+            SAME_CHAR | SAME_ATTR => {
+                let run = bytes.get(i..i + 1 + count).ok_or_else(truncated)?;
+                for varying in &run[1..] {
+                    if control & 0xc0 == SAME_CHAR {
+                        chunk_bytes.extend_from_slice(&[run[0], *varying]);
+                    } else {
+                        chunk_bytes.extend_from_slice(&[*varying, run[0]]);
+                    }
+                }
+                i += 1 + count;
+            }
+            _ => {
+                let cell = bytes.get(i..i + 2).ok_or_else(truncated)?;
+                for _ in 0..count {
+                    chunk_bytes.extend_from_slice(cell);
+                }
+                i += 2;
+            }
+        }
+    }
+    chunk_bytes.truncate(cells * 2);
+    Ok((chunk_bytes, i))
+}
+
+/// Reads an XBIN: its header, any palette and font, and its cells,
+/// decompressing them if need be. Anything after them, such as SAUCE, is
+/// ignored.
+pub fn parse(bytes: &[u8]) -> Result<Xbin, String> {
+    if !bytes.starts_with(b"XBIN\x1a") || bytes.len() < 11 {
+        return Err("not an XBIN".to_string());
+    }
+    let columns = u16::from_le_bytes([bytes[5], bytes[6]]) as usize;
+    let rows = u16::from_le_bytes([bytes[7], bytes[8]]) as usize;
+    let (height, flags) = (bytes[9], bytes[10]);
+    let features = Features {
+        embed_palette: flags & 0x01 != 0,
+        embed_font: flags & 0x02 != 0,
+        compress: flags & 0x04 != 0,
+        non_blink: flags & 0x08 != 0,
+    };
+    let glyph_count = if flags & 0x10 != 0 { 512 } else { 256 };
+    let mut rest = &bytes[11..];
+    let mut take = |length: usize, part: &str| {
+        let taken = rest
+            .get(..length)
+            .ok_or_else(|| format!("the file ends in its {part}"))?;
+        rest = &rest[length..];
+        Ok::<&[u8], String>(taken)
+    };
+    let palette = if features.embed_palette {
+        take(48, "palette")?
+            .chunks_exact(3)
+            .map(|color| RGBA {
+                r: (color[0] & 0x3f) * 4,
+                g: (color[1] & 0x3f) * 4,
+                b: (color[2] & 0x3f) * 4,
+                a: 255,
+            })
+            .collect()
+    } else {
+        crate::palette::ANSI.to_vec()
+    };
+    let font = if features.embed_font {
+        let glyphs = take(glyph_count * height as usize, "font")?.to_vec();
+        Font { height, glyphs }
+    } else {
+        match height {
+            16 => Font::ibm_vga_8x16(),
+            14 => Font::ibm_vga_8x14(),
+            8 => Font::ibm_vga_8x8(),
+            _ => return Err(format!("no VGA font is {height} rows high")),
+        }
+    };
+    let (chunk_bytes, cell_bytes) = if features.compress {
+        decompress(rest, columns * rows)?
+    } else {
+        (
+            take(columns * rows * 2, "cells")?.to_vec(),
+            columns * rows * 2,
+        )
+    };
+    let chunks = chunk_bytes
+        .chunks_exact(2)
+        .map(|cell| Chunk {
+            codepoint: cell[0],
+            fg: cell[1] & 0x0f,
+            bg: cell[1] >> 4,
+        })
+        .collect();
+    Ok(Xbin {
+        columns,
+        font,
+        palette,
+        chunks,
+        features,
+        cell_bytes,
+    })
+}
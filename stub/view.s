@@ -0,0 +1,142 @@
+# A DOS .COM viewer for the screen appended after it, built with binutils:
+#
+#   as --32 -o view.o view.s
+#   ld -m elf_i386 -Ttext 0x100 --oformat binary -o view.com view.o
+#
+# The header at the end, left zeroed here, is filled in when a file is
+# written with everything the viewer needs, followed by the font and cells,
+# so the code only loads and copies.
+
+        .code16
+        .intel_syntax noprefix
+        .text
+        .globl _start
+_start:
+        mov ax, 0x0003                  # 80x25 text mode
+        int 0x10
+
+        mov ax, 0x1110                  # load and activate font block 0
+        mov bh, [height]
+        xor bl, bl
+        mov cx, 256
+        xor dx, dx
+        mov bp, [font]
+        int 0x10
+        cmp byte ptr [banks], 2
+        jb 1f
+        mov ax, 0x1110                  # font block 1 for bit 3 of attributes
+        mov bh, [height]
+        mov bl, 1
+        mov cx, 256
+        xor dx, dx
+        mov bp, [font2]
+        int 0x10
+        mov ax, 0x1103                  # map A to block 1, map B to block 0
+        mov bl, 0x04
+        int 0x10
+1:
+        mov ax, 0x1002                  # attribute controller registers
+        mov dx, offset registers
+        int 0x10
+        mov ax, 0x1012                  # DAC colors for entries 0-15
+        xor bx, bx
+        mov cx, 16
+        mov dx, offset palette
+        int 0x10
+        mov ax, 0x1003                  # bright backgrounds instead of blink
+        xor bl, bl
+        int 0x10
+        mov ah, 0x01                    # hide the cursor
+        mov cx, 0x2000
+        int 0x10
+
+        mov dx, 0x3c4                   # 8-pixel characters at 640 pixels
+        mov ax, 0x0100
+        out dx, ax
+        mov ax, 0x0101
+        out dx, ax
+        mov dx, 0x3cc
+        in al, dx
+        and al, 0xf3
+        mov dl, 0xc2
+        out dx, al
+        mov dx, 0x3c4
+        mov ax, 0x0300
+        out dx, ax
+        mov dx, 0x3da                   # no panning for the missing 9th pixel
+        in al, dx
+        mov dl, 0xc0
+        mov al, 0x33
+        out dx, al
+        xor al, al
+        out dx, al
+
+        mov dx, 0x3ce                   # map all 64K of text memory at A000,
+        mov ax, 0x0606                  # enough for 400 rows of 1-pixel cells
+        out dx, ax
+        mov ax, 0xa000
+        mov es, ax
+        xor di, di
+        xor ax, ax
+        mov cx, 0x8000
+        rep stosw
+
+draw:
+        mov ax, [top]
+        mul word ptr [stride]
+        add ax, [cells]
+        mov si, ax
+        xor di, di
+        mov bx, [shown_rows]
+2:
+        push si
+        push di
+        mov cx, [shown_columns]
+        rep movsw
+        pop di
+        pop si
+        add di, 160
+        add si, [stride]
+        dec bx
+        jnz 2b
+
+        xor ah, ah                      # up and down scroll, other keys quit
+        int 0x16
+        mov cl, ah
+        mov bx, [step]
+        mov ax, [top]
+        cmp cl, 0x48
+        je up
+        cmp cl, 0x50
+        je down
+        mov ax, 0x0003
+        int 0x10
+        ret
+up:
+        sub ax, bx
+        jnc store
+        xor ax, ax
+        jmp store
+down:
+        add ax, bx
+        cmp ax, [max_top]
+        jbe store
+        mov ax, [max_top]
+store:
+        mov [top], ax
+        jmp draw
+
+top:    .word 0
+header:
+height: .byte 0
+banks:  .byte 0
+step:   .word 0
+stride: .word 0
+shown_columns: .word 0
+shown_rows: .word 0
+max_top: .word 0
+font:   .word 0
+font2:  .word 0
+cells:  .word 0
+registers: .fill 17
+palette: .fill 48